@@ -142,6 +142,12 @@ pub struct PermissionedBuilderOptions {
     #[clap(long, env = "ESPRESSO_SEQUENCER_STATE_PEERS", value_delimiter = ',')]
     pub state_peers: Vec<Url>,
 
+    /// Maximum number of requests to send to state peers at a time.
+    ///
+    /// If not set, there is no limit.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_STATE_PEERS_MAX_REQUESTS")]
+    pub state_peers_max_requests: Option<usize>,
+
     /// Port to run the builder server on.
     #[clap(short, long, env = "ESPRESSO_BUILDER_SERVER_PORT")]
     pub port: u16,
@@ -183,6 +189,16 @@ pub struct PermissionedBuilderOptions {
     /// Whether or not we are a DA node.
     #[clap(long, env = "ESPRESSO_SEQUENCER_IS_DA", action)]
     pub is_da: bool,
+
+    /// Maximum time to spend adding transactions to a block.
+    ///
+    /// Once this much time has passed, the block is built from the transactions added so far.
+    #[clap(
+        long,
+        env = "ESPRESSO_BUILDER_BLOCK_BUILDING_DEADLINE",
+        value_parser = parse_duration
+    )]
+    pub block_building_deadline: Option<Duration>,
 }
 
 #[derive(Clone, Debug, Snafu)]
@@ -262,6 +278,7 @@ async fn main() -> anyhow::Result<()> {
         private_staking_key: private_staking_key.clone(),
         private_state_key,
         state_peers: opt.state_peers,
+        state_peers_max_requests: opt.state_peers_max_requests,
     };
 
     let sequencer_version = SEQUENCER_VERSION;
@@ -292,6 +309,7 @@ async fn main() -> anyhow::Result<()> {
         buffer_view_num_count,
         opt.is_da,
         txn_timeout_duration,
+        opt.block_building_deadline,
     )
     .await?;
 
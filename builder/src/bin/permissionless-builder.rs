@@ -1,5 +1,8 @@
 use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
-use builder::non_permissioned::{build_instance_state, BuilderConfig};
+use builder::{
+    log_truncated_transactions,
+    non_permissioned::{build_instance_state, BuilderConfig},
+};
 use clap::Parser;
 use cld::ClDuration;
 use es_version::SEQUENCER_VERSION;
@@ -79,6 +82,16 @@ struct NonPermissionedBuilderOptions {
     /// Path to TOML file containing genesis state.
     #[clap(long, name = "GENESIS_FILE", env = "ESPRESSO_BUILDER_GENESIS_FILE")]
     genesis_file: PathBuf,
+
+    /// Maximum time to spend adding transactions to a block.
+    ///
+    /// Once this much time has passed, the block is built from the transactions added so far.
+    #[clap(
+        long,
+        env = "ESPRESSO_BUILDER_BLOCK_BUILDING_DEADLINE",
+        value_parser = parse_duration
+    )]
+    block_building_deadline: Option<Duration>,
 }
 
 #[derive(Clone, Debug, Snafu)]
@@ -114,13 +127,18 @@ async fn main() -> anyhow::Result<()> {
 
     let builder_server_url: Url = format!("http://0.0.0.0:{}", opt.port).parse().unwrap();
 
-    let instance_state = build_instance_state(
+    let mut instance_state = build_instance_state(
         genesis.chain_config,
         l1_params,
         opt.state_peers,
         sequencer_version,
     )
-    .unwrap();
+    .unwrap()
+    .with_truncation_events(log_truncated_transactions())
+    .with_fee_policy(genesis.fee_policy);
+    if let Some(deadline) = opt.block_building_deadline {
+        instance_state = instance_state.with_block_building_deadline(deadline);
+    }
 
     let api_response_timeout_duration = opt.max_api_timeout_duration;
 
@@ -5,6 +5,7 @@ use ethers::{
     types::{Address, U256},
 };
 use futures::{
+    channel::mpsc,
     future::{join_all, Future},
     stream::{Stream, StreamExt},
 };
@@ -54,7 +55,7 @@ use sequencer::{
     state::FeeAccount,
     state::ValidatedState,
     state_signature::{static_stake_table_commitment, StateSigner},
-    L1Params, NetworkParams, Node, NodeState, PrivKey, PubKey, SeqTypes,
+    L1Params, NetworkParams, Node, NodeState, PrivKey, PubKey, SeqTypes, TruncatedTransactions,
 };
 use std::{alloc::System, any, fmt::Debug, mem};
 use std::{marker::PhantomData, net::IpAddr};
@@ -94,6 +95,27 @@ pub fn run_builder_api_service(url: Url, source: ProxyGlobalState<SeqTypes>) {
     async_spawn(app.serve(url, STATIC_VER_0_1));
 }
 
+/// Log the transactions left out of blocks built by this builder because they did not fit.
+///
+/// Returns a channel to install with
+/// [`NodeState::with_truncation_events`](sequencer::NodeState::with_truncation_events). For each
+/// truncated block, the commitments of the first transactions left out are logged along with the
+/// height of the block being built.
+pub fn log_truncated_transactions() -> mpsc::UnboundedSender<TruncatedTransactions> {
+    let (sender, mut receiver) = mpsc::unbounded::<TruncatedTransactions>();
+    async_spawn(async move {
+        while let Some(event) = receiver.next().await {
+            tracing::info!(
+                height = event.height,
+                dropped = ?event.dropped,
+                more_dropped = event.more_dropped,
+                "transactions left out of block"
+            );
+        }
+    });
+    sender
+}
+
 #[cfg(test)]
 pub mod testing {
     use super::*;
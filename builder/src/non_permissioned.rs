@@ -157,8 +157,8 @@ impl BuilderConfig {
             node_count,
             maximize_txns_count_timeout_duration,
             instance_state
-                .chain_config
-                .base_fee
+                .fee_policy
+                .builder_fee_per_byte(&instance_state.chain_config)
                 .as_u64()
                 .context("the base fee exceeds the maximum amount that a builder can pay (defined by u64::MAX)")?,
             Arc::new(instance_state),
@@ -94,7 +94,7 @@ use hotshot_types::{
     },
 };
 
-use crate::run_builder_api_service;
+use crate::{log_truncated_transactions, run_builder_api_service};
 use hotshot_events_service::{
     events::{Error as EventStreamApiError, Options as EventStreamingApiOptions},
     events_source::{BuilderEvent, EventConsumer, EventsStreamer},
@@ -142,6 +142,7 @@ pub async fn init_node<P: SequencerPersistence, Ver: StaticVersionType + 'static
     buffered_view_num_count: usize,
     is_da: bool,
     maximize_txns_count_timeout_duration: Duration,
+    block_building_deadline: Option<Duration>,
 ) -> anyhow::Result<BuilderContext<network::Production, P, Ver>> {
     // Orchestrator client
     let validator_args = ValidatorArgs {
@@ -259,6 +260,7 @@ pub async fn init_node<P: SequencerPersistence, Ver: StaticVersionType + 'static
     // crash horribly just because we're not using the P2P network yet.
     let _ = NetworkingMetricsValue::new(metrics);
 
+    let tx_verifier = genesis.tx_verifier();
     let mut genesis_state = ValidatedState::default();
     for (address, amount) in genesis.accounts {
         tracing::warn!(%address, %amount, "Prefunding account for demo");
@@ -280,7 +282,22 @@ pub async fn init_node<P: SequencerPersistence, Ver: StaticVersionType + 'static
         genesis_header: genesis.header,
         genesis_state: genesis_state.clone(),
         l1_genesis,
-        peers: Arc::new(StatePeers::<Ver>::from_urls(network_params.state_peers)),
+        tx_ordering: genesis
+            .tx_ordering
+            .into_iter()
+            .map(|o| (o.namespace, o.ordering))
+            .collect(),
+        max_transaction_size: genesis.max_transaction_size,
+        min_fee: genesis.min_fee,
+        truncation_events: Some(log_truncated_transactions()),
+        tx_verifier,
+        block_building_deadline,
+        ns_table_layout: genesis.ns_table_layout,
+        fee_policy: Arc::new(genesis.fee_policy),
+        peers: Arc::new(
+            StatePeers::<Ver>::from_urls(network_params.state_peers)
+                .with_max_in_flight_requests(network_params.state_peers_max_requests),
+        ),
         node_id: node_index,
     };
 
@@ -472,8 +489,8 @@ impl<N: network::Type, P: SequencerPersistence, Ver: StaticVersionType + 'static
             NonZeroUsize::new(1).unwrap(),
             maximize_txns_count_timeout_duration,
             instance_state
-                .chain_config
-                .base_fee
+                .fee_policy
+                .builder_fee_per_byte(&instance_state.chain_config)
                 .as_u64()
                 .context("the base fee exceeds the maximum amount that a builder can pay (defined by u64::MAX)")?,
             Arc::new(instance_state),
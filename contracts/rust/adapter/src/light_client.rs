@@ -8,9 +8,10 @@ use ethers::{
     types::U256,
 };
 use hotshot_types::light_client::{CircuitField, LightClientState, PublicInput};
+use serde::{Deserialize, Serialize};
 
 /// Intermediate representations for `LightClientState` in Solidity
-#[derive(Clone, Debug, EthAbiType, EthAbiCodec, PartialEq)]
+#[derive(Clone, Debug, EthAbiType, EthAbiCodec, PartialEq, Serialize, Deserialize)]
 pub struct ParsedLightClientState {
     pub view_num: u64,
     pub block_height: u64,
@@ -0,0 +1,5 @@
+ALTER TABLE header
+ADD column builder_commitment text
+GENERATED ALWAYS AS (data->>'builder_commitment') STORED NOT NULL;
+
+CREATE INDEX header_builder_commitment_idx ON header (builder_commitment);
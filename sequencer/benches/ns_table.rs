@@ -0,0 +1,40 @@
+//! Namespace lookup in flat and sorted namespace tables.
+
+use async_std::task::block_on;
+use criterion::{criterion_group, criterion_main, Criterion};
+use hotshot::traits::BlockPayload;
+use sequencer::{NamespaceId, NodeState, NsTableLayout, Payload, Transaction};
+use std::hint::black_box;
+
+const NUM_NAMESPACES: u32 = 500;
+
+fn payload(layout: NsTableLayout) -> Payload {
+    // Insert namespaces in descending order, so the flat table is not sorted by accident.
+    let txs = (0..NUM_NAMESPACES)
+        .rev()
+        .map(|ns| Transaction::new(NamespaceId::from(ns), vec![1, 2, 3, 4]));
+    let instance_state = NodeState::default().with_ns_table_layout(layout);
+    block_on(Payload::from_transactions(txs, &Default::default(), &instance_state))
+        .unwrap()
+        .0
+}
+
+fn find_ns_id(c: &mut Criterion) {
+    let mut group = c.benchmark_group("find_ns_id");
+    for layout in [NsTableLayout::Flat, NsTableLayout::Sorted] {
+        let payload = payload(layout);
+        let ns_table = payload.ns_table();
+        assert_eq!(ns_table.layout(), layout);
+        group.bench_function(format!("{layout:?}/{NUM_NAMESPACES}"), |b| {
+            b.iter(|| {
+                for ns in 0..NUM_NAMESPACES {
+                    black_box(ns_table.find_ns_id(&NamespaceId::from(ns)).unwrap());
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, find_ns_id);
+criterion_main!(benches);
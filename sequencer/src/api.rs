@@ -1,55 +1,182 @@
-use self::data_source::{HotShotConfigDataSource, PublicHotShotConfig, StateSignatureDataSource};
+use self::data_source::{
+    ConsensusDataSource, HotShotConfigDataSource, PublicHotShotConfig, StateSignatureDataSource,
+};
+use self::{endpoints::CatchupProgress, options::CatchupSource};
 use crate::{
+    catchup::StateCatchup,
+    chain_config::ResolvableChainConfig,
     network,
     persistence::SequencerPersistence,
-    state::{BlockMerkleTree, FeeAccountProof},
-    state_signature::StateSigner,
-    Node, NodeState, PubKey, SeqTypes, SequencerContext, Transaction,
+    state::{BlockMerkleCommitment, BlockMerkleTree, FeeAccountProof, FeeMerkleCommitment},
+    state_signature::{StateSignatureBundle, StateSigner},
+    ChainConfig, NamespaceId, Node, NodeState, PubKey, SeqTypes, SequencerContext, Transaction,
+    TransactionSubmission,
 };
-use anyhow::Context;
+use anyhow::{bail, ensure, Context};
 use async_once_cell::Lazy;
-use async_std::sync::{Arc, RwLock};
+use async_std::{
+    future::timeout,
+    sync::{Arc, RwLock},
+    task::sleep,
+};
 use async_trait::async_trait;
+use committable::{Commitment, Committable};
 use data_source::{CatchupDataSource, SubmitDataSource};
 use derivative::Derivative;
 use ethers::prelude::{Address, U256};
 use futures::{
-    future::{BoxFuture, Future, FutureExt},
-    stream::{BoxStream, Stream},
+    future::{self, BoxFuture, Future, FutureExt},
+    stream::{self, BoxStream, Stream, StreamExt},
 };
-use hotshot::types::{Event, SystemContextHandle};
+use hotshot::types::{Event, EventType, SystemContextHandle};
+use hotshot_contract_adapter::light_client::ParsedLightClientState;
 use hotshot_events_service::events_source::{BuilderEvent, EventsSource, EventsStreamer};
-use hotshot_query_service::data_source::ExtensibleDataSource;
+use hotshot_query_service::{
+    availability::{AvailabilityDataSource, LeafQueryData},
+    data_source::ExtensibleDataSource,
+    node::NodeDataSource,
+};
 use hotshot_state_prover::service::light_client_genesis_from_stake_table;
-use hotshot_types::{data::ViewNumber, light_client::StateSignatureRequestBody, HotShotConfig};
-use jf_merkle_tree::MerkleTreeScheme;
+use hotshot_types::{
+    data::ViewNumber, light_client::StateSignatureRequestBody,
+    traits::node_implementation::ConsensusTime, HotShotConfig,
+};
+use jf_merkle_tree::{ForgetableMerkleTreeScheme, MerkleCommitment, MerkleTreeScheme};
 use serde::{Deserialize, Serialize};
-use std::pin::Pin;
+use snafu::Snafu;
+use std::{
+    collections::{HashMap, VecDeque},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+use time::OffsetDateTime;
 use vbs::version::StaticVersionType;
 
+pub mod archive;
+mod body_limit;
 pub mod data_source;
 pub mod endpoints;
+mod event_replay;
 pub mod fs;
+mod listener;
 pub mod options;
+pub mod sink;
 pub mod sql;
+mod startup_gate;
 mod update;
 
 pub use options::Options;
 
+/// The version of the proof format in catchup responses requested by this node.
+///
+/// The format of a catchup response is selected by the route it is requested from, rather than
+/// described in the response, so that the response types keep the same encoding in every format.
+/// Format `n` is served at `catchup/format/n/...`, and a server which does not support the
+/// requested format fails the request rather than responding in another format. Format 0, the
+/// original format, is also served at the unversioned routes, which older servers serve
+/// exclusively.
+pub const CATCHUP_FORMAT_VERSION: u32 = 0;
+
+/// The path of the catchup endpoint `route` serving proofs in format [`CATCHUP_FORMAT_VERSION`].
+pub(crate) fn catchup_path(route: &str) -> String {
+    match CATCHUP_FORMAT_VERSION {
+        // Request format 0 from the unversioned route, so that we can catch up from older servers.
+        0 => format!("catchup/{route}"),
+        version => format!("catchup/format/{version}/{route}"),
+    }
+}
+
+/// Header marking a catchup request as made by a node's [`StatePeers`](crate::catchup::StatePeers).
+///
+/// A node serving a request with this header does not forward it to its own peers, even if
+/// [`CatchupSource::Peers`] is configured, so a request is forwarded at most once.
+pub(crate) const CATCHUP_NO_FORWARD_HEADER: &str = "Espresso-Catchup-No-Forward";
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct AccountQueryData {
     pub balance: U256,
     pub proof: FeeAccountProof,
 }
 
+impl AccountQueryData {
+    pub fn new(balance: U256, proof: FeeAccountProof) -> Self {
+        Self { balance, proof }
+    }
+
+    /// Verify the proof against the fee Merkle root `comm`, returning the proven balance.
+    pub fn verify(&self, comm: &FeeMerkleCommitment) -> anyhow::Result<U256> {
+        self.proof.verify(comm)
+    }
+}
+
 impl From<(FeeAccountProof, U256)> for AccountQueryData {
     fn from((proof, balance): (FeeAccountProof, U256)) -> Self {
-        Self { balance, proof }
+        Self::new(balance, proof)
+    }
+}
+
+/// The state of an account in the most recent state available to a node.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LatestAccountQueryData {
+    /// The height of the block whose state the account was read from.
+    pub height: u64,
+    /// The view of the block whose state the account was read from.
+    pub view: ViewNumber,
+    /// The account balance and proof, relative to the fee state root at `height` and `view`.
+    pub account: AccountQueryData,
+}
+
+/// The state of an account in the most recent state signed by a node.
+///
+/// This state may be stale, for example if consensus has stalled, but it is still verifiable: the
+/// account proof is relative to the fee state root in the signed header, and `signature` can be
+/// verified against the stake table it contains.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SignedAccountQueryData {
+    /// The height of the block whose state the account was read from.
+    ///
+    /// The account is up to date as of this height, and may be stale if later blocks have been
+    /// decided.
+    pub height: u64,
+    /// The account balance and proof, relative to the fee state root at `height`.
+    pub account: AccountQueryData,
+    /// A signature of the light client state at `height`, along with the header at `height`.
+    pub signature: StateSignatureBundle,
+}
+
+impl SignedAccountQueryData {
+    /// Verify the state signature and the account proof, returning the proven balance.
+    pub fn verify(&self) -> anyhow::Result<U256> {
+        ensure!(
+            self.signature.header.height == self.height,
+            "signed header {} does not match height {}",
+            self.signature.header.height,
+            self.height
+        );
+        self.signature.verify()?;
+        self.account
+            .verify(&self.signature.header.fee_merkle_tree_root)
     }
 }
 
 pub type BlocksFrontier = <BlockMerkleTree as MerkleTreeScheme>::MembershipProof;
 
+/// A proof that a block header is included in the block Merkle tree.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BlockMerkleProofQueryData {
+    /// The height of the header whose `block_merkle_tree_root` the proof is relative to.
+    ///
+    /// The block Merkle tree committed to by the header at this height contains all blocks before
+    /// it.
+    pub root_height: u64,
+    /// Proof that the commitment of the requested header is in the tree, at its block height.
+    pub proof: <BlockMerkleTree as MerkleTreeScheme>::MembershipProof,
+}
+
 type BoxLazy<T> = Pin<Arc<Lazy<T, BoxFuture<'static, T>>>>;
 
 #[derive(Derivative)]
@@ -58,6 +185,8 @@ struct ConsensusState<N: network::Type, P: SequencerPersistence, Ver: StaticVers
     state_signer: Arc<StateSigner<Ver>>,
     event_streamer: Arc<RwLock<EventsStreamer<SeqTypes>>>,
     node_state: NodeState,
+    light_client_genesis: ParsedLightClientState,
+    shutting_down: Arc<AtomicBool>,
 
     #[derivative(Debug = "ignore")]
     handle: Arc<RwLock<SystemContextHandle<SeqTypes, Node<N, P>>>>,
@@ -71,6 +200,8 @@ impl<N: network::Type, P: SequencerPersistence, Ver: StaticVersionType + 'static
             state_signer: ctx.state_signer(),
             event_streamer: ctx.event_streamer(),
             node_state: ctx.node_state(),
+            light_client_genesis: ctx.light_client_genesis(),
+            shutting_down: ctx.shutting_down_flag(),
             handle: ctx.consensus(),
         }
     }
@@ -85,6 +216,311 @@ struct ApiState<N: network::Type, P: SequencerPersistence, Ver: StaticVersionTyp
     // without waiting.
     #[derivative(Debug = "ignore")]
     consensus: BoxLazy<ConsensusState<N, P, Ver>>,
+
+    // Maximum time to wait for each source when serving catchup requests.
+    catchup_timeout: Option<Duration>,
+
+    // Sources to consult, in order, when serving catchup requests.
+    catchup_sources: Vec<CatchupSource>,
+
+    // Maximum total time to spend retrying transaction submissions which fail transiently.
+    submit_retry_timeout: Option<Duration>,
+
+    // The most recently rejected transaction submissions, if enabled.
+    rejections: Option<Arc<RwLock<RejectionLog>>>,
+
+    // Limit on the rate of transaction submissions to each namespace, if enabled.
+    namespace_rate_limiter: Option<Arc<NamespaceRateLimiter>>,
+
+    // Maximum size of a submission request body, if different from the default.
+    max_submit_body_size: Option<u64>,
+
+    // Progress catching up to the chain tip after startup, shared by all clones of the state.
+    startup_catchup: Arc<StartupCatchup>,
+
+    // Idempotency keys of recent transaction submissions, shared by all clones of the state.
+    idempotency_keys: Arc<RwLock<IdempotencyKeys>>,
+}
+
+/// A transaction submission which was rejected by this node.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RejectedTransaction {
+    pub hash: Commitment<Transaction>,
+    pub namespace: NamespaceId,
+    pub reason: String,
+    /// Unix timestamp of the rejection, in seconds.
+    pub timestamp: u64,
+}
+
+/// A bounded log of recently rejected transaction submissions, oldest first.
+#[derive(Debug)]
+struct RejectionLog {
+    capacity: usize,
+    entries: VecDeque<RejectedTransaction>,
+}
+
+impl RejectionLog {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    fn push(&mut self, rejection: RejectedTransaction) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(rejection);
+    }
+}
+
+/// Recent transaction submissions, indexed by namespace and idempotency key.
+///
+/// Keys are scoped to the namespace of the transaction submitted with them, so clients of one
+/// namespace cannot interfere with those of another. Once full, the oldest key is forgotten to make
+/// room for each new one.
+#[derive(Debug)]
+struct IdempotencyKeys {
+    capacity: usize,
+    submissions: HashMap<(NamespaceId, String), IdempotentSubmission>,
+    // Keys in `submissions`, oldest first.
+    order: VecDeque<(NamespaceId, String)>,
+}
+
+/// A transaction submitted with an idempotency key.
+#[derive(Clone, Debug)]
+struct IdempotentSubmission {
+    hash: Commitment<Transaction>,
+    // Whether the transaction has been submitted. This is locked while a submission is in flight,
+    // so that concurrent retries wait to find out whether it succeeded.
+    submitted: Arc<async_std::sync::Mutex<bool>>,
+}
+
+impl IdempotencyKeys {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            submissions: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// The submission made with `key` in `namespace`, or a new one of the transaction `hash`.
+    fn get_or_insert(
+        &mut self,
+        namespace: NamespaceId,
+        key: String,
+        hash: Commitment<Transaction>,
+    ) -> IdempotentSubmission {
+        let key = (namespace, key);
+        if let Some(submission) = self.submissions.get(&key) {
+            return submission.clone();
+        }
+
+        let submission = IdempotentSubmission {
+            hash,
+            submitted: Default::default(),
+        };
+        if self.capacity == 0 {
+            return submission;
+        }
+        if self.order.len() == self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.submissions.remove(&oldest);
+            }
+        }
+        self.submissions.insert(key.clone(), submission.clone());
+        self.order.push_back(key);
+        submission
+    }
+}
+
+impl Default for IdempotencyKeys {
+    fn default() -> Self {
+        Self::new(DEFAULT_IDEMPOTENCY_KEY_CAPACITY)
+    }
+}
+
+/// The number of idempotency keys remembered by default.
+const DEFAULT_IDEMPOTENCY_KEY_CAPACITY: usize = 10_000;
+
+/// Tracks a node catching up to the chain tip after startup.
+#[derive(Debug)]
+struct StartupCatchup {
+    // How many blocks behind the tip the node may be and still be considered caught up, or `None`
+    // if the node does not wait to catch up.
+    target: Option<u64>,
+    // How long to wait to catch up before serving the full API anyway.
+    timeout: Duration,
+    // The highest block height most recently reported by peers.
+    tip: RwLock<Option<u64>>,
+    caught_up: AtomicBool,
+    timed_out: AtomicBool,
+}
+
+impl StartupCatchup {
+    fn new(target: Option<u64>, timeout: Duration) -> Self {
+        Self {
+            target,
+            timeout,
+            tip: RwLock::new(None),
+            caught_up: AtomicBool::new(target.is_none()),
+            timed_out: AtomicBool::new(false),
+        }
+    }
+
+    /// Whether the node has stopped waiting to catch up, and serves its full API.
+    fn ready(&self) -> bool {
+        self.caught_up.load(Ordering::Relaxed) || self.timed_out.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for StartupCatchup {
+    fn default() -> Self {
+        Self::new(None, Duration::ZERO)
+    }
+}
+
+/// Wait until the block height in storage is within the startup catchup target of the chain tip.
+///
+/// The tip is learned from this node's state peers, which are polled until the node catches up. If
+/// the node has not caught up by the startup catchup timeout, for example because none of its peers
+/// are reachable, it stops waiting. Returns immediately if no startup catchup target is configured.
+async fn wait_for_startup_catchup<N, P, D, Ver>(state: Arc<RwLock<StorageState<N, P, D, Ver>>>)
+where
+    N: network::Type,
+    P: SequencerPersistence,
+    D: NodeDataSource<SeqTypes> + Send + Sync,
+    Ver: StaticVersionType + 'static,
+{
+    let api_state = state.read().await.as_ref().clone();
+    let catchup = api_state.startup_catchup.clone();
+    let Some(target) = catchup.target else {
+        return;
+    };
+    let poll = async {
+        loop {
+            let block_height = state.read().await.inner().block_height().await;
+            let tip = api_state
+                .node_state()
+                .await
+                .peers
+                .try_fetch_block_height()
+                .await;
+            match (block_height, tip) {
+                (Ok(block_height), Ok(tip)) => {
+                    *catchup.tip.write().await = Some(tip);
+                    if block_height as u64 + target >= tip {
+                        tracing::info!(block_height, tip, "caught up to chain tip");
+                        catchup.caught_up.store(true, Ordering::Relaxed);
+                        return;
+                    }
+                    tracing::info!(block_height, tip, "catching up to chain tip");
+                }
+                (Err(err), _) => tracing::warn!("failed to get block height: {err:#}"),
+                (_, Err(err)) => tracing::warn!("failed to get chain tip from peers: {err:#}"),
+            }
+            sleep(STARTUP_CATCHUP_INTERVAL).await;
+        }
+    };
+    if timeout(catchup.timeout, poll).await.is_err() {
+        tracing::warn!(
+            timeout = ?catchup.timeout,
+            "did not catch up to chain tip in time, serving full API anyway"
+        );
+        catchup.timed_out.store(true, Ordering::Relaxed);
+    }
+}
+
+/// How often to check whether a node has caught up to the chain tip after startup.
+const STARTUP_CATCHUP_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A transaction was rejected because its idempotency key was used for a different transaction.
+#[derive(Clone, Debug, Snafu)]
+#[snafu(display(
+    "idempotency key {key:?} was already used for transaction {hash} in namespace {namespace}"
+))]
+pub struct IdempotencyKeyConflict {
+    namespace: NamespaceId,
+    key: String,
+    hash: Commitment<Transaction>,
+}
+
+/// A transaction was rejected because its namespace exceeded its submission rate limit.
+#[derive(Clone, Copy, Debug, Snafu)]
+#[snafu(display("too many transactions submitted to namespace {namespace}, try again later"))]
+pub struct NamespaceRateLimited {
+    namespace: NamespaceId,
+}
+
+/// A transaction was rejected because the node is shutting down.
+#[derive(Clone, Copy, Debug, Snafu)]
+#[snafu(display("node is shutting down"))]
+pub struct NodeShuttingDown;
+
+/// Number of namespaces tracked by a [`NamespaceRateLimiter`] before idle ones are pruned.
+const MAX_RATE_LIMITED_NAMESPACES: usize = 10_000;
+
+/// Limits the rate of transaction submissions to each namespace independently.
+///
+/// Each namespace has a token bucket holding up to `burst` tokens, refilled at `rate` tokens per
+/// second. Each submission to a namespace takes a token from its bucket, and is rejected if the
+/// bucket is empty, so a namespace flooding the sequencer does not affect submissions to others.
+#[derive(Debug)]
+struct NamespaceRateLimiter {
+    rate: f64,
+    burst: f64,
+    buckets: Mutex<HashMap<NamespaceId, TokenBucket>>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl NamespaceRateLimiter {
+    fn new(rate: u64, burst: u64) -> Self {
+        Self {
+            rate: rate as f64,
+            burst: burst as f64,
+            buckets: Default::default(),
+        }
+    }
+
+    fn acquire(&self, namespace: NamespaceId) -> Result<(), NamespaceRateLimited> {
+        self.acquire_at(namespace, Instant::now())
+    }
+
+    fn acquire_at(&self, namespace: NamespaceId, now: Instant) -> Result<(), NamespaceRateLimited> {
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_RATE_LIMITED_NAMESPACES {
+            // A bucket which has refilled completely is no different from a new one, so we can
+            // forget it without loosening the limit.
+            buckets.retain(|_, bucket| self.refill(bucket, now) < self.burst);
+        }
+
+        let bucket = buckets.entry(namespace).or_insert(TokenBucket {
+            tokens: self.burst,
+            updated: now,
+        });
+        bucket.tokens = self.refill(bucket, now);
+        bucket.updated = now;
+        if bucket.tokens < 1.0 {
+            return Err(NamespaceRateLimited { namespace });
+        }
+        bucket.tokens -= 1.0;
+        Ok(())
+    }
+
+    fn refill(&self, bucket: &TokenBucket, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        (bucket.tokens + elapsed * self.rate).min(self.burst)
+    }
 }
 
 impl<N: network::Type, P: SequencerPersistence, Ver: StaticVersionType + 'static>
@@ -93,9 +529,72 @@ impl<N: network::Type, P: SequencerPersistence, Ver: StaticVersionType + 'static
     fn new(init: impl Future<Output = ConsensusState<N, P, Ver>> + Send + 'static) -> Self {
         Self {
             consensus: Arc::pin(Lazy::from_future(init.boxed())),
+            catchup_timeout: None,
+            catchup_sources: DEFAULT_CATCHUP_SOURCES.to_vec(),
+            submit_retry_timeout: None,
+            rejections: None,
+            namespace_rate_limiter: None,
+            max_submit_body_size: None,
+            startup_catchup: Default::default(),
+            idempotency_keys: Default::default(),
+        }
+    }
+
+    fn with_catchup_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.catchup_timeout = timeout;
+        self
+    }
+
+    fn with_catchup_sources(mut self, sources: Vec<CatchupSource>) -> Self {
+        if !sources.is_empty() {
+            self.catchup_sources = sources;
+        }
+        self
+    }
+
+    fn with_submit_retry_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.submit_retry_timeout = timeout;
+        self
+    }
+
+    fn with_rejection_log(mut self, capacity: Option<usize>) -> Self {
+        self.rejections =
+            capacity.map(|capacity| Arc::new(RwLock::new(RejectionLog::new(capacity))));
+        self
+    }
+
+    fn with_namespace_rate_limit(mut self, rate: Option<u64>, burst: Option<u64>) -> Self {
+        self.namespace_rate_limiter =
+            rate.map(|rate| Arc::new(NamespaceRateLimiter::new(rate, burst.unwrap_or(rate))));
+        self
+    }
+
+    fn with_startup_catchup(mut self, target: Option<u64>, timeout: Duration) -> Self {
+        self.startup_catchup = Arc::new(StartupCatchup::new(target, timeout));
+        self
+    }
+
+    async fn catchup_progress(&self, block_height: u64) -> CatchupProgress {
+        CatchupProgress {
+            block_height,
+            tip: *self.startup_catchup.tip.read().await,
+            caught_up: self.startup_catchup.caught_up.load(Ordering::Relaxed),
+            timed_out: self.startup_catchup.timed_out.load(Ordering::Relaxed),
         }
     }
 
+    fn with_max_submit_body_size(mut self, max: Option<u64>) -> Self {
+        self.max_submit_body_size = max;
+        self
+    }
+
+    fn with_idempotency_key_capacity(mut self, capacity: Option<usize>) -> Self {
+        if let Some(capacity) = capacity {
+            self.idempotency_keys = Arc::new(RwLock::new(IdempotencyKeys::new(capacity)));
+        }
+        self
+    }
+
     fn event_stream(&self) -> impl Stream<Item = Event<SeqTypes>> + Unpin {
         let state = self.clone();
         async move { state.consensus().await.read().await.event_stream() }
@@ -115,10 +614,19 @@ impl<N: network::Type, P: SequencerPersistence, Ver: StaticVersionType + 'static
         Arc::clone(&self.consensus.as_ref().get().await.get_ref().handle)
     }
 
+    async fn is_shutting_down(&self) -> bool {
+        let consensus = self.consensus.as_ref().get().await.get_ref();
+        consensus.shutting_down.load(Ordering::Relaxed)
+    }
+
     async fn node_state(&self) -> &NodeState {
         &self.consensus.as_ref().get().await.get_ref().node_state
     }
 
+    async fn light_client_genesis(&self) -> &ParsedLightClientState {
+        &self.consensus.as_ref().get().await.get_ref().light_client_genesis
+    }
+
     async fn hotshot_config(&self) -> HotShotConfig<PubKey> {
         self.consensus
             .as_ref()
@@ -159,22 +667,116 @@ impl<
         P: SequencerPersistence,
     > SubmitDataSource<N, P> for StorageState<N, P, D, Ver>
 {
-    async fn submit(&self, tx: Transaction) -> anyhow::Result<()> {
-        self.as_ref().submit(tx).await
+    async fn submit(&self, submission: TransactionSubmission) -> anyhow::Result<()> {
+        self.as_ref().submit(submission).await
+    }
+
+    async fn submit_idempotent(
+        &self,
+        key: String,
+        submission: TransactionSubmission,
+    ) -> anyhow::Result<Commitment<Transaction>> {
+        self.as_ref().submit_idempotent(key, submission).await
+    }
+
+    async fn rejections(&self) -> Option<Vec<RejectedTransaction>> {
+        self.as_ref().rejections().await
+    }
+
+    async fn max_submit_body_size(&self) -> u64 {
+        self.as_ref().max_submit_body_size().await
     }
 }
 
 impl<N: network::Type, Ver: StaticVersionType + 'static, P: SequencerPersistence>
     SubmitDataSource<N, P> for ApiState<N, P, Ver>
 {
-    async fn submit(&self, tx: Transaction) -> anyhow::Result<()> {
-        self.consensus()
-            .await
-            .read()
+    async fn submit(&self, submission: TransactionSubmission) -> anyhow::Result<()> {
+        if self.is_shutting_down().await {
+            return Err(NodeShuttingDown.into());
+        }
+        let fee = submission.fee();
+        let tx = submission.into_transaction();
+        if let Some(limiter) = &self.namespace_rate_limiter {
+            limiter.acquire(tx.namespace())?;
+        }
+        // An invalid transaction will never be accepted, so validation errors are not retried.
+        let node_state = self.node_state().await;
+        let valid = node_state
+            .validate_fee(fee)
+            .and_then(|()| node_state.validate_transaction(&tx));
+        if let Err(err) = valid {
+            let rejection = RejectedTransaction {
+                hash: tx.commit(),
+                namespace: tx.namespace(),
+                reason: format!("{err:#}"),
+                timestamp: OffsetDateTime::now_utc().unix_timestamp() as u64,
+            };
+            tracing::info!(
+                hash = %rejection.hash,
+                namespace = %rejection.namespace,
+                reason = %rejection.reason,
+                "rejected transaction"
+            );
+            if let Some(log) = &self.rejections {
+                log.write().await.push(rejection);
+            }
+            return Err(err);
+        }
+        let handle = self.consensus().await;
+        retry_with_backoff("submit transaction", self.submit_retry_timeout, || async {
+            handle.read().await.submit_transaction(tx.clone()).await?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn submit_idempotent(
+        &self,
+        key: String,
+        submission: TransactionSubmission,
+    ) -> anyhow::Result<Commitment<Transaction>> {
+        let namespace = submission.transaction().namespace();
+        let hash = submission.transaction().commit();
+        let original = self
+            .idempotency_keys
+            .write()
             .await
-            .submit_transaction(tx)
-            .await?;
-        Ok(())
+            .get_or_insert(namespace, key.clone(), hash);
+        if original.hash != hash {
+            return Err(IdempotencyKeyConflict {
+                namespace,
+                key,
+                hash: original.hash,
+            }
+            .into());
+        }
+
+        // Hold the lock while submitting, so that a concurrent retry with the same key waits for
+        // this submission, and only succeeds without submitting again if this one succeeded.
+        let mut submitted = original.submitted.lock().await;
+        if *submitted {
+            tracing::info!(key, %hash, "ignoring repeated submission");
+            return Ok(hash);
+        }
+        self.submit(submission).await?;
+        *submitted = true;
+        Ok(hash)
+    }
+
+    async fn rejections(&self) -> Option<Vec<RejectedTransaction>> {
+        let log = self.rejections.as_ref()?;
+        Some(log.read().await.entries.iter().cloned().collect())
+    }
+
+    async fn max_submit_body_size(&self) -> u64 {
+        match self.max_submit_body_size {
+            Some(max) => max,
+            None => {
+                let max_block_size = self.node_state().await.chain_config.max_block_size;
+                u64::from(max_block_size).saturating_mul(SUBMIT_BODY_SIZE_BLOCK_MULTIPLE)
+            }
+        }
     }
 }
 
@@ -182,7 +784,7 @@ impl<
         N: network::Type,
         Ver: StaticVersionType + 'static,
         P: SequencerPersistence,
-        D: CatchupDataSource + Send + Sync,
+        D: CatchupDataSource + AvailabilityDataSource<SeqTypes> + Send + Sync,
     > CatchupDataSource for StorageState<N, P, D, Ver>
 {
     #[tracing::instrument(skip(self))]
@@ -192,125 +794,683 @@ impl<
         view: ViewNumber,
         account: Address,
     ) -> anyhow::Result<AccountQueryData> {
-        // Check if we have the desired state in memory.
-        match self.as_ref().get_account(height, view, account).await {
-            Ok(account) => return Ok(account),
-            Err(err) => {
-                tracing::info!("account is not in memory, trying storage: {err:#}");
-            }
-        }
-
-        // Try storage.
-        self.inner().get_account(height, view, account).await
+        let sources = &self.as_ref().catchup_sources;
+        account_from_sources(self, sources, height, view, account).await
     }
 
     #[tracing::instrument(skip(self))]
     async fn get_frontier(&self, height: u64, view: ViewNumber) -> anyhow::Result<BlocksFrontier> {
-        // Check if we have the desired state in memory.
-        match self.as_ref().get_frontier(height, view).await {
-            Ok(frontier) => return Ok(frontier),
-            Err(err) => {
-                tracing::info!("frontier is not in memory, trying storage: {err:#}");
-            }
-        }
-
-        // Try storage.
-        self.inner().get_frontier(height, view).await
+        let sources = &self.as_ref().catchup_sources;
+        frontier_from_sources(self, sources, height, view).await
     }
-}
 
-impl<N: network::Type, Ver: StaticVersionType + 'static, P: SequencerPersistence> CatchupDataSource
-    for ApiState<N, P, Ver>
-{
     #[tracing::instrument(skip(self))]
-    async fn get_account(
+    async fn get_local_account(
         &self,
         height: u64,
         view: ViewNumber,
         account: Address,
     ) -> anyhow::Result<AccountQueryData> {
-        let state = self
-            .consensus()
-            .await
-            .read()
-            .await
-            .state(view)
-            .await
-            .context(format!(
-                "state not available for height {height}, view {view:?}"
-            ))?;
-        let (proof, balance) = FeeAccountProof::prove(&state.fee_merkle_tree, account).context(
-            format!("account {account} not available for height {height}, view {view:?}"),
-        )?;
-        Ok(AccountQueryData { balance, proof })
+        let sources = local_catchup_sources(&self.as_ref().catchup_sources);
+        account_from_sources(self, &sources, height, view, account).await
     }
 
     #[tracing::instrument(skip(self))]
-    async fn get_frontier(&self, height: u64, view: ViewNumber) -> anyhow::Result<BlocksFrontier> {
-        let state = self
-            .consensus()
-            .await
-            .read()
-            .await
-            .state(view)
-            .await
-            .context(format!(
-                "state not available for height {height}, view {view:?}"
-            ))?;
-        let tree = &state.block_merkle_tree;
-        let frontier = tree.lookup(tree.num_leaves() - 1).expect_ok()?.1;
-        Ok(frontier)
+    async fn get_local_frontier(
+        &self,
+        height: u64,
+        view: ViewNumber,
+    ) -> anyhow::Result<BlocksFrontier> {
+        let sources = local_catchup_sources(&self.as_ref().catchup_sources);
+        frontier_from_sources(self, &sources, height, view).await
     }
-}
 
-impl<N: network::Type, D: Sync, Ver: StaticVersionType + 'static, P: SequencerPersistence>
-    HotShotConfigDataSource for StorageState<N, P, D, Ver>
-{
-    async fn get_config(&self) -> PublicHotShotConfig {
-        self.as_ref().hotshot_config().await.into()
+    async fn get_block_proof(&self, height: u64) -> anyhow::Result<BlockMerkleProofQueryData> {
+        // Proofs relative to the latest stored state are only available from storage.
+        self.inner().get_block_proof(height).await
     }
-}
 
-impl<N: network::Type, Ver: StaticVersionType + 'static, P: SequencerPersistence>
-    HotShotConfigDataSource for ApiState<N, P, Ver>
-{
-    async fn get_config(&self) -> PublicHotShotConfig {
-        self.hotshot_config().await.into()
+    async fn get_latest_account(&self, account: Address) -> anyhow::Result<LatestAccountQueryData> {
+        // The latest state is always in memory.
+        self.as_ref().get_latest_account(account).await
     }
-}
 
-#[async_trait]
-impl<N: network::Type, D: Sync, Ver: StaticVersionType + 'static, P: SequencerPersistence>
-    StateSignatureDataSource<N> for StorageState<N, P, D, Ver>
-{
-    async fn get_state_signature(&self, height: u64) -> Option<StateSignatureRequestBody> {
-        self.as_ref().get_state_signature(height).await
+    #[tracing::instrument(skip(self))]
+    async fn get_account_at_root(
+        &self,
+        root: BlockMerkleCommitment,
+        account: Address,
+    ) -> anyhow::Result<AccountQueryData> {
+        // Recent states are in memory, while older ones may only be in storage or at peers. As in
+        // `get_account`, make sure proofs from storage or peers are consistent with the header for
+        // this root before returning them.
+        let height = root.size();
+        let header_for_root = || async move {
+            let leaf = stored_leaf(self.inner(), height).await?;
+            ensure!(
+                leaf.header().block_merkle_tree_root == root,
+                "block Merkle root does not match header {height}"
+            );
+            Ok(leaf)
+        };
+        fetch_from_sources(
+            "account",
+            &self.as_ref().catchup_sources,
+            self.as_ref().catchup_timeout,
+            move |source| match source {
+                CatchupSource::Memory => self.as_ref().get_account_at_root(root, account).boxed(),
+                CatchupSource::Storage => async move {
+                    let res = self.inner().get_account_at_root(root, account).await?;
+                    let header = header_for_root().await?.header().clone();
+                    let balance = res.verify(&header.fee_merkle_tree_root).context(format!(
+                        "storage proof for account {account} does not match header {height}"
+                    ))?;
+                    ensure!(
+                        balance == res.balance,
+                        "storage balance for account {account} does not match proof at height \
+                         {height}"
+                    );
+                    Ok(res)
+                }
+                .boxed(),
+                CatchupSource::Peers => async move {
+                    let leaf = header_for_root().await?;
+                    let peers = &self.as_ref().node_state().await.peers;
+                    peers
+                        .try_fetch_account(
+                            height,
+                            leaf.leaf().view_number(),
+                            leaf.header().fee_merkle_tree_root,
+                            account.into(),
+                        )
+                        .await
+                }
+                .boxed(),
+            },
+        )
+        .await
     }
-}
 
-#[async_trait]
-impl<N: network::Type, Ver: StaticVersionType + 'static, P: SequencerPersistence>
-    StateSignatureDataSource<N> for ApiState<N, P, Ver>
-{
-    async fn get_state_signature(&self, height: u64) -> Option<StateSignatureRequestBody> {
-        self.state_signer().await.get_state_signature(height).await
+    async fn get_signed_account(&self, account: Address) -> anyhow::Result<SignedAccountQueryData> {
+        signed_account(self.as_ref(), self, account).await
     }
 }
 
-#[cfg(any(test, feature = "testing"))]
-pub mod test_helpers {
-    use super::*;
-    use crate::{
-        catchup::{mock::MockStateCatchup, StateCatchup},
-        persistence::{no_storage, PersistenceOptions, SequencerPersistence},
-        state::{BlockMerkleTree, ValidatedState},
+/// Default maximum size of a submission request body, as a multiple of the maximum block size.
+///
+/// This leaves room for the serialization overhead of a transaction as large as a block, such as
+/// the base64 encoding of its payload in JSON.
+const SUBMIT_BODY_SIZE_BLOCK_MULTIPLE: u64 = 2;
+
+/// The chain config of each block decided in `events`, in order.
+fn decided_chain_configs(
+    events: impl Stream<Item = Event<SeqTypes>> + Send + 'static,
+) -> impl Stream<Item = ResolvableChainConfig> + Send {
+    events.flat_map(|event| {
+        let configs = match event.event {
+            EventType::Decide { leaf_chain, .. } => leaf_chain
+                .iter()
+                .rev()
+                .map(|info| info.leaf.block_header().chain_config)
+                .collect(),
+            _ => vec![],
+        };
+        stream::iter(configs)
+    })
+}
+
+/// Yield `current`, followed by each chain config in `configs` which differs from the last one
+/// yielded.
+///
+/// Decided headers may carry only a commitment to their chain config. Such a commitment is resolved
+/// if it matches a config seen earlier; otherwise the change cannot be reported, and is logged.
+fn chain_config_changes(
+    current: ChainConfig,
+    configs: impl Stream<Item = ResolvableChainConfig> + Send + 'static,
+) -> impl Stream<Item = ChainConfig> + Send {
+    let mut known = HashMap::from([(current.commit(), current)]);
+    let mut last = current.commit();
+    let changes = configs.filter_map(move |config| {
+        let commit = config.commit();
+        let change = if commit == last {
+            None
+        } else if let Some(config) = config.resolve().or_else(|| known.get(&commit).copied()) {
+            known.insert(commit, config);
+            last = commit;
+            Some(config)
+        } else {
+            tracing::warn!(%commit, "chain config changed, but the new config is not known");
+            None
+        };
+        future::ready(change)
+    });
+    stream::once(future::ready(current)).chain(changes)
+}
+
+/// Delay between attempts to look up an account in the state of a block, for a balance stream.
+const ACCOUNT_STREAM_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Maximum number of attempts to look up an account in the state of a block, for a balance stream.
+const ACCOUNT_STREAM_MAX_ATTEMPTS: u32 = 30;
+
+/// Follow decided blocks, given as `(height, view)`, and yield the balance of an account each time
+/// it changes.
+///
+/// The state for a block may not be available as soon as the block is decided, for example if
+/// state storage is lagging behind consensus, so each lookup is retried up to `max_attempts` times,
+/// `delay` apart. If the account still cannot be looked up, the stream yields the error and ends.
+fn account_balance_changes<F, Fut>(
+    blocks: impl Stream<Item = (u64, ViewNumber)> + Send + Unpin + 'static,
+    get_account: F,
+    delay: Duration,
+    max_attempts: u32,
+) -> impl Stream<Item = anyhow::Result<AccountQueryData>> + Send
+where
+    F: Fn(u64, ViewNumber) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = anyhow::Result<AccountQueryData>> + Send,
+{
+    stream::unfold(
+        (blocks, get_account, None, false),
+        move |(mut blocks, get_account, mut balance, failed)| async move {
+            if failed {
+                return None;
+            }
+            loop {
+                let (height, view) = blocks.next().await?;
+                let mut attempt = 1;
+                let res = loop {
+                    match get_account(height, view).await {
+                        Ok(res) => break res,
+                        Err(err) if attempt >= max_attempts => {
+                            let err = err.context(format!("account not available at {height}"));
+                            return Some((Err(err), (blocks, get_account, balance, true)));
+                        }
+                        Err(err) => {
+                            tracing::warn!(height, attempt, "account not yet available: {err:#}");
+                            sleep(delay).await;
+                            attempt += 1;
+                        }
+                    }
+                };
+
+                if balance != Some(res.balance) {
+                    balance = Some(res.balance);
+                    return Some((Ok(res), (blocks, get_account, balance, false)));
+                }
+            }
+        },
+    )
+}
+
+/// Sources consulted when serving catchup requests, unless configured otherwise.
+const DEFAULT_CATCHUP_SOURCES: [CatchupSource; 2] = [CatchupSource::Memory, CatchupSource::Storage];
+
+/// The catchup sources in `sources` which do not forward requests to this node's own peers.
+fn local_catchup_sources(sources: &[CatchupSource]) -> Vec<CatchupSource> {
+    sources
+        .iter()
+        .copied()
+        .filter(|source| *source != CatchupSource::Peers)
+        .collect()
+}
+
+/// Get the state of `account` at `(height, view)` from each of `sources` in turn.
+async fn account_from_sources<N, P, D, Ver>(
+    state: &StorageState<N, P, D, Ver>,
+    sources: &[CatchupSource],
+    height: u64,
+    view: ViewNumber,
+    account: Address,
+) -> anyhow::Result<AccountQueryData>
+where
+    N: network::Type,
+    Ver: StaticVersionType + 'static,
+    P: SequencerPersistence,
+    D: CatchupDataSource + AvailabilityDataSource<SeqTypes> + Send + Sync,
+{
+    // Storage and peers may reflect a different version of the state than consensus did for this
+    // `(height, view)`, so make sure the proofs we get from them are consistent with the header at
+    // this height before returning them.
+    fetch_from_sources(
+        "account",
+        sources,
+        state.as_ref().catchup_timeout,
+        move |source| match source {
+            CatchupSource::Memory => state.as_ref().get_account(height, view, account).boxed(),
+            CatchupSource::Storage => async move {
+                let res = state.inner().get_account(height, view, account).await?;
+                let header = stored_leaf(state.inner(), height).await?.header().clone();
+                let balance = res.verify(&header.fee_merkle_tree_root).context(format!(
+                    "storage proof for account {account} does not match header {height}"
+                ))?;
+                ensure!(
+                    balance == res.balance,
+                    "storage balance for account {account} does not match proof at height \
+                     {height}"
+                );
+                Ok(res)
+            }
+            .boxed(),
+            CatchupSource::Peers => async move {
+                let header = stored_leaf(state.inner(), height).await?.header().clone();
+                let peers = &state.as_ref().node_state().await.peers;
+                peers
+                    .try_fetch_account(height, view, header.fee_merkle_tree_root, account.into())
+                    .await
+            }
+            .boxed(),
+        },
+    )
+    .await
+}
+
+/// Get the block Merkle tree frontier at `(height, view)` from each of `sources` in turn.
+async fn frontier_from_sources<N, P, D, Ver>(
+    state: &StorageState<N, P, D, Ver>,
+    sources: &[CatchupSource],
+    height: u64,
+    view: ViewNumber,
+) -> anyhow::Result<BlocksFrontier>
+where
+    N: network::Type,
+    Ver: StaticVersionType + 'static,
+    P: SequencerPersistence,
+    D: CatchupDataSource + AvailabilityDataSource<SeqTypes> + Send + Sync,
+{
+    fetch_from_sources(
+        "frontier",
+        sources,
+        state.as_ref().catchup_timeout,
+        move |source| match source {
+            CatchupSource::Memory => state.as_ref().get_frontier(height, view).boxed(),
+            CatchupSource::Storage => state.inner().get_frontier(height, view).boxed(),
+            CatchupSource::Peers => async move {
+                let header = stored_leaf(state.inner(), height).await?.header().clone();
+                let mut tree = BlockMerkleTree::from_commitment(header.block_merkle_tree_root);
+                let peers = &state.as_ref().node_state().await.peers;
+                peers
+                    .try_remember_blocks_merkle_tree(height, view, &mut tree)
+                    .await?;
+                block_tree_frontier(&tree)
+            }
+            .boxed(),
+        },
+    )
+    .await
+}
+
+/// The frontier of a block Merkle tree: the proof for its most recently appended block.
+///
+/// The tree is empty in the state of the genesis block, which therefore has no frontier.
+fn block_tree_frontier(tree: &BlockMerkleTree) -> anyhow::Result<BlocksFrontier> {
+    let Some(last) = tree.num_leaves().checked_sub(1) else {
+        bail!("block Merkle tree is empty");
+    };
+    Ok(tree.lookup(last).expect_ok()?.1)
+}
+
+/// Get the state of `account` in the state most recently signed by the state signer of `api`.
+///
+/// The account is looked up by its block Merkle root using `source`.
+async fn signed_account<N, P, Ver>(
+    api: &ApiState<N, P, Ver>,
+    source: &(impl CatchupDataSource + Sync),
+    account: Address,
+) -> anyhow::Result<SignedAccountQueryData>
+where
+    N: network::Type,
+    P: SequencerPersistence,
+    Ver: StaticVersionType + 'static,
+{
+    let signature = api
+        .state_signer()
+        .await
+        .get_latest_state_signature_bundle()
+        .await
+        .context("no signed state available")?;
+    let header = &signature.header;
+    Ok(SignedAccountQueryData {
+        height: header.height,
+        account: source
+            .get_account_at_root(header.block_merkle_tree_root, account)
+            .await?,
+        signature,
+    })
+}
+
+/// Get the leaf at `height` from storage, failing if it is not available.
+async fn stored_leaf<D: AvailabilityDataSource<SeqTypes> + Sync>(
+    storage: &D,
+    height: u64,
+) -> anyhow::Result<LeafQueryData<SeqTypes>> {
+    storage
+        .get_leaf(height as usize)
+        .await
+        .try_resolve()
+        .ok()
+        .context(format!("header {height} not available"))
+}
+
+/// Get a catchup resource from each of `sources` in turn, until one has it.
+///
+/// If `timeout` is set, each source is given at most that long to respond before it is abandoned,
+/// so that a single slow backend cannot stall catchup.
+async fn fetch_from_sources<'a, T>(
+    resource: &str,
+    sources: &[CatchupSource],
+    timeout: Option<Duration>,
+    mut fetch: impl FnMut(CatchupSource) -> BoxFuture<'a, anyhow::Result<T>>,
+) -> anyhow::Result<T> {
+    let mut err = anyhow::anyhow!("no catchup sources configured");
+    for &source in sources {
+        match with_timeout(timeout, fetch(source)).await {
+            Ok(res) => return Ok(res),
+            Err(source_err) => {
+                tracing::info!(%source, "{resource} is not available: {source_err:#}");
+                err = source_err.context(format!("{resource} is not available from {source}"));
+            }
+        }
+    }
+    Err(err)
+}
+
+/// Minimum delay between attempts in [`retry_with_backoff`].
+const RETRY_MIN_DELAY: Duration = Duration::from_millis(100);
+
+/// Maximum delay between attempts in [`retry_with_backoff`].
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(2);
+
+/// Retry a fallible operation with exponential backoff until it succeeds or `timeout` has passed.
+///
+/// If `timeout` is not set, the operation is attempted only once.
+async fn retry_with_backoff<T, F, Fut>(
+    operation: &str,
+    timeout: Option<Duration>,
+    mut f: F,
+) -> anyhow::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    let Some(timeout) = timeout else {
+        return f().await;
+    };
+    let deadline = Instant::now() + timeout;
+    let mut delay = RETRY_MIN_DELAY;
+    loop {
+        let err = match f().await {
+            Ok(res) => return Ok(res),
+            Err(err) => err,
+        };
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(err.context(format!("{operation} failed after retrying for {timeout:?}")));
+        }
+        tracing::warn!(?delay, "{operation} failed, retrying: {err:#}");
+        sleep(delay.min(deadline - now)).await;
+        delay = (delay * 2).min(RETRY_MAX_DELAY);
+    }
+}
+
+async fn with_timeout<T>(
+    timeout: Option<Duration>,
+    fut: impl Future<Output = anyhow::Result<T>>,
+) -> anyhow::Result<T> {
+    match timeout {
+        Some(timeout) => async_std::future::timeout(timeout, fut)
+            .await
+            .context(format!("timed out after {timeout:?}"))?,
+        None => fut.await,
+    }
+}
+
+impl<N: network::Type, Ver: StaticVersionType + 'static, P: SequencerPersistence> CatchupDataSource
+    for ApiState<N, P, Ver>
+{
+    #[tracing::instrument(skip(self))]
+    async fn get_account(
+        &self,
+        height: u64,
+        view: ViewNumber,
+        account: Address,
+    ) -> anyhow::Result<AccountQueryData> {
+        let state = self
+            .consensus()
+            .await
+            .read()
+            .await
+            .state(view)
+            .await
+            .context(format!(
+                "state not available for height {height}, view {view:?}"
+            ))?;
+        let (proof, balance) = FeeAccountProof::prove(&state.fee_merkle_tree, account).context(
+            format!("account {account} not available for height {height}, view {view:?}"),
+        )?;
+        Ok(AccountQueryData::new(balance, proof))
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_frontier(&self, height: u64, view: ViewNumber) -> anyhow::Result<BlocksFrontier> {
+        let state = self
+            .consensus()
+            .await
+            .read()
+            .await
+            .state(view)
+            .await
+            .context(format!(
+                "state not available for height {height}, view {view:?}"
+            ))?;
+        block_tree_frontier(&state.block_merkle_tree)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_latest_account(&self, account: Address) -> anyhow::Result<LatestAccountQueryData> {
+        let handle = self.consensus().await;
+        let handle = handle.read().await;
+
+        // Search back from the current view for the most recent view whose state we have. The state
+        // of the last decided view is always retained, so we never need to look further back.
+        let decided_view = handle.decided_leaf().await.view_number();
+        let mut view = handle.cur_view().await;
+        let state = loop {
+            if let Some(state) = handle.state(view).await {
+                break state;
+            }
+            ensure!(
+                view > decided_view,
+                "no state available since decided view {decided_view:?}"
+            );
+            view = ViewNumber::new(view.u64() - 1);
+        };
+
+        // The block Merkle tree in the state for a block contains all blocks before it.
+        let height = state.block_merkle_tree.num_leaves();
+        let (proof, balance) = FeeAccountProof::prove(&state.fee_merkle_tree, account).context(
+            format!("account {account} not available for height {height}, view {view:?}"),
+        )?;
+        Ok(LatestAccountQueryData {
+            height,
+            view,
+            account: AccountQueryData::new(balance, proof),
+        })
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_account_at_root(
+        &self,
+        root: BlockMerkleCommitment,
+        account: Address,
+    ) -> anyhow::Result<AccountQueryData> {
+        let handle = self.consensus().await;
+        let handle = handle.read().await;
+
+        // Search the states in memory, from the current view back to the last decided view, for
+        // one with the requested root.
+        let height = root.size();
+        let decided_view = handle.decided_leaf().await.view_number();
+        let mut view = handle.cur_view().await;
+        let state = loop {
+            if let Some(state) = handle.state(view).await {
+                if state.block_merkle_tree.commitment() == root {
+                    break state;
+                }
+            }
+            ensure!(
+                view > decided_view,
+                "no state in memory with the requested block Merkle root at height {height}"
+            );
+            view = ViewNumber::new(view.u64() - 1);
+        };
+
+        let (proof, balance) = FeeAccountProof::prove(&state.fee_merkle_tree, account).context(
+            format!("account {account} not available for height {height}, view {view:?}"),
+        )?;
+        Ok(AccountQueryData::new(balance, proof))
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_signed_account(&self, account: Address) -> anyhow::Result<SignedAccountQueryData> {
+        // The most recently signed state is the last decided state this node has processed, so it
+        // is retained in memory even if consensus is not making progress.
+        signed_account(self, self, account).await
+    }
+}
+
+impl<N: network::Type, D: Sync, Ver: StaticVersionType + 'static, P: SequencerPersistence>
+    ConsensusDataSource for StorageState<N, P, D, Ver>
+{
+    async fn decided_block_height(&self) -> u64 {
+        self.as_ref().decided_block_height().await
+    }
+
+    async fn last_decided(&self) -> (u64, ViewNumber) {
+        self.as_ref().last_decided().await
+    }
+}
+
+impl<N: network::Type, Ver: StaticVersionType + 'static, P: SequencerPersistence>
+    ConsensusDataSource for ApiState<N, P, Ver>
+{
+    async fn decided_block_height(&self) -> u64 {
+        let leaf = self.consensus().await.read().await.decided_leaf().await;
+        leaf.height() + 1
+    }
+
+    async fn last_decided(&self) -> (u64, ViewNumber) {
+        let leaf = self.consensus().await.read().await.decided_leaf().await;
+        (leaf.height(), leaf.view_number())
+    }
+}
+
+impl<N: network::Type, D: Sync, Ver: StaticVersionType + 'static, P: SequencerPersistence>
+    HotShotConfigDataSource for StorageState<N, P, D, Ver>
+{
+    async fn get_config(&self) -> PublicHotShotConfig {
+        self.as_ref().hotshot_config().await.into()
+    }
+
+    async fn get_chain_config(&self) -> ChainConfig {
+        self.as_ref().get_chain_config().await
+    }
+
+    async fn chain_config_stream(&self) -> BoxStream<'static, ChainConfig> {
+        self.as_ref().chain_config_stream().await
+    }
+
+    async fn get_light_client_genesis(&self) -> ParsedLightClientState {
+        self.as_ref().get_light_client_genesis().await
+    }
+}
+
+impl<N: network::Type, Ver: StaticVersionType + 'static, P: SequencerPersistence>
+    HotShotConfigDataSource for ApiState<N, P, Ver>
+{
+    async fn get_config(&self) -> PublicHotShotConfig {
+        self.hotshot_config().await.into()
+    }
+
+    async fn get_chain_config(&self) -> ChainConfig {
+        // The config this node started with may have been replaced since, so prefer the config of
+        // the last decided block.
+        let started_with = self.node_state().await.chain_config;
+        let decided = self
+            .consensus()
+            .await
+            .read()
+            .await
+            .decided_leaf()
+            .await
+            .block_header()
+            .chain_config;
+        decided.resolve().unwrap_or_else(|| {
+            if decided.commit() != started_with.commit() {
+                tracing::warn!(
+                    commit = %decided.commit(),
+                    "decided chain config is not known, serving the config this node started with"
+                );
+            }
+            started_with
+        })
+    }
+
+    async fn chain_config_stream(&self) -> BoxStream<'static, ChainConfig> {
+        let current = self.get_chain_config().await;
+        chain_config_changes(current, decided_chain_configs(self.event_stream())).boxed()
+    }
+
+    async fn get_light_client_genesis(&self) -> ParsedLightClientState {
+        self.light_client_genesis().await.clone()
+    }
+}
+
+#[async_trait]
+impl<N: network::Type, D: Sync, Ver: StaticVersionType + 'static, P: SequencerPersistence>
+    StateSignatureDataSource<N> for StorageState<N, P, D, Ver>
+{
+    async fn get_state_signature(&self, height: u64) -> Option<StateSignatureRequestBody> {
+        self.as_ref().get_state_signature(height).await
+    }
+
+    async fn get_state_signature_bundle(&self, height: u64) -> Option<StateSignatureBundle> {
+        self.as_ref().get_state_signature_bundle(height).await
+    }
+}
+
+#[async_trait]
+impl<N: network::Type, Ver: StaticVersionType + 'static, P: SequencerPersistence>
+    StateSignatureDataSource<N> for ApiState<N, P, Ver>
+{
+    async fn get_state_signature(&self, height: u64) -> Option<StateSignatureRequestBody> {
+        self.state_signer().await.get_state_signature(height).await
+    }
+
+    async fn get_state_signature_bundle(&self, height: u64) -> Option<StateSignatureBundle> {
+        self.state_signer()
+            .await
+            .get_state_signature_bundle(height)
+            .await
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+pub mod test_helpers {
+    use super::*;
+    use crate::{
+        catchup::{mock::MockStateCatchup, StateCatchup},
+        persistence::{no_storage, PersistenceOptions, SequencerPersistence},
+        state::{BlockMerkleTree, ValidatedState},
         testing::{run_test_builder, wait_for_decide_on_handle, TestConfig},
     };
     use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
     use async_std::task::sleep;
     use committable::Committable;
     use es_version::{SequencerVersion, SEQUENCER_VERSION};
-    use ethers::{prelude::Address, utils::Anvil};
+    use ethers::{
+        prelude::Address,
+        utils::{Anvil, AnvilInstance},
+    };
     use futures::{
         future::{join_all, FutureExt},
         stream::StreamExt,
@@ -320,10 +1480,12 @@ pub mod test_helpers {
     use hotshot_contract_adapter::light_client::ParsedLightClientState;
     use hotshot_types::{
         event::LeafInfo,
-        traits::{metrics::NoMetrics, node_implementation::ConsensusTime},
+        traits::{
+            metrics::NoMetrics, node_implementation::ConsensusTime, signature_key::SignatureKey,
+        },
     };
     use itertools::izip;
-    use jf_merkle_tree::{MerkleCommitment, MerkleTreeScheme};
+    use jf_merkle_tree::{AppendableMerkleTreeScheme, MerkleCommitment, MerkleTreeScheme};
     use portpicker::pick_unused_port;
     use std::time::Duration;
     use surf_disco::Client;
@@ -445,6 +1607,44 @@ pub mod test_helpers {
         }
     }
 
+    /// A [`TestNetwork`] serving the API, along with a client connected to the API server.
+    pub struct TestNetworkWithClient {
+        pub network: TestNetwork<no_storage::NoStorage>,
+        pub client: Client<ServerError, SequencerVersion>,
+        pub port: u16,
+        // The L1 node used by the network, which is shut down when this is dropped.
+        _anvil: AnvilInstance,
+    }
+
+    /// Start a [`TestNetwork`] with the API served on a free port, and connect a client to it.
+    ///
+    /// The `opt` function is given options configuring the port, and returns the [`Options`] used
+    /// to start the server. It should enable the modules under test but should not change the port.
+    pub async fn connected_test_network(
+        opt: impl FnOnce(Options) -> Options,
+    ) -> TestNetworkWithClient {
+        let port = pick_unused_port().expect("No ports free");
+        let anvil = Anvil::new().spawn();
+        let l1 = anvil.endpoint().parse().unwrap();
+        let network = TestNetwork::new(
+            opt(Options::with_port(port)),
+            [no_storage::Options; TestConfig::NUM_NODES],
+            l1,
+            None,
+        )
+        .await;
+        let client: Client<ServerError, SequencerVersion> =
+            Client::new(format!("http://localhost:{port}").parse().unwrap());
+        client.connect(None).await;
+
+        TestNetworkWithClient {
+            network,
+            client,
+            port,
+            _anvil: anvil,
+        }
+    }
+
     /// Test the status API with custom options.
     ///
     /// The `opt` function can be used to modify the [`Options`] which are used to start the server.
@@ -578,6 +1778,23 @@ pub mod test_helpers {
             .send()
             .await
             .unwrap();
+
+        // The bundle includes the stake table, so it can be verified.
+        let bundle = client
+            .get::<StateSignatureBundle>(&format!("state-signature/bundle/{}", height))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(bundle.header.height, height);
+        bundle.verify().unwrap();
+
+        // Verification fails if any part of the bundle is inconsistent.
+        let mut bad_bundle = bundle.clone();
+        bad_bundle.stake_table.pop();
+        bad_bundle.verify().unwrap_err();
+        let mut bad_bundle = bundle;
+        bad_bundle.header.height += 1;
+        bad_bundle.verify().unwrap_err();
     }
 
     /// Test the catchup API with custom options.
@@ -675,35 +1892,156 @@ pub mod test_helpers {
         BlockMerkleTree::verify(root.digest(), root.size() - 1, res)
             .unwrap()
             .unwrap();
-    }
-}
 
-#[cfg(test)]
-#[espresso_macros::generic_tests]
-mod api_tests {
-    use self::options::HotshotEvents;
+        // The versioned routes serve the same responses in the current format, and reject formats
+        // the server does not support.
+        let account_route = format!("{height}/{}/account/{:x}", view.u64(), Address::default());
+        let blocks_route = format!("{height}/{}/blocks", view.u64());
+        let version = CATCHUP_FORMAT_VERSION;
+        let res = client
+            .get::<AccountQueryData>(&format!("catchup/format/{version}/{account_route}"))
+            .send()
+            .await
+            .unwrap();
+        let unversioned = client
+            .get::<AccountQueryData>(&format!("catchup/{account_route}"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            bincode::serialize(&res).unwrap(),
+            bincode::serialize(&unversioned).unwrap()
+        );
+        let res = client
+            .get::<BlocksFrontier>(&format!("catchup/format/{version}/{blocks_route}"))
+            .send()
+            .await
+            .unwrap();
+        BlockMerkleTree::verify(root.digest(), root.size() - 1, res)
+            .unwrap()
+            .unwrap();
+        for route in [account_route, blocks_route] {
+            client
+                .get::<serde_json::Value>(&format!("catchup/format/{}/{route}", version + 1))
+                .send()
+                .await
+                .unwrap_err();
+        }
 
-    use super::*;
-    use crate::{
-        persistence::no_storage,
-        testing::{wait_for_decide_on_handle, TestConfig},
-        Header, NamespaceId,
+        // Latest state: the server picks the view, which is at least as recent as the last decide.
+        let res = client
+            .get::<LatestAccountQueryData>(&format!(
+                "catchup/latest/account/{:x}",
+                Address::default()
+            ))
+            .send()
+            .await
+            .unwrap();
+        assert!(res.view >= leaf.view_number());
+        let state = network.server.state(res.view).await.unwrap();
+        assert_eq!(res.height, state.block_merkle_tree.num_leaves());
+        assert_eq!(res.account.balance, 0.into());
+        assert_eq!(
+            res.account
+                .proof
+                .verify(&state.fee_merkle_tree.commitment())
+                .unwrap(),
+            0.into()
+        );
+
+        // State pinned to a block Merkle root.
+        let state = network.server.state(view).await.unwrap();
+        let root = state.block_merkle_tree.commitment();
+        let res = client
+            .get::<AccountQueryData>(&format!(
+                "catchup/root/{root}/account/{:x}",
+                Address::default()
+            ))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.balance, 0.into());
+        assert_eq!(
+            res.proof
+                .verify(&state.fee_merkle_tree.commitment())
+                .unwrap(),
+            0.into()
+        );
+
+        // A root we don't have state for.
+        let mut tree = state.block_merkle_tree.clone();
+        tree.push(leaf.block_header().commit()).unwrap();
+        client
+            .get::<AccountQueryData>(&format!(
+                "catchup/root/{}/account/{:x}",
+                tree.commitment(),
+                Address::default()
+            ))
+            .send()
+            .await
+            .unwrap_err();
+
+        // Signed state: with consensus stopped, the last decided state is still served, along with
+        // its signature. Signing happens asynchronously after each decide, so wait until the last
+        // decided state has been signed.
+        let res = loop {
+            match client
+                .get::<SignedAccountQueryData>(&format!(
+                    "catchup/signed/account/{:x}",
+                    Address::default()
+                ))
+                .send()
+                .await
+            {
+                Ok(res) if res.height == leaf.height() => break res,
+                res => {
+                    tracing::info!(?res, "waiting for last decided state to be signed");
+                    sleep(Duration::from_secs(1)).await;
+                }
+            }
+        };
+        assert_eq!(res.signature.header.commit(), leaf.block_header().commit());
+        assert_eq!(res.verify().unwrap(), 0.into());
+
+        // The signed state cannot be passed off as more recent than it is.
+        let mut bad = res;
+        bad.height += 1;
+        bad.verify().unwrap_err();
+    }
+}
+
+#[cfg(test)]
+#[espresso_macros::generic_tests]
+mod api_tests {
+    use self::options::HotshotEvents;
+
+    use super::*;
+    use crate::{
+        block::NsPayloadCommitment,
+        persistence::no_storage,
+        testing::{wait_for_decide_on_handle, TestConfig},
+        Header, NamespaceId,
     };
     use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
     use committable::Committable;
     use data_source::testing::TestableSequencerDataSource;
-    use endpoints::NamespaceProofQueryData;
+    use endpoints::{
+        BlockSizeQueryData, BlockTransactionQueryData, BuilderFeeQueryData, EmptyBlockProof,
+        NamespaceProofBundle, NamespaceProofQueryData, NamespaceRawProofQueryData,
+        TransactionProofQueryData,
+    };
     use es_version::SequencerVersion;
     use ethers::utils::Anvil;
     use futures::stream::StreamExt;
-    use hotshot_query_service::availability::{LeafQueryData, VidCommonQueryData};
+    use hotshot_query_service::availability::{BlockQueryData, LeafQueryData, VidCommonQueryData};
+    use hotshot_types::simple_certificate::QuorumCertificate;
     use portpicker::pick_unused_port;
     use surf_disco::Client;
     use test_helpers::{
         catchup_test_helper, state_signature_test_helper, status_test_helper, submit_test_helper,
         TestNetwork,
     };
-    use tide_disco::error::ServerError;
+    use tide_disco::{error::ServerError, StatusCode};
 
     #[async_std::test]
     pub(crate) async fn submit_test_with_query_module<D: TestableSequencerDataSource>() {
@@ -787,6 +2125,80 @@ mod api_tests {
                 .send()
                 .await
                 .unwrap();
+            // The binary encoding of the response has only the proof and the transactions, so
+            // existing binary clients can still decode it.
+            assert_eq!(
+                bincode::serialize(&ns_query_res).unwrap(),
+                bincode::serialize(&(&ns_query_res.proof, &ns_query_res.transactions)).unwrap()
+            );
+
+            // The raw bytes of each transaction match the decoded transactions.
+            let raw_query_res: NamespaceRawProofQueryData = client
+                .get(&format!("availability/block/{block_num}/namespace/{ns_id}/raw"))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(raw_query_res.transactions, ns_query_res.transactions);
+            assert_eq!(
+                raw_query_res.raw_transactions.len(),
+                raw_query_res.transactions.len()
+            );
+            for (raw, tx) in raw_query_res
+                .raw_transactions
+                .iter()
+                .zip(&raw_query_res.transactions)
+            {
+                assert_eq!(raw.0, tx.payload());
+            }
+
+            // The block size matches the full block.
+            let size: BlockSizeQueryData = client
+                .get(&format!("availability/block/{block_num}/size"))
+                .send()
+                .await
+                .unwrap();
+            let block: BlockQueryData<SeqTypes> = client
+                .get(&format!("availability/block/{block_num}"))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(size.payload_byte_len.as_usize() as u64, block.size());
+            assert_eq!(size.num_namespaces, header.ns_table.iter().count());
+
+            // The builder fee of each block after genesis verifies against its header.
+            if block_num > 0 {
+                let fee: BuilderFeeQueryData = client
+                    .get(&format!("availability/block/{block_num}/builder-fee"))
+                    .send()
+                    .await
+                    .unwrap();
+                assert_eq!(fee.verify(header.commit()).unwrap(), header.fee_info);
+            }
+
+            // A block can be proven empty if and only if its payload is empty.
+            let res = client
+                .get::<EmptyBlockProof>(&format!("availability/block/{block_num}/empty"))
+                .send()
+                .await;
+            if size.payload_byte_len.as_usize() == 0 {
+                res.unwrap().verify(header.commit()).unwrap();
+            } else {
+                assert_eq!(res.unwrap_err().status, StatusCode::NOT_FOUND);
+            }
+
+            // The QC for each leaf signs that leaf.
+            let leaf: LeafQueryData<SeqTypes> = client
+                .get(&format!("availability/leaf/{block_num}"))
+                .send()
+                .await
+                .unwrap();
+            let qc: QuorumCertificate<SeqTypes> = client
+                .get(&format!("availability/leaf/{block_num}/qc"))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(qc, *leaf.qc());
+            assert_eq!(qc.data.leaf_commit, leaf.leaf().commit());
 
             // Verify namespace proof if present
             if let Some(ns_proof) = ns_query_res.proof {
@@ -803,12 +2215,59 @@ mod api_tests {
                         vid_common.common(),
                     )
                     .unwrap();
+
+                // Check the namespace commitment against the full block payload.
+                let block: BlockQueryData<SeqTypes> = client
+                    .get(&format!("availability/block/{block_num}"))
+                    .send()
+                    .await
+                    .unwrap();
+                let ns_commitment: NsPayloadCommitment = client
+                    .get(&format!(
+                        "availability/block/{block_num}/namespace/{ns_id}/commitment"
+                    ))
+                    .send()
+                    .await
+                    .unwrap();
+                assert_eq!(Some(ns_commitment), block.payload().ns_payload_commitment(&ns_id));
             } else {
                 // Namespace proof should be present if ns_id exists in ns_table
                 assert!(header.ns_table.find_ns_id(&ns_id).is_none());
                 assert!(ns_query_res.transactions.is_empty());
+                client
+                    .get::<NsPayloadCommitment>(&format!(
+                        "availability/block/{block_num}/namespace/{ns_id}/commitment"
+                    ))
+                    .send()
+                    .await
+                    .unwrap_err();
             }
 
+            // A client trusting only the header commitment can verify the bundled proof, whether
+            // or not the namespace is present, but not against a different header.
+            let mut bundle: NamespaceProofBundle = client
+                .get(&format!("availability/block/{block_num}/namespace/{ns_id}/bundle"))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(bundle.verify(header.commit(), ns_id).unwrap(), ns_query_res.transactions);
+            bundle.header.timestamp += 1;
+            bundle.verify(header.commit(), ns_id).unwrap_err();
+
+            // The transactions in the namespace appear, in order, among all the transactions in
+            // the block.
+            let block_txs: Vec<BlockTransactionQueryData> = client
+                .get(&format!("availability/block/{block_num}/transactions"))
+                .send()
+                .await
+                .unwrap();
+            let ns_txs = block_txs
+                .iter()
+                .filter(|tx| tx.namespace == ns_id)
+                .map(|tx| tx.transaction.clone())
+                .collect::<Vec<_>>();
+            assert_eq!(ns_txs, ns_query_res.transactions);
+
             found_empty_block = found_empty_block || ns_query_res.transactions.is_empty();
 
             for txn in ns_query_res.transactions {
@@ -820,6 +2279,42 @@ mod api_tests {
         }
         assert!(found_txn);
         assert!(found_empty_block);
+
+        // The transaction can be looked up by its commitment, with a verifiable inclusion proof.
+        let tx_proof: TransactionProofQueryData = client
+            .get(&format!("availability/transaction/{hash}/proof"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(tx_proof.transaction, txn);
+        assert_eq!(tx_proof.height, block_height as u64);
+        let header: Header = client
+            .get(&format!("availability/header/{block_height}"))
+            .send()
+            .await
+            .unwrap();
+        let vid_common: VidCommonQueryData<SeqTypes> = client
+            .get(&format!("availability/vid/common/{block_height}"))
+            .send()
+            .await
+            .unwrap();
+        assert!(tx_proof
+            .proof
+            .verify(
+                &header.ns_table,
+                &tx_proof.transaction,
+                &header.payload_commitment,
+                vid_common.common(),
+            )
+            .unwrap());
+        let block_txs: Vec<BlockTransactionQueryData> = client
+            .get(&format!("availability/block/{block_height}/transactions"))
+            .send()
+            .await
+            .unwrap();
+        assert!(block_txs
+            .iter()
+            .any(|tx| tx.index == tx_proof.index && tx.transaction == txn));
     }
 
     #[async_std::test]
@@ -847,6 +2342,7 @@ mod api_tests {
 
         let hotshot_events = HotshotEvents {
             events_service_port: hotshot_event_streaming_port,
+            replay_buffer_size: 0,
         };
 
         let client: Client<ServerError, SequencerVersion> = Client::new(url);
@@ -892,19 +2388,25 @@ mod api_tests {
 #[cfg(test)]
 mod test {
     use self::{
-        data_source::testing::TestableSequencerDataSource, sql::DataSource as SqlDataSource,
+        data_source::{testing::TestableSequencerDataSource, SequencerDataSource},
+        endpoints::{
+            BlockWithVidQueryData, FeeRevenueQueryData, HeaderLinkQueryData,
+            ValidatorStakeQueryData,
+        },
+        sql::DataSource as SqlDataSource,
     };
     use super::*;
     use crate::{
         catchup::{mock::MockStateCatchup, StatePeers},
         persistence::no_storage,
-        state::{FeeAccount, FeeAmount, ValidatedState},
+        state::{replay_state, FeeAccount, FeeAmount, ValidatedState},
         testing::TestConfig,
-        Header,
+        Header, Leaf,
     };
     use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
     use async_std::task::sleep;
-    use committable::Commitment;
+    use clap::Parser;
+    use committable::{Commitment, Committable};
     use es_version::{SequencerVersion, SEQUENCER_VERSION};
     use ethers::utils::Anvil;
     use futures::future::{self, join_all};
@@ -912,21 +2414,29 @@ mod test {
     use hotshot::types::EventType;
     use hotshot_query_service::{
         availability::{BlockQueryData, LeafQueryData},
+        node::NodeDataSource,
         types::HeightIndexed,
     };
     use hotshot_types::{
         event::LeafInfo,
+        simple_certificate::QuorumCertificate,
         traits::{metrics::NoMetrics, node_implementation::ConsensusTime},
+        vid::VidSchemeType,
     };
-    use jf_merkle_tree::prelude::{MerkleProof, Sha3Node};
+    use jf_merkle_tree::{
+        prelude::{MerkleProof, Sha3Node},
+        AppendableMerkleTreeScheme, MerkleCommitment,
+    };
+    use jf_vid::VidScheme;
     use portpicker::pick_unused_port;
-    use std::time::Duration;
+    use sequencer_utils::commitment_to_u256;
+    use std::time::{Duration, Instant};
     use surf_disco::Client;
     use test_helpers::{
-        catchup_test_helper, state_signature_test_helper, status_test_helper, submit_test_helper,
-        TestNetwork,
+        catchup_test_helper, connected_test_network, state_signature_test_helper,
+        status_test_helper, submit_test_helper, TestNetwork, TestNetworkWithClient,
     };
-    use tide_disco::{app::AppHealth, error::ServerError, healthcheck::HealthStatus};
+    use tide_disco::{app::AppHealth, error::ServerError, healthcheck::HealthStatus, StatusCode};
 
     #[async_std::test]
     async fn test_healthcheck() {
@@ -952,6 +2462,303 @@ mod test {
         assert_eq!(health.status, HealthStatus::Available);
     }
 
+    #[test]
+    fn test_account_query_data_binary_layout() {
+        setup_logging();
+        setup_backtrace();
+
+        // Catchup responses are versioned by route, so their binary encoding must not change: an
+        // account response is exactly the balance followed by the proof.
+        let account = FeeAccount::from(Address::default());
+        let mut state = ValidatedState::default();
+        state.prefund_account(account, 1000.into());
+        let (proof, balance) =
+            FeeAccountProof::prove(&state.fee_merkle_tree, account.address()).unwrap();
+        let res = AccountQueryData::new(balance, proof.clone());
+        assert_eq!(
+            bincode::serialize(&res).unwrap(),
+            bincode::serialize(&(balance, &proof)).unwrap()
+        );
+        let res: AccountQueryData =
+            bincode::deserialize(&bincode::serialize(&(balance, &proof)).unwrap()).unwrap();
+        assert_eq!(res.verify(&state.fee_merkle_tree.commitment()).unwrap(), balance);
+    }
+
+    #[test]
+    fn test_rejection_log() {
+        setup_logging();
+        setup_backtrace();
+
+        let rejection = |i: u32| {
+            let tx = Transaction::new(NamespaceId::from(i), vec![i as u8]);
+            RejectedTransaction {
+                hash: tx.commit(),
+                namespace: tx.namespace(),
+                reason: format!("rejection {i}"),
+                timestamp: i.into(),
+            }
+        };
+
+        let mut log = RejectionLog::new(2);
+        log.push(rejection(0));
+        log.push(rejection(1));
+        assert_eq!(log.entries, [rejection(0), rejection(1)]);
+
+        // The oldest rejection is evicted once the log is full.
+        log.push(rejection(2));
+        assert_eq!(log.entries, [rejection(1), rejection(2)]);
+
+        // A log with no capacity records nothing.
+        let mut log = RejectionLog::new(0);
+        log.push(rejection(0));
+        assert!(log.entries.is_empty());
+    }
+
+    #[test]
+    fn test_namespace_rate_limiter() {
+        setup_logging();
+        setup_backtrace();
+
+        let limiter = NamespaceRateLimiter::new(2, 3);
+        let flooded = NamespaceId::from(1);
+        let other = NamespaceId::from(2);
+        let start = Instant::now();
+
+        // A namespace can submit a burst of transactions before it is limited.
+        for _ in 0..3 {
+            limiter.acquire_at(flooded, start).unwrap();
+        }
+        limiter.acquire_at(flooded, start).unwrap_err();
+
+        // Other namespaces are unaffected.
+        for _ in 0..3 {
+            limiter.acquire_at(other, start).unwrap();
+        }
+
+        // The limited namespace recovers at the configured rate.
+        let later = start + Duration::from_millis(500);
+        limiter.acquire_at(flooded, later).unwrap();
+        limiter.acquire_at(flooded, later).unwrap_err();
+
+        // Buckets never refill beyond the burst size.
+        let much_later = start + Duration::from_secs(60);
+        for _ in 0..3 {
+            limiter.acquire_at(flooded, much_later).unwrap();
+        }
+        limiter.acquire_at(flooded, much_later).unwrap_err();
+    }
+
+    #[async_std::test]
+    async fn test_config_api() {
+        setup_logging();
+        setup_backtrace();
+
+        let port = pick_unused_port().expect("No ports free");
+        let url = format!("http://localhost:{port}").parse().unwrap();
+        let client: Client<ServerError, SequencerVersion> = Client::new(url);
+        let options = Options::with_port(port).config(Default::default());
+        let anvil = Anvil::new().spawn();
+        let l1 = anvil.endpoint().parse().unwrap();
+        let network = TestNetwork::new(
+            options,
+            [no_storage::Options; TestConfig::NUM_NODES],
+            l1,
+            None,
+        )
+        .await;
+
+        client.connect(None).await;
+        let chain_config = client
+            .get::<ChainConfig>("config/chain")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(chain_config, network.server.node_state().chain_config);
+
+        // Subscribers get the current chain config first.
+        let streamed = client
+            .socket("config/stream")
+            .subscribe::<ChainConfig>()
+            .await
+            .unwrap()
+            .next()
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(streamed, chain_config);
+
+        let light_client_genesis = client
+            .get::<ParsedLightClientState>("config/light-client-genesis")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(light_client_genesis, network.light_client_genesis());
+
+        // Each node has stake 1 in the genesis stake table.
+        let stake_table = &network.cfg.hotshot_config().known_nodes_with_stake;
+        let key = stake_table[0].stake_table_entry.stake_key;
+        let stake = client
+            .get::<ValidatorStakeQueryData>(&format!("config/stake/{key}/0"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(stake.height, 0);
+        assert_eq!(stake.stake_key, key);
+        assert_eq!(stake.stake, U256::one());
+        assert_eq!(stake.total_stake, stake_table.len().into());
+
+        // A key which is not in the stake table has no stake.
+        let (other_key, _) = PubKey::generated_from_seed_indexed([1; 32], 0);
+        let stake = client
+            .get::<ValidatorStakeQueryData>(&format!("config/stake/{other_key}/0"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(stake.stake, U256::zero());
+        assert_eq!(stake.total_stake, stake_table.len().into());
+
+        // Future blocks are not available.
+        client
+            .get::<ValidatorStakeQueryData>(&format!("config/stake/{key}/{}", u64::MAX))
+            .send()
+            .await
+            .unwrap_err();
+    }
+
+    #[async_std::test]
+    async fn test_chain_config_changes() {
+        setup_logging();
+        setup_backtrace();
+
+        let current = ChainConfig::default();
+        let updated = ChainConfig {
+            base_fee: 1.into(),
+            ..current
+        };
+        let unknown = ChainConfig {
+            base_fee: 2.into(),
+            ..current
+        };
+        let decided = [
+            // No change.
+            ResolvableChainConfig::from(current),
+            ResolvableChainConfig::from(current.commit()),
+            // A change to a new config.
+            ResolvableChainConfig::from(updated),
+            ResolvableChainConfig::from(updated.commit()),
+            // A change back to a known config, given only by commitment.
+            ResolvableChainConfig::from(current.commit()),
+            // A change to a config which cannot be resolved is skipped.
+            ResolvableChainConfig::from(unknown.commit()),
+            ResolvableChainConfig::from(updated.commit()),
+        ];
+        let changes = chain_config_changes(current, futures::stream::iter(decided))
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(changes, [current, updated, current, updated]);
+    }
+
+    #[async_std::test]
+    async fn test_decided_chain_config_changes() {
+        setup_logging();
+        setup_backtrace();
+
+        let instance = NodeState::mock();
+        let current = instance.chain_config;
+        let updated = ChainConfig {
+            base_fee: 1.into(),
+            ..current
+        };
+        let upgraded = instance.clone().with_chain_config(updated);
+
+        // Decide a block with the current config, then blocks with an updated config, given first
+        // in full and then by commitment, then a block with the original config again.
+        let leaf = Leaf::genesis(&instance.genesis_state, &instance).await;
+        let upgraded_leaf = Leaf::genesis(&upgraded.genesis_state, &upgraded).await;
+        let mut upgraded_leaf_by_commit = upgraded_leaf.clone();
+        upgraded_leaf_by_commit.block_header_mut().chain_config = updated.commit().into();
+        let mut leaf_by_commit = leaf.clone();
+        leaf_by_commit.block_header_mut().chain_config = current.commit().into();
+
+        let qc = QuorumCertificate::genesis(&instance.genesis_state, &instance).await;
+        let decide = |leaves: Vec<Leaf>| Event {
+            view_number: ViewNumber::genesis(),
+            event: EventType::Decide {
+                // Leaf chains are ordered from newest to oldest.
+                leaf_chain: Arc::new(
+                    leaves
+                        .into_iter()
+                        .rev()
+                        .map(|leaf| LeafInfo::new(leaf, Default::default(), None, None))
+                        .collect(),
+                ),
+                qc: Arc::new(qc.clone()),
+                block_size: None,
+            },
+        };
+        let events = [
+            decide(vec![leaf]),
+            decide(vec![upgraded_leaf, upgraded_leaf_by_commit]),
+            decide(vec![leaf_by_commit]),
+        ];
+
+        // A subscriber receives the current config, followed by each change.
+        let changes = chain_config_changes(current, decided_chain_configs(stream::iter(events)))
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(changes, [current, updated, current]);
+    }
+
+    #[async_std::test]
+    async fn test_account_balance_changes() {
+        setup_logging();
+        setup_backtrace();
+
+        let account = FeeAccount::from(Address::default());
+        let query = |balance: u64| {
+            let mut state = ValidatedState::default();
+            state.prefund_account(account, balance.into());
+            let (proof, balance) =
+                FeeAccountProof::prove(&state.fee_merkle_tree, account.address()).unwrap();
+            AccountQueryData::new(balance, proof)
+        };
+
+        // The balance changes after blocks 2 and 4. The state of block 3 is only available on the
+        // second attempt.
+        let balances = [5, 5, 3, 3, 1];
+        let attempts = Arc::new(Mutex::new(0));
+        let get_account = move |height: u64, _: ViewNumber| {
+            let attempts = attempts.clone();
+            async move {
+                if height == 3 && std::mem::replace(&mut *attempts.lock().unwrap(), 1) == 0 {
+                    anyhow::bail!("state not yet available");
+                }
+                Ok(query(balances[height as usize]))
+            }
+        };
+        let blocks = stream::iter((0..5).map(|height| (height, ViewNumber::new(height))));
+        let changes = account_balance_changes(blocks, get_account, Duration::ZERO, 2)
+            .map(|res| res.unwrap().balance)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(changes, [5.into(), 3.into(), 1.into()]);
+
+        // If the state of a block never becomes available, the stream fails and ends.
+        let get_account = |height: u64, _: ViewNumber| async move {
+            if height == 1 {
+                anyhow::bail!("state not available");
+            }
+            Ok(query(5))
+        };
+        let blocks = stream::iter((0..5).map(|height| (height, ViewNumber::new(height))));
+        let res = account_balance_changes(blocks, get_account, Duration::ZERO, 2)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0].as_ref().unwrap().balance, 5.into());
+        res[1].as_ref().unwrap_err();
+    }
+
     #[async_std::test]
     async fn status_test_without_query_module() {
         status_test_helper(|opt| opt).await
@@ -1031,6 +2838,49 @@ mod test {
                 .unwrap();
             assert_eq!(*path.elem().unwrap(), block.hash());
 
+            tracing::info!(i, "get block Merkle proof");
+            let res = client
+                .get::<BlockMerkleProofQueryData>(&format!("availability/block/{i}/merkle-proof"))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(*res.proof.elem().unwrap(), block.hash());
+            let root = client
+                .get::<Header>(&format!("availability/header/{}", res.root_height))
+                .send()
+                .await
+                .unwrap()
+                .block_merkle_tree_root;
+            BlockMerkleTree::verify(root.digest(), i, &res.proof)
+                .unwrap()
+                .unwrap();
+
+            tracing::info!(i, "get header link");
+            let link = client
+                .get::<HeaderLinkQueryData>(&format!("availability/header/{i}/link"))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(link.parent.commit(), block.hash());
+            assert_eq!(link.header.height, i + 1);
+            assert_eq!(*link.proof.elem().unwrap(), link.parent.commit());
+            BlockMerkleTree::verify(link.header.block_merkle_tree_root.digest(), i, &link.proof)
+                .unwrap()
+                .unwrap();
+
+            tracing::info!(i, "get block by builder commitment");
+            let commitment = block.header().builder_commitment.clone();
+            let res = client
+                .get::<BlockQueryData<SeqTypes>>(&format!(
+                    "availability/block/builder-commitment/{commitment}"
+                ))
+                .send()
+                .await
+                .unwrap();
+            // Empty blocks share a builder commitment, so we may get a later block.
+            assert_eq!(res.header().builder_commitment, commitment);
+            assert!(res.height() >= i);
+
             tracing::info!(i, "get fee state");
             let account = TestConfig::builder_key().fee_account();
             let path = client
@@ -1045,14 +2895,426 @@ mod test {
             assert_eq!(*path.index(), account);
             assert!(*path.elem().unwrap() > 0.into(), "{:?}", path.elem());
         }
-    }
-
-    #[async_std::test]
-    async fn test_catchup() {
-        setup_logging();
-        setup_backtrace();
 
-        // Start a sequencer network, using the query service for catchup.
+        tracing::info!("get fee revenue");
+        let revenue = client
+            .get::<FeeRevenueQueryData>("fee-state/revenue/1/3")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(revenue.from.height, 1);
+        assert_eq!(revenue.to.height, 3);
+        for end in [&revenue.from, &revenue.to] {
+            let header = client
+                .get::<Header>(&format!("availability/header/{}", end.height))
+                .send()
+                .await
+                .unwrap();
+            let balance = end
+                .account
+                .proof
+                .verify(&header.fee_merkle_tree_root)
+                .unwrap();
+            assert_eq!(balance, end.account.balance);
+        }
+        assert_eq!(
+            revenue.revenue,
+            FeeAmount::from(revenue.to.account.balance - revenue.from.account.balance)
+        );
+
+        // Ranges must not be reversed.
+        client
+            .get::<FeeRevenueQueryData>("fee-state/revenue/3/1")
+            .send()
+            .await
+            .unwrap_err();
+    }
+
+    #[async_std::test]
+    async fn test_fee_account_stream() {
+        setup_logging();
+        setup_backtrace();
+
+        let port = pick_unused_port().expect("No ports free");
+
+        let storage = SqlDataSource::create_storage().await;
+        let options = SqlDataSource::options(
+            &storage,
+            Options::with_port(port)
+                .state(Default::default())
+                .status(Default::default()),
+        );
+
+        let anvil = Anvil::new().spawn();
+        let l1 = anvil.endpoint().parse().unwrap();
+        let _network = TestNetwork::new(
+            options,
+            [no_storage::Options; TestConfig::NUM_NODES],
+            l1,
+            None,
+        )
+        .await;
+        let url = format!("http://localhost:{port}").parse().unwrap();
+        let client: Client<ServerError, SequencerVersion> = Client::new(url);
+        client.connect(None).await;
+
+        // Wait until some blocks have been decided.
+        client
+            .socket("availability/stream/blocks/0")
+            .subscribe::<BlockQueryData<SeqTypes>>()
+            .await
+            .unwrap()
+            .take(2)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        // The builder account is prefunded, so the first message should report a positive balance.
+        let account = TestConfig::builder_key().fee_account();
+        let res = client
+            .socket(&format!("fee-state/account/{account}/stream"))
+            .subscribe::<AccountQueryData>()
+            .await
+            .unwrap()
+            .next()
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(res.balance > 0.into());
+    }
+
+    #[async_std::test]
+    async fn test_catchup_source_timeout() {
+        setup_logging();
+        setup_backtrace();
+
+        let timeout = Duration::from_millis(100);
+        let slow = || async {
+            sleep(Duration::from_secs(60)).await;
+            Ok::<u64, anyhow::Error>(0)
+        };
+        let fast = || async { Ok::<u64, anyhow::Error>(1) };
+        let missing = || async { Err::<u64, _>(anyhow::anyhow!("missing")) };
+
+        let sources = [CatchupSource::Memory, CatchupSource::Storage];
+
+        // A slow memory lookup is abandoned in favor of storage.
+        let start = Instant::now();
+        let res = fetch_from_sources("test", &sources, Some(timeout), |source| match source {
+            CatchupSource::Memory => slow().boxed(),
+            _ => fast().boxed(),
+        })
+        .await
+        .unwrap();
+        assert_eq!(res, 1);
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        // A slow storage query fails the request rather than hanging.
+        let start = Instant::now();
+        fetch_from_sources("test", &sources, Some(timeout), |source| match source {
+            CatchupSource::Memory => missing().boxed(),
+            _ => slow().boxed(),
+        })
+        .await
+        .unwrap_err();
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        // Fast sources are unaffected.
+        let res = fetch_from_sources("test", &sources, Some(timeout), |source| match source {
+            CatchupSource::Memory => fast().boxed(),
+            _ => slow().boxed(),
+        })
+        .await
+        .unwrap();
+        assert_eq!(res, 1);
+    }
+
+    #[async_std::test]
+    async fn test_catchup_source_order() {
+        setup_logging();
+        setup_backtrace();
+
+        let fetch = |source| match source {
+            CatchupSource::Memory => {
+                async { Ok::<_, anyhow::Error>(CatchupSource::Memory) }.boxed()
+            }
+            CatchupSource::Storage => async { Err(anyhow::anyhow!("missing")) }.boxed(),
+            CatchupSource::Peers => async { Ok(CatchupSource::Peers) }.boxed(),
+        };
+
+        // Sources are consulted in the configured order, stopping at the first which succeeds.
+        for (sources, expected_consulted, expected) in [
+            (
+                vec![CatchupSource::Memory, CatchupSource::Storage, CatchupSource::Peers],
+                vec![CatchupSource::Memory],
+                CatchupSource::Memory,
+            ),
+            (
+                vec![CatchupSource::Storage, CatchupSource::Peers, CatchupSource::Memory],
+                vec![CatchupSource::Storage, CatchupSource::Peers],
+                CatchupSource::Peers,
+            ),
+            (
+                vec![CatchupSource::Peers, CatchupSource::Memory],
+                vec![CatchupSource::Peers],
+                CatchupSource::Peers,
+            ),
+        ] {
+            let mut consulted = vec![];
+            let res = fetch_from_sources("test", &sources, None, |source| {
+                consulted.push(source);
+                fetch(source)
+            })
+            .await
+            .unwrap();
+            assert_eq!(res, expected);
+            assert_eq!(consulted, expected_consulted);
+        }
+
+        // If no configured source has the resource, the request fails.
+        fetch_from_sources("test", &[CatchupSource::Storage], None, fetch)
+            .await
+            .unwrap_err();
+
+        // By default, memory is consulted before storage, and peers are not consulted.
+        let opt = options::Query::parse_from(["query"]);
+        assert_eq!(opt.catchup_sources, DEFAULT_CATCHUP_SOURCES);
+        let opt = options::Query::parse_from(["query", "--catchup-sources", "storage,peers"]);
+        assert_eq!(opt.catchup_sources, [CatchupSource::Storage, CatchupSource::Peers]);
+    }
+
+    #[async_std::test]
+    async fn test_catchup_storage_proof_matches_header() {
+        setup_logging();
+        setup_backtrace();
+
+        let port = pick_unused_port().expect("No ports free");
+
+        let storage = SqlDataSource::create_storage().await;
+        let options = SqlDataSource::options(
+            &storage,
+            Options::with_port(port)
+                .catchup(Default::default())
+                .state(Default::default())
+                .status(Default::default()),
+        );
+
+        let anvil = Anvil::new().spawn();
+        let l1 = anvil.endpoint().parse().unwrap();
+        let mut network = TestNetwork::new(
+            options,
+            [no_storage::Options; TestConfig::NUM_NODES],
+            l1,
+            None,
+        )
+        .await;
+        let url = format!("http://localhost:{port}").parse().unwrap();
+        let client: Client<ServerError, SequencerVersion> = Client::new(url);
+        client.connect(None).await;
+
+        // Wait until some blocks have been decided.
+        let blocks = client
+            .socket("availability/stream/blocks/0")
+            .subscribe::<BlockQueryData<SeqTypes>>()
+            .await
+            .unwrap()
+            .take(3)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        // Sleep for a few seconds so that state data is upserted.
+        sleep(Duration::from_secs(5)).await;
+        network.stop_consensus().await;
+
+        let account = TestConfig::builder_key().fee_account();
+        for block in blocks {
+            let height = block.height();
+            let header = block.header();
+
+            // Use a view that consensus has never reached, to force a miss in memory so the
+            // account is served from storage.
+            let res = client
+                .get::<AccountQueryData>(&format!(
+                    "catchup/{height}/{}/account/{account}",
+                    u64::MAX
+                ))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(
+                res.proof.verify(&header.fee_merkle_tree_root).unwrap(),
+                res.balance
+            );
+        }
+    }
+
+    #[async_std::test]
+    async fn test_catchup_from_peers_is_not_forwarded() {
+        setup_logging();
+        setup_backtrace();
+
+        // Serve catchup from peers first, and make this node its own only peer, so that a request
+        // which was forwarded again would loop forever.
+        let port = pick_unused_port().expect("No ports free");
+        let storage = SqlDataSource::create_storage().await;
+        let mut options = SqlDataSource::options(
+            &storage,
+            Options::with_port(port)
+                .catchup(Default::default())
+                .state(Default::default())
+                .status(Default::default()),
+        );
+        options.query.as_mut().unwrap().catchup_sources =
+            vec![CatchupSource::Peers, CatchupSource::Storage];
+
+        let anvil = Anvil::new().spawn();
+        let l1 = anvil.endpoint().parse().unwrap();
+        let mut network = TestNetwork::with_state(
+            options,
+            Default::default(),
+            [no_storage::Options; TestConfig::NUM_NODES],
+            std::array::from_fn(|_| {
+                StatePeers::<SequencerVersion>::from_urls(vec![format!("http://localhost:{port}")
+                    .parse()
+                    .unwrap()])
+            }),
+            l1,
+            None,
+        )
+        .await;
+        let url = format!("http://localhost:{port}").parse().unwrap();
+        let client: Client<ServerError, SequencerVersion> = Client::new(url);
+        client.connect(None).await;
+
+        // Wait until some blocks have been decided.
+        let blocks = client
+            .socket("availability/stream/blocks/0")
+            .subscribe::<BlockQueryData<SeqTypes>>()
+            .await
+            .unwrap()
+            .take(3)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        // Sleep for a few seconds so that state data is upserted.
+        sleep(Duration::from_secs(5)).await;
+        network.stop_consensus().await;
+
+        // The request is forwarded to this node once, which serves it from storage rather than
+        // forwarding it again.
+        let account = TestConfig::builder_key().fee_account();
+        let block = &blocks[2];
+        let height = block.height();
+        let res = async_std::future::timeout(
+            Duration::from_secs(30),
+            client
+                .get::<AccountQueryData>(&format!(
+                    "catchup/{height}/{}/account/{account}",
+                    u64::MAX
+                ))
+                .send(),
+        )
+        .await
+        .expect("catchup request was forwarded in a loop")
+        .unwrap();
+        assert_eq!(
+            res.proof
+                .verify(&block.header().fee_merkle_tree_root)
+                .unwrap(),
+            res.balance
+        );
+
+        let frontier = async_std::future::timeout(
+            Duration::from_secs(30),
+            client
+                .get::<BlocksFrontier>(&format!("catchup/{height}/{}/blocks", u64::MAX))
+                .send(),
+        )
+        .await
+        .expect("catchup request was forwarded in a loop")
+        .unwrap();
+        assert_eq!(*frontier.elem().unwrap(), blocks[1].hash());
+    }
+
+    #[test]
+    fn test_empty_block_tree_frontier() {
+        // The block Merkle tree in the genesis state has no frontier, which is an error rather
+        // than an underflow.
+        let state = ValidatedState::default();
+        assert_eq!(state.block_merkle_tree.num_leaves(), 0);
+        block_tree_frontier(&state.block_merkle_tree).unwrap_err();
+    }
+
+    #[async_std::test]
+    async fn test_replay_state() {
+        setup_logging();
+        setup_backtrace();
+
+        let port = pick_unused_port().expect("No ports free");
+        let storage = SqlDataSource::create_storage().await;
+        let options =
+            SqlDataSource::options(&storage, Options::with_port(port).state(Default::default()));
+
+        let anvil = Anvil::new().spawn();
+        let l1 = anvil.endpoint().parse().unwrap();
+        let mut network = TestNetwork::new(
+            options,
+            [no_storage::Options; TestConfig::NUM_NODES],
+            l1,
+            None,
+        )
+        .await;
+        let url = format!("http://localhost:{port}").parse().unwrap();
+        let client: Client<ServerError, SequencerVersion> = Client::new(url);
+        client.connect(None).await;
+
+        // Wait until some blocks have been decided.
+        let blocks = client
+            .socket("availability/stream/blocks/0")
+            .subscribe::<BlockQueryData<SeqTypes>>()
+            .await
+            .unwrap()
+            .take(3)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        // Sleep for a few seconds so that state data is upserted.
+        sleep(Duration::from_secs(5)).await;
+        network.stop_consensus().await;
+
+        let instance = network.server.node_state().clone();
+        let ds = SqlDataSource::create(
+            SqlDataSource::persistence_options(&storage),
+            Default::default(),
+            false,
+        )
+        .await
+        .unwrap();
+        let ds = Arc::new(RwLock::new(ds));
+        let header = blocks[2].header();
+
+        // Replay from genesis.
+        let state = replay_state(ds.clone(), instance.clone(), 0, 2).await.unwrap();
+        assert_eq!(
+            state.block_merkle_tree.commitment(),
+            header.block_merkle_tree_root
+        );
+        assert_eq!(state.fee_merkle_tree.commitment(), header.fee_merkle_tree_root);
+
+        // Replay from an intermediate block, using merklized state from storage.
+        let state = replay_state(ds, instance, 1, 2).await.unwrap();
+        assert_eq!(state.fee_merkle_tree.commitment(), header.fee_merkle_tree_root);
+    }
+
+    #[async_std::test]
+    async fn test_catchup() {
+        setup_logging();
+        setup_backtrace();
+
+        // Start a sequencer network, using the query service for catchup.
         let port = pick_unused_port().expect("No ports free");
         let anvil = Anvil::new().spawn();
         let l1 = anvil.endpoint().parse().unwrap();
@@ -1144,25 +3406,1120 @@ mod test {
     }
 
     #[async_std::test]
-    pub(crate) async fn test_restart() {
+    async fn test_hotshot_event_streaming_replay() {
+        use hotshot_events_service::events_source::BuilderEvent;
+
         setup_logging();
         setup_backtrace();
 
-        // Initialize nodes.
-        let storage =
-            join_all((0..TestConfig::NUM_NODES).map(|_| SqlDataSource::create_storage())).await;
-        let persistence = storage
-            .iter()
-            .map(<SqlDataSource as TestableSequencerDataSource>::persistence_options)
-            .collect::<Vec<_>>()
-            .try_into()
-            .unwrap();
-        let port = pick_unused_port().unwrap();
+        let port = pick_unused_port().expect("No ports free");
+        let options = Options::with_port(pick_unused_port().expect("No ports free"))
+            .hotshot_events(options::HotshotEvents {
+                events_service_port: port,
+                replay_buffer_size: 10000,
+            });
         let anvil = Anvil::new().spawn();
         let l1 = anvil.endpoint().parse().unwrap();
-        let mut network = TestNetwork::with_state(
-            SqlDataSource::options(&storage[0], Options::with_port(port))
-                .state(Default::default())
+        let _network = TestNetwork::new(
+            options,
+            [no_storage::Options; TestConfig::NUM_NODES],
+            l1,
+            None,
+        )
+        .await;
+
+        let client: Client<ServerError, SequencerVersion> =
+            Client::new(format!("http://localhost:{port}").parse().unwrap());
+        client.connect(None).await;
+
+        // Receive some events (including the startup event), then disconnect.
+        let events = client
+            .socket("hotshot-events/events")
+            .subscribe::<BuilderEvent<SeqTypes>>()
+            .await
+            .unwrap()
+            .take(6)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let last_seen = serde_json::to_value(events.last().unwrap()).unwrap();
+
+        // Wait while events are emitted, then reconnect.
+        sleep(Duration::from_secs(3)).await;
+        let mut events = client
+            .socket("hotshot-events/events")
+            .subscribe::<BuilderEvent<SeqTypes>>()
+            .await
+            .unwrap();
+
+        // Skip the startup event, then find the last event seen before disconnecting among the
+        // replayed events. Everything after it is delivered in order, so nothing was lost.
+        events.next().await.unwrap().unwrap();
+        loop {
+            let event = events.next().await.unwrap().unwrap();
+            if serde_json::to_value(&event).unwrap() == last_seen {
+                break;
+            }
+        }
+        events.next().await.unwrap().unwrap();
+    }
+
+    #[async_std::test]
+    async fn test_retry_with_backoff() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        setup_logging();
+        setup_backtrace();
+
+        // A flaky consensus handle which fails a few times before accepting a transaction.
+        let attempts = AtomicUsize::new(0);
+        let flaky_submit = || async {
+            if attempts.fetch_add(1, Ordering::SeqCst) < 3 {
+                anyhow::bail!("mempool full");
+            }
+            Ok(())
+        };
+
+        // Without a retry timeout, the first failure is returned.
+        retry_with_backoff("submit", None, flaky_submit)
+            .await
+            .unwrap_err();
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+        // With a retry timeout, transient failures are retried until the submission succeeds.
+        attempts.store(0, Ordering::SeqCst);
+        retry_with_backoff("submit", Some(Duration::from_secs(10)), flaky_submit)
+            .await
+            .unwrap();
+        assert_eq!(attempts.load(Ordering::SeqCst), 4);
+
+        // Retries stop once the timeout has passed.
+        let start = Instant::now();
+        let timeout = Duration::from_millis(500);
+        retry_with_backoff("submit", Some(timeout), || async {
+            Err::<(), _>(anyhow::anyhow!("mempool full"))
+        })
+        .await
+        .unwrap_err();
+        assert!(start.elapsed() < timeout + Duration::from_secs(1));
+    }
+
+    #[async_std::test]
+    async fn test_submit_transaction_with_fee() {
+        setup_logging();
+        setup_backtrace();
+
+        let fee = FeeAmount::from(1000);
+        let txn = Transaction::new(Default::default(), vec![1, 2, 3, 4]);
+        let submission = TransactionSubmission::new(txn.clone(), fee);
+        assert_eq!(submission.fee(), Some(fee));
+        assert_eq!(*submission.transaction(), txn);
+
+        // The fee survives both the JSON and the binary encoding of the submission.
+        let json = serde_json::to_value(&submission).unwrap();
+        assert_eq!(
+            serde_json::from_value::<TransactionSubmission>(json).unwrap(),
+            submission
+        );
+        let bytes = bincode::serialize(&submission).unwrap();
+        assert_eq!(
+            bincode::deserialize::<TransactionSubmission>(&bytes).unwrap(),
+            submission
+        );
+
+        let TestNetworkWithClient { network, client, .. } =
+            connected_test_network(|opt| opt.submit(Default::default())).await;
+        let mut events = network.server.event_stream().await;
+        let hash = client
+            .post("submit/submit/with-fee")
+            .body_binary(&submission)
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(txn.commit(), hash);
+
+        // The fee is not part of the transaction handed to consensus.
+        loop {
+            let event = events.next().await.unwrap();
+            let EventType::Transactions { transactions } = event.event else {
+                continue;
+            };
+            if let Some(submitted) = transactions.iter().find(|tx| tx.commit() == hash) {
+                assert_eq!(*submitted, txn);
+                break;
+            }
+        }
+    }
+
+    #[async_std::test]
+    async fn test_submit_namespace_rate_limit() {
+        setup_logging();
+        setup_backtrace();
+
+        let port = pick_unused_port().expect("No ports free");
+        let options = Options::with_port(port).submit(options::Submit {
+            namespace_rate_limit: Some(1),
+            namespace_burst: Some(3),
+            ..Default::default()
+        });
+        let anvil = Anvil::new().spawn();
+        let l1 = anvil.endpoint().parse().unwrap();
+        let _network = TestNetwork::new(
+            options,
+            [no_storage::Options; TestConfig::NUM_NODES],
+            l1,
+            None,
+        )
+        .await;
+
+        let client: Client<ServerError, SequencerVersion> =
+            Client::new(format!("http://localhost:{port}").parse().unwrap());
+        client.connect(None).await;
+        let submit = |ns: u32, i: u8| {
+            let txn = Transaction::new(NamespaceId::from(ns), vec![i]);
+            let client = &client;
+            async move {
+                client
+                    .post::<Commitment<Transaction>>("submit/submit")
+                    .body_json(&txn)
+                    .unwrap()
+                    .send()
+                    .await
+            }
+        };
+
+        // Flood one namespace until it is rate limited.
+        let mut limited = false;
+        for i in 0..20 {
+            if let Err(err) = submit(1, i).await {
+                assert_eq!(err.status, StatusCode::TOO_MANY_REQUESTS, "{err:#}");
+                limited = true;
+                break;
+            }
+        }
+        assert!(limited);
+
+        // Submissions to another namespace still succeed.
+        for i in 0..3 {
+            submit(2, i).await.unwrap();
+        }
+    }
+
+    #[async_std::test]
+    async fn test_submit_max_body_size() {
+        use async_std::{
+            io::{timeout, ReadExt, WriteExt},
+            net::TcpStream,
+        };
+
+        setup_logging();
+        setup_backtrace();
+
+        let TestNetworkWithClient { client, port, .. } = connected_test_network(|opt| {
+            opt.submit(options::Submit {
+                max_body_size: Some(1000),
+                ..Default::default()
+            })
+        })
+        .await;
+
+        // A small transaction is accepted.
+        let txn = Transaction::new(NamespaceId::from(1), vec![1; 100]);
+        client
+            .post::<Commitment<Transaction>>("submit/submit")
+            .body_json(&txn)
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+
+        // A request body over the limit is rejected.
+        let txn = Transaction::new(NamespaceId::from(1), vec![1; 1000]);
+        let err = client
+            .post::<Commitment<Transaction>>("submit/submit")
+            .body_json(&txn)
+            .unwrap()
+            .send()
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, StatusCode::PAYLOAD_TOO_LARGE, "{err:#}");
+
+        // A request declaring a huge body is rejected before the body is read: the server responds
+        // even though we never send the body.
+        let mut stream = TcpStream::connect(("localhost", port)).await.unwrap();
+        stream
+            .write_all(
+                format!(
+                    "POST /submit/submit HTTP/1.1\r\n\
+                     Host: localhost:{port}\r\n\
+                     Content-Type: application/json\r\n\
+                     Content-Length: {}\r\n\r\n",
+                    u32::MAX
+                )
+                .as_bytes(),
+            )
+            .await
+            .unwrap();
+        let mut res = [0; 12];
+        timeout(Duration::from_secs(10), stream.read_exact(&mut res))
+            .await
+            .unwrap();
+        assert_eq!(&res, b"HTTP/1.1 413");
+    }
+
+    #[async_std::test]
+    async fn test_submit_idempotency_key() {
+        use hotshot_types::traits::{block_contents::BlockHeader, BlockPayload};
+
+        setup_logging();
+        setup_backtrace();
+
+        // Allow one submission per namespace per second, so we can tell whether a request with a
+        // repeated key actually submits its transaction.
+        let TestNetworkWithClient { network, client, .. } = connected_test_network(|opt| {
+            opt.submit(options::Submit {
+                namespace_rate_limit: Some(1),
+                namespace_burst: Some(1),
+                ..Default::default()
+            })
+        })
+        .await;
+        let mut events = network.server.event_stream().await;
+        let submit = |tx: Transaction, key: &'static str| {
+            let client = &client;
+            async move {
+                client
+                    .post::<Commitment<Transaction>>("submit/submit")
+                    .header("Idempotency-Key", key)
+                    .body_json(&tx)
+                    .unwrap()
+                    .send()
+                    .await
+            }
+        };
+
+        // Use up the rate limit of namespace 1. A submission which fails does not mark its
+        // transaction as submitted: a retry with the same key fails too, rather than pretending it
+        // was submitted.
+        let txn = Transaction::new(NamespaceId::from(1), vec![1, 2, 3]);
+        submit(Transaction::new(NamespaceId::from(1), vec![0]), "other")
+            .await
+            .unwrap();
+        for _ in 0..2 {
+            let err = submit(txn.clone(), "key").await.unwrap_err();
+            assert_eq!(err.status, StatusCode::TOO_MANY_REQUESTS, "{err:#}");
+        }
+
+        // Once the rate limit allows it, a retry submits the transaction. Further retries are not
+        // submitted again, so they succeed even though the rate limit would reject a submission.
+        sleep(Duration::from_secs(2)).await;
+        for _ in 0..2 {
+            assert_eq!(submit(txn.clone(), "key").await.unwrap(), txn.commit());
+        }
+
+        // Reusing the key for a different transaction in the same namespace is a conflict.
+        let other = Transaction::new(NamespaceId::from(1), vec![4, 5, 6]);
+        let err = submit(other, "key").await.unwrap_err();
+        assert_eq!(err.status, StatusCode::CONFLICT, "{err:#}");
+
+        // Keys are scoped to a namespace, so the key is unaffected in other namespaces.
+        let other = Transaction::new(NamespaceId::from(2), vec![1, 2, 3]);
+        assert_eq!(submit(other.clone(), "key").await.unwrap(), other.commit());
+
+        // Concurrent submissions with the same key both succeed, but submit the transaction once.
+        let concurrent = Transaction::new(NamespaceId::from(3), vec![7, 8, 9]);
+        let (first, second) = future::join(
+            submit(concurrent.clone(), "concurrent"),
+            submit(concurrent.clone(), "concurrent"),
+        )
+        .await;
+        assert_eq!(first.unwrap(), concurrent.commit());
+        assert_eq!(second.unwrap(), concurrent.commit());
+
+        // Wait for the transactions to be sequenced, and check none was sequenced twice.
+        let mut sequenced = vec![];
+        while ![&txn, &concurrent].iter().all(|tx| sequenced.contains(&tx.commit())) {
+            let event = events.next().await.unwrap();
+            let EventType::Decide { leaf_chain, .. } = event.event else {
+                continue;
+            };
+            for LeafInfo { leaf, .. } in leaf_chain.iter().rev() {
+                if let Some(payload) = leaf.block_payload() {
+                    sequenced.extend(
+                        payload.transaction_commitments(leaf.block_header().metadata()),
+                    );
+                }
+            }
+        }
+        for tx in [&txn, &concurrent] {
+            let count = sequenced.iter().filter(|hash| **hash == tx.commit()).count();
+            assert_eq!(count, 1, "{tx:?} sequenced {count} times");
+        }
+
+        // A key which is too long is rejected.
+        let err = client
+            .post::<Commitment<Transaction>>("submit/submit")
+            .header("Idempotency-Key", "k".repeat(1000))
+            .body_json(&txn)
+            .unwrap()
+            .send()
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST, "{err:#}");
+    }
+
+    #[async_std::test]
+    async fn test_block_archive() {
+        setup_logging();
+        setup_backtrace();
+
+        // Rotate after every block, so the archive spans many files.
+        let dir = tempfile::tempdir().unwrap();
+        let port = pick_unused_port().expect("No ports free");
+        let options = Options::with_port(port).block_archive(options::BlockArchive {
+            path: dir.path().into(),
+            max_file_size: 1,
+            max_file_age: None,
+        });
+        let anvil = Anvil::new().spawn();
+        let l1 = anvil.endpoint().parse().unwrap();
+        let mut network = TestNetwork::new(
+            options,
+            [no_storage::Options; TestConfig::NUM_NODES],
+            l1,
+            None,
+        )
+        .await;
+
+        // Wait for a few blocks to be decided, then stop so the archive stops growing.
+        let mut events = network.server.event_stream().await;
+        loop {
+            let event = events.next().await.unwrap();
+            let EventType::Decide { leaf_chain, .. } = event.event else {
+                continue;
+            };
+            if leaf_chain[0].leaf.height() >= 3 {
+                break;
+            }
+        }
+        network.server.shut_down().await;
+
+        // The archived blocks are read back in order, one per file.
+        let blocks = archive::read_archive(dir.path())
+            .unwrap()
+            .collect::<anyhow::Result<Vec<_>>>()
+            .unwrap();
+        assert!(blocks.len() >= 3, "{} blocks archived", blocks.len());
+        for (prev, block) in blocks.iter().zip(&blocks[1..]) {
+            assert_eq!(block.height(), prev.height() + 1);
+        }
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), blocks.len());
+
+        // An incomplete record at the end of a file, as left by a crash, is skipped.
+        let dir = tempfile::tempdir().unwrap();
+        let mut archiver = archive::BlockArchiver::new(dir.path(), u64::MAX, None).unwrap();
+        archiver.append(&blocks[0]).await.unwrap();
+        archiver.append(&blocks[1]).await.unwrap();
+        drop(archiver);
+        let paths = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        assert_eq!(paths.len(), 1);
+        let len = std::fs::metadata(&paths[0]).unwrap().len();
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(&paths[0])
+            .unwrap()
+            .set_len(len - 1)
+            .unwrap();
+        let reread = archive::read_archive(dir.path())
+            .unwrap()
+            .collect::<anyhow::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(reread.len(), 1);
+        assert_eq!(reread[0].height(), blocks[0].height());
+    }
+
+    #[async_std::test]
+    async fn test_graceful_shutdown() {
+        setup_logging();
+        setup_backtrace();
+
+        let port = pick_unused_port().expect("No ports free");
+        let options = Options::with_port(port).submit(Default::default());
+        let anvil = Anvil::new().spawn();
+        let l1 = anvil.endpoint().parse().unwrap();
+        let mut network = TestNetwork::new(
+            options,
+            [no_storage::Options; TestConfig::NUM_NODES],
+            l1,
+            None,
+        )
+        .await;
+        let client: Client<ServerError, SequencerVersion> =
+            Client::new(format!("http://localhost:{port}").parse().unwrap());
+        client.connect(None).await;
+
+        let txn = Transaction::new(Default::default(), vec![1, 2, 3]);
+        client
+            .post::<Commitment<Transaction>>("submit/submit")
+            .body_json(&txn)
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+
+        // Consensus is running, so the node shuts down after the next decide, well within the
+        // grace period. Hold the consensus handle so that the node cannot finish shutting down
+        // until we have checked that it rejects new transactions while draining.
+        let consensus = network.server.consensus();
+        let held = consensus.read().await;
+        let shutting_down = network.server.shutting_down_flag();
+        let start = Instant::now();
+        let draining = async {
+            while !shutting_down.load(Ordering::Relaxed) {
+                sleep(Duration::from_millis(10)).await;
+            }
+            let err = client
+                .post::<Commitment<Transaction>>("submit/submit")
+                .body_json(&txn)
+                .unwrap()
+                .send()
+                .await
+                .unwrap_err();
+            assert_eq!(err.status, StatusCode::SERVICE_UNAVAILABLE, "{err:#}");
+            drop(held);
+        };
+        future::join(
+            network
+                .server
+                .shut_down_gracefully(Duration::from_secs(60)),
+            draining,
+        )
+        .await;
+        assert!(start.elapsed() < Duration::from_secs(60));
+        assert!(network.server.is_shutting_down());
+
+        // New transactions are rejected.
+        network
+            .server
+            .submit_transaction(txn.clone())
+            .await
+            .unwrap_err();
+        client
+            .post::<Commitment<Transaction>>("submit/submit")
+            .body_json(&txn)
+            .unwrap()
+            .send()
+            .await
+            .unwrap_err();
+    }
+
+    #[async_std::test]
+    async fn test_blocks_with_vid_stream() {
+        setup_logging();
+        setup_backtrace();
+
+        let storage = SqlDataSource::create_storage().await;
+        let TestNetworkWithClient { client, .. } =
+            connected_test_network(|opt| SqlDataSource::options(&storage, opt)).await;
+
+        let blocks = client
+            .socket("availability/stream/blocks-with-vid/1")
+            .subscribe::<BlockWithVidQueryData>()
+            .await
+            .unwrap()
+            .take(3)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        for (i, BlockWithVidQueryData { block, vid_common }) in blocks.into_iter().enumerate() {
+            // Each block comes with its own VID common data, which is consistent with the header.
+            assert_eq!(block.height(), i as u64 + 1);
+            assert_eq!(vid_common.height(), block.height());
+            assert_eq!(vid_common.block_hash(), block.hash());
+            VidSchemeType::is_consistent(&block.header().payload_commitment, vid_common.common())
+                .unwrap();
+        }
+    }
+
+    #[async_std::test]
+    async fn test_subscription_limit() {
+        setup_logging();
+        setup_backtrace();
+
+        let storage = SqlDataSource::create_storage().await;
+        let TestNetworkWithClient { client, .. } = connected_test_network(|mut opt| {
+            opt.http.max_subscriptions = Some(1);
+            SqlDataSource::options(&storage, opt)
+        })
+        .await;
+
+        // The limit applies across all streams, including those provided by the query service.
+        let mut blocks = client
+            .socket("availability/stream/blocks/0")
+            .subscribe::<BlockQueryData<SeqTypes>>()
+            .await
+            .unwrap();
+        blocks.next().await.unwrap().unwrap();
+        client
+            .socket("availability/stream/leaves/0")
+            .subscribe::<LeafQueryData<SeqTypes>>()
+            .await
+            .unwrap_err();
+
+        // Requests which do not open a subscription are not limited.
+        client.get::<u64>("node/block-height").send().await.unwrap();
+
+        // Closing the subscription frees up a slot once the server sees the socket close.
+        drop(blocks);
+        let mut retries = 0;
+        let mut leaves = loop {
+            match client
+                .socket("availability/stream/leaves/0")
+                .subscribe::<LeafQueryData<SeqTypes>>()
+                .await
+            {
+                Ok(leaves) => break leaves,
+                Err(err) => {
+                    tracing::info!("subscription not yet released: {err:#}");
+                    retries += 1;
+                    assert!(retries < 20, "subscription was never released");
+                    sleep(Duration::from_millis(500)).await;
+                }
+            }
+        };
+        leaves.next().await.unwrap().unwrap();
+    }
+
+    #[async_std::test]
+    async fn test_proposed_block_stream() {
+        setup_logging();
+        setup_backtrace();
+
+        let port = pick_unused_port().expect("No ports free");
+        let storage = SqlDataSource::create_storage().await;
+        let options = SqlDataSource::options(&storage, Options::with_port(port));
+        let anvil = Anvil::new().spawn();
+        let l1 = anvil.endpoint().parse().unwrap();
+        let _network = TestNetwork::new(
+            options,
+            [no_storage::Options; TestConfig::NUM_NODES],
+            l1,
+            None,
+        )
+        .await;
+        let client: Client<ServerError, SequencerVersion> =
+            Client::new(format!("http://localhost:{port}").parse().unwrap());
+        client.connect(None).await;
+
+        let proposals = client
+            .socket("availability/stream/proposed")
+            .subscribe::<endpoints::ProposedBlockQueryData>()
+            .await
+            .unwrap()
+            .take(5)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        for (prev, proposal) in proposals.iter().zip(&proposals[1..]) {
+            assert!(!proposal.decided);
+            assert!(prev.view < proposal.view);
+        }
+
+        // Without view failures, the proposed block is eventually decided.
+        let proposal = proposals.last().unwrap();
+        let block = client
+            .socket(&format!("availability/stream/blocks/{}", proposal.header.height))
+            .subscribe::<BlockQueryData<SeqTypes>>()
+            .await
+            .unwrap()
+            .next()
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(*block.header(), proposal.header);
+        if let Some(payload) = &proposal.payload {
+            assert_eq!(block.payload(), payload);
+        }
+    }
+
+    #[async_std::test]
+    async fn test_status_proposers() {
+        setup_logging();
+        setup_backtrace();
+
+        let port = pick_unused_port().expect("No ports free");
+        let storage = SqlDataSource::create_storage().await;
+        let options =
+            SqlDataSource::options(&storage, Options::with_port(port).status(Default::default()));
+        let anvil = Anvil::new().spawn();
+        let l1 = anvil.endpoint().parse().unwrap();
+        let _network = TestNetwork::new(
+            options,
+            [no_storage::Options; TestConfig::NUM_NODES],
+            l1,
+            None,
+        )
+        .await;
+        let client: Client<ServerError, SequencerVersion> =
+            Client::new(format!("http://localhost:{port}").parse().unwrap());
+        client.connect(None).await;
+
+        // Wait until some blocks have been decided.
+        let blocks = client
+            .socket("availability/stream/blocks/0")
+            .subscribe::<BlockQueryData<SeqTypes>>()
+            .await
+            .unwrap()
+            .take(3)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        let proposers = client
+            .get::<Vec<endpoints::BlockProposer>>("status/proposers/0/3")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(proposers.len(), blocks.len());
+        for (proposer, block) in proposers.iter().zip(&blocks) {
+            assert_eq!(proposer.height, block.height());
+            assert_eq!(proposer.proposer, block.header().fee_info.account());
+
+            let view = client
+                .get::<ViewNumber>(&format!("status/height/{}/view", block.height()))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(view, proposer.view);
+
+            let l1_commitment = client
+                .get::<U256>(&format!("status/l1-commitment/{}", block.height()))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(l1_commitment, commitment_to_u256(block.hash()));
+        }
+
+        // Invalid range.
+        client
+            .get::<Vec<endpoints::BlockProposer>>("status/proposers/3/0")
+            .send()
+            .await
+            .unwrap_err();
+
+        // The failed views before each block lie between the views of consecutive blocks.
+        let timeouts = client
+            .get::<Vec<endpoints::BlockViewTimeouts>>("status/view-timeouts/0/3")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(timeouts.len(), proposers.len());
+        assert_eq!(timeouts[0].parent_view, None);
+        assert_eq!(timeouts[0].timeouts, 0);
+        for (i, (block, proposer)) in timeouts.iter().zip(&proposers).enumerate() {
+            assert_eq!(block.height, proposer.height);
+            assert_eq!(block.view, proposer.view);
+            if i > 0 {
+                let parent = proposers[i - 1].view;
+                assert_eq!(block.parent_view, Some(parent));
+                assert_eq!(block.timeouts, block.view.u64() - parent.u64() - 1);
+            }
+        }
+        let timeouts = client
+            .get::<Vec<endpoints::BlockViewTimeouts>>("status/view-timeouts/1/3")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(timeouts[0].parent_view, Some(proposers[0].view));
+
+        // Decided views report the height of their block, views skipped between decided views
+        // have failed, and views past the last decided view are pending.
+        for block in &timeouts {
+            let status = client
+                .get::<endpoints::ViewStatus>(&format!("status/view/{}", block.view.u64()))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(
+                status,
+                endpoints::ViewStatus::Decided {
+                    height: block.height
+                }
+            );
+            if let Some(parent_view) = block.parent_view {
+                for view in parent_view.u64() + 1..block.view.u64() {
+                    let status = client
+                        .get::<endpoints::ViewStatus>(&format!("status/view/{view}"))
+                        .send()
+                        .await
+                        .unwrap();
+                    assert_eq!(status, endpoints::ViewStatus::Failed);
+                }
+            }
+        }
+        let status = client
+            .get::<endpoints::ViewStatus>(&format!("status/view/{}", u64::MAX))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(status, endpoints::ViewStatus::Pending);
+    }
+
+    #[async_std::test]
+    async fn test_status_storage_lag() {
+        setup_logging();
+        setup_backtrace();
+
+        let port = pick_unused_port().expect("No ports free");
+        let storage = SqlDataSource::create_storage().await;
+        let options =
+            SqlDataSource::options(&storage, Options::with_port(port).status(Default::default()));
+        let anvil = Anvil::new().spawn();
+        let l1 = anvil.endpoint().parse().unwrap();
+        let mut network = TestNetwork::new(
+            options,
+            [no_storage::Options; TestConfig::NUM_NODES],
+            l1,
+            None,
+        )
+        .await;
+        let client: Client<ServerError, SequencerVersion> =
+            Client::new(format!("http://localhost:{port}").parse().unwrap());
+        client.connect(None).await;
+
+        // Wait until some blocks have been decided.
+        client
+            .socket("availability/stream/blocks/0")
+            .subscribe::<BlockQueryData<SeqTypes>>()
+            .await
+            .unwrap()
+            .take(3)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        let lag = client
+            .get::<endpoints::StorageLag>("status/storage-lag")
+            .send()
+            .await
+            .unwrap();
+        assert!(lag.consensus_height >= 3);
+        assert_eq!(lag.lag, lag.consensus_height.saturating_sub(lag.storage_height));
+
+        // Once consensus stops, storage catches up to the decided height.
+        network.stop_consensus().await;
+        let consensus_height = network.server.decided_leaf().await.height() + 1;
+        let lag = loop {
+            let lag = client
+                .get::<endpoints::StorageLag>("status/storage-lag")
+                .send()
+                .await
+                .unwrap();
+            if lag.lag == 0 {
+                break lag;
+            }
+            tracing::info!(?lag, "waiting for storage to catch up");
+            sleep(Duration::from_secs(1)).await;
+        };
+        assert_eq!(lag.consensus_height, consensus_height);
+        assert_eq!(lag.storage_height, consensus_height);
+    }
+
+    #[async_std::test]
+    async fn test_startup_catchup_timeout() {
+        setup_logging();
+        setup_backtrace();
+
+        let storage = SqlDataSource::create_storage().await;
+        let TestNetworkWithClient { client, .. } = connected_test_network(|opt| {
+            let mut opt = SqlDataSource::options(&storage, opt);
+            let query = opt.query.as_mut().unwrap();
+            query.startup_catchup_target = Some(0);
+            query.startup_catchup_timeout = Duration::from_secs(10);
+            opt
+        })
+        .await;
+
+        // The test network has no state peers, so the node never learns the chain tip. At first,
+        // it reports that it is not caught up, and serves only its status API.
+        let progress = client
+            .get::<CatchupProgress>("status/catchup-progress")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(progress.tip, None);
+        assert!(!progress.caught_up);
+        assert!(!progress.timed_out);
+        let err = client
+            .get::<LeafQueryData<SeqTypes>>("availability/leaf/0")
+            .send()
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, StatusCode::SERVICE_UNAVAILABLE, "{err:#}");
+
+        // Once the timeout expires, it stops waiting and serves its full API without catching up.
+        let progress = loop {
+            let progress = client
+                .get::<CatchupProgress>("status/catchup-progress")
+                .send()
+                .await
+                .unwrap();
+            if progress.timed_out {
+                break progress;
+            }
+            tracing::info!(?progress, "waiting for startup catchup to time out");
+            sleep(Duration::from_secs(1)).await;
+        };
+        assert!(!progress.caught_up);
+        client
+            .get::<LeafQueryData<SeqTypes>>("availability/leaf/0")
+            .send()
+            .await
+            .unwrap();
+    }
+
+    #[async_std::test]
+    async fn test_startup_catchup_target() {
+        setup_logging();
+        setup_backtrace();
+
+        // The chain tip reported by the node's peers, which the node will have to catch up to.
+        let tip = 10;
+
+        let port = pick_unused_port().expect("No ports free");
+        let storage = SqlDataSource::create_storage().await;
+        let mut options = SqlDataSource::options(&storage, Options::with_port(port));
+        options.query.as_mut().unwrap().startup_catchup_target = Some(0);
+        options.query.as_mut().unwrap().startup_catchup_timeout = Duration::from_secs(600);
+        let anvil = Anvil::new().spawn();
+        let l1 = anvil.endpoint().parse().unwrap();
+        let _network = TestNetwork::with_state(
+            options,
+            Default::default(),
+            [no_storage::Options; TestConfig::NUM_NODES],
+            std::array::from_fn(|_| MockStateCatchup::default().with_block_height(tip)),
+            l1,
+            None,
+        )
+        .await;
+        let client: Client<ServerError, SequencerVersion> =
+            Client::new(format!("http://localhost:{port}").parse().unwrap());
+        client.connect(None).await;
+
+        // While the node is behind, it serves only its status API.
+        let progress = client
+            .get::<CatchupProgress>("status/catchup-progress")
+            .send()
+            .await
+            .unwrap();
+        assert!(!progress.caught_up);
+        let err = client
+            .get::<LeafQueryData<SeqTypes>>("availability/leaf/0")
+            .send()
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, StatusCode::SERVICE_UNAVAILABLE, "{err:#}");
+
+        // Once it reaches the tip, the same server switches to serving the full API.
+        let progress = loop {
+            let progress = client
+                .get::<CatchupProgress>("status/catchup-progress")
+                .send()
+                .await
+                .unwrap();
+            if progress.caught_up {
+                break progress;
+            }
+            tracing::info!(?progress, "waiting for node to catch up");
+            sleep(Duration::from_secs(1)).await;
+        };
+        assert_eq!(progress.tip, Some(tip));
+        assert!(progress.block_height >= tip);
+        assert!(!progress.timed_out);
+        let leaf = client
+            .get::<LeafQueryData<SeqTypes>>(&format!("availability/leaf/{}", tip - 1))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(leaf.height(), tip - 1);
+    }
+
+    #[async_std::test]
+    async fn test_startup_catchup_progress_without_target() {
+        setup_logging();
+        setup_backtrace();
+
+        let storage = SqlDataSource::create_storage().await;
+        let TestNetworkWithClient { client, .. } = connected_test_network(|opt| {
+            SqlDataSource::options(&storage, opt.status(Default::default()))
+        })
+        .await;
+
+        // Without a startup catchup target, the node is always caught up.
+        let progress = client
+            .get::<CatchupProgress>("status/catchup-progress")
+            .send()
+            .await
+            .unwrap();
+        assert!(progress.caught_up);
+        client
+            .socket("availability/stream/leaves/0")
+            .subscribe::<LeafQueryData<SeqTypes>>()
+            .await
+            .unwrap()
+            .next()
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[async_std::test]
+    async fn test_batched_writes() {
+        setup_logging();
+        setup_backtrace();
+
+        // Write blocks to storage in batches of several decides at a time.
+        let storage = SqlDataSource::create_storage().await;
+        let TestNetworkWithClient {
+            mut network,
+            client,
+            ..
+        } = connected_test_network(|opt| {
+            let mut opt = SqlDataSource::options(&storage, opt.status(Default::default()));
+            let query = opt.query.as_mut().unwrap();
+            query.write_batch_size = 4;
+            query.write_batch_delay = Duration::from_secs(2);
+            opt
+        })
+        .await;
+
+        // A separate connection to the database only sees committed batches.
+        let ds = SqlDataSource::create(
+            SqlDataSource::persistence_options(&storage),
+            Default::default(),
+            false,
+        )
+        .await
+        .unwrap();
+
+        // Wait until several batches worth of blocks have been decided.
+        let leaves = client
+            .socket("availability/stream/leaves/0")
+            .subscribe::<LeafQueryData<SeqTypes>>()
+            .await
+            .unwrap()
+            .take(10)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        // Stop consensus and wait for the last, partial batch to be committed. Meanwhile, the API
+        // never serves blocks which are not yet committed.
+        network.stop_consensus().await;
+        let height = network.server.decided_leaf().await.height() + 1;
+        let deadline = Instant::now() + Duration::from_secs(30);
+        loop {
+            let served = client
+                .get::<u64>("status/block-height")
+                .send()
+                .await
+                .unwrap();
+            let committed = ds.block_height().await.unwrap() as u64;
+            assert!(served <= committed, "served {served} > committed {committed}");
+            if committed == height {
+                break;
+            }
+            assert!(Instant::now() < deadline, "batch not committed: {committed} < {height}");
+            sleep(Duration::from_millis(100)).await;
+        }
+
+        // Every decided block is persisted.
+        for leaf in leaves {
+            let stored = ds.get_leaf(leaf.height() as usize).await.try_resolve().ok();
+            assert_eq!(stored, Some(leaf));
+        }
+    }
+
+    #[async_std::test]
+    async fn test_status_block_height_without_consensus() {
+        setup_logging();
+        setup_backtrace();
+
+        // Run a network until some blocks have been decided and persisted to storage.
+        let storage = SqlDataSource::create_storage().await;
+        let port = pick_unused_port().unwrap();
+        let anvil = Anvil::new().spawn();
+        let l1 = anvil.endpoint().parse().unwrap();
+        let network = TestNetwork::new(
+            SqlDataSource::options(&storage, Options::with_port(port)).status(Default::default()),
+            [no_storage::Options; TestConfig::NUM_NODES],
+            l1,
+            None,
+        )
+        .await;
+        let client: Client<ServerError, SequencerVersion> =
+            Client::new(format!("http://localhost:{port}").parse().unwrap());
+        client.connect(None).await;
+        client
+            .socket("availability/stream/blocks/0")
+            .subscribe::<BlockQueryData<SeqTypes>>()
+            .await
+            .unwrap()
+            .take(3)
+            .collect::<Vec<_>>()
+            .await;
+        drop(network);
+
+        // Start a server on the same storage whose consensus never initializes. It should still
+        // report the persisted block height.
+        let port = pick_unused_port().unwrap();
+        let server = SqlDataSource::options(&storage, Options::with_port(port))
+            .status(Default::default())
+            .serve::<network::Memory, no_storage::NoStorage, _, _>(
+                |_| future::pending().boxed(),
+                SEQUENCER_VERSION,
+            );
+        let check = async {
+            let client: Client<ServerError, SequencerVersion> =
+                Client::new(format!("http://localhost:{port}").parse().unwrap());
+            client.connect(None).await;
+            client
+                .get::<usize>("status/block-height")
+                .send()
+                .await
+                .unwrap()
+        };
+        let height = match future::select(Box::pin(server), Box::pin(check)).await {
+            future::Either::Left(_) => panic!("server exited before consensus was initialized"),
+            future::Either::Right((height, _)) => height,
+        };
+        assert!(height >= 3, "block height {height} not served from storage");
+    }
+
+    #[async_std::test]
+    pub(crate) async fn test_restart() {
+        setup_logging();
+        setup_backtrace();
+
+        // Initialize nodes.
+        let storage =
+            join_all((0..TestConfig::NUM_NODES).map(|_| SqlDataSource::create_storage())).await;
+        let persistence = storage
+            .iter()
+            .map(<SqlDataSource as TestableSequencerDataSource>::persistence_options)
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+        let port = pick_unused_port().unwrap();
+        let anvil = Anvil::new().spawn();
+        let l1 = anvil.endpoint().parse().unwrap();
+        let mut network = TestNetwork::with_state(
+            SqlDataSource::options(&storage[0], Options::with_port(port))
+                .state(Default::default())
                 .status(Default::default()),
             Default::default(),
             persistence,
@@ -1212,6 +4569,26 @@ mod test {
             .unwrap();
         let decided_view = chain.last().unwrap().leaf().view_number();
 
+        // The same chain can be downloaded page by page, without a subscription.
+        let mut pages = vec![];
+        for from in (0..height).step_by(3) {
+            let to = std::cmp::min(from + 3, height);
+            let page: Vec<LeafQueryData<SeqTypes>> = client
+                .get(&format!("availability/leaves/{from}/{to}"))
+                .send()
+                .await
+                .unwrap();
+            pages.extend(page);
+        }
+        assert_eq!(chain, pages);
+
+        // Oversized ranges must be paginated.
+        client
+            .get::<Vec<LeafQueryData<SeqTypes>>>("availability/leaves/0/1000")
+            .send()
+            .await
+            .unwrap_err();
+
         // Get the most recent state, for catchup.
 
         let state = network.server.decided_state().await;
@@ -0,0 +1,280 @@
+//! Archive decided blocks to local files.
+//!
+//! The archive is a lightweight, durable log of decided blocks which is independent of the
+//! queryable store. The archiver follows the HotShot event stream and appends each decided block,
+//! in order of increasing height, to a file in the archive directory. Each record is the byte
+//! length of the block as a little-endian `u64`, followed by the block serialized with `bincode`.
+//! Records are synced to disk as they are written.
+//!
+//! Files are named after the height of the first block they contain, so that reading them in
+//! lexicographic order yields blocks in the order they were archived. A new file is started when
+//! the current one reaches a maximum size or age, and whenever the archiver restarts. Thus a record
+//! left incomplete by a crash is always at the end of a file, and the reader simply skips it.
+//!
+//! If a block cannot be written, the archiver retries it in a new file until it succeeds, holding
+//! back later blocks. The archive may still have gaps: a decided block is only archived if its
+//! payload is included in the decide event, which is not the case if this node never received it,
+//! and blocks decided while the archiver was not running are not archived. A block may also be
+//! archived more than once if the node restarts shortly after deciding it. Consumers should
+//! therefore be prepared to find gaps and to deduplicate blocks by height.
+
+use crate::{
+    catchup::{backoff, MIN_RETRY_DELAY},
+    SeqTypes,
+};
+use anyhow::Context;
+use async_std::task::{sleep, spawn_blocking};
+use futures::stream::{Stream, StreamExt};
+use hotshot::types::{Event, EventType};
+use hotshot_query_service::availability::BlockQueryData;
+use hotshot_types::event::LeafInfo;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufReader, Read, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Byte length of the length prefix of each record in an archive file.
+const LEN_PREFIX_BYTE_LEN: u64 = 8;
+
+/// File extension of archive files.
+const ARCHIVE_FILE_EXTENSION: &str = "blocks";
+
+/// Appends decided blocks to files in an archive directory.
+#[derive(Debug)]
+pub struct BlockArchiver {
+    dir: PathBuf,
+    max_file_size: u64,
+    max_file_age: Option<Duration>,
+    current: Option<ArchiveFile>,
+}
+
+#[derive(Debug)]
+struct ArchiveFile {
+    file: File,
+    size: u64,
+    created: Instant,
+}
+
+impl BlockArchiver {
+    /// Archive blocks in `dir`, creating it if it does not exist.
+    ///
+    /// A new file is started once the current file reaches `max_file_size` bytes or, if
+    /// `max_file_age` is set, once it has been open for that long.
+    pub fn new(
+        dir: impl Into<PathBuf>,
+        max_file_size: u64,
+        max_file_age: Option<Duration>,
+    ) -> anyhow::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)
+            .context(format!("creating archive directory {}", dir.display()))?;
+        Ok(Self {
+            dir,
+            max_file_size,
+            max_file_age,
+            current: None,
+        })
+    }
+
+    /// Append `block` to the archive.
+    ///
+    /// When this function returns successfully, the block has been synced to disk. If it fails, the
+    /// current file may end with an incomplete record, so the next block is appended to a new file.
+    pub async fn append(&mut self, block: &BlockQueryData<SeqTypes>) -> anyhow::Result<()> {
+        let bytes = bincode::serialize(block).context("serializing block")?;
+        let height = block.height();
+        let mut current = self
+            .current
+            .take()
+            .filter(|current| !self.is_full(current));
+        let dir = self.dir.clone();
+
+        // Writing and syncing the file blocks, so do it off the async executor.
+        let (current, res) = spawn_blocking(move || {
+            let res = append_record(&dir, &mut current, height, &bytes);
+            (current, res)
+        })
+        .await;
+        if res.is_ok() {
+            self.current = current;
+        }
+        res
+    }
+
+    fn is_full(&self, file: &ArchiveFile) -> bool {
+        file.size >= self.max_file_size
+            || self
+                .max_file_age
+                .is_some_and(|age| file.created.elapsed() >= age)
+    }
+}
+
+/// Append a record containing `bytes`, the block at `height`, to the `current` archive file.
+///
+/// If there is no current file, a new one is created in `dir`.
+fn append_record(
+    dir: &Path,
+    current: &mut Option<ArchiveFile>,
+    height: u64,
+    bytes: &[u8],
+) -> anyhow::Result<()> {
+    if current.is_none() {
+        *current = Some(create_file(dir, height)?);
+    }
+    let current = current.as_mut().unwrap();
+
+    current
+        .file
+        .write_all(&(bytes.len() as u64).to_le_bytes())?;
+    current.file.write_all(bytes)?;
+    current.file.sync_data()?;
+    current.size += LEN_PREFIX_BYTE_LEN + bytes.len() as u64;
+    Ok(())
+}
+
+/// Create a new archive file in `dir`, starting with the block at `height`.
+fn create_file(dir: &Path, height: u64) -> anyhow::Result<ArchiveFile> {
+    // Include the creation time in the name, so that a file started at the same height after a
+    // restart does not clobber an existing one.
+    let created = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let path = dir.join(format!("{height:020}-{created:020}.{ARCHIVE_FILE_EXTENSION}"));
+    tracing::info!(height, path = %path.display(), "starting new archive file");
+
+    let file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .context(format!("creating archive file {}", path.display()))?;
+    Ok(ArchiveFile {
+        file,
+        size: 0,
+        created: Instant::now(),
+    })
+}
+
+/// Read back the blocks in the archive directory `dir`, in the order they were archived.
+pub fn read_archive(dir: impl AsRef<Path>) -> anyhow::Result<ArchiveReader> {
+    let dir = dir.as_ref();
+    let mut paths = vec![];
+    for entry in fs::read_dir(dir).context(format!("reading directory {}", dir.display()))? {
+        let path = entry?.path();
+        if path.extension() == Some(ARCHIVE_FILE_EXTENSION.as_ref()) {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(ArchiveReader {
+        paths: paths.into_iter(),
+        current: None,
+    })
+}
+
+/// An iterator over the blocks in an archive directory.
+///
+/// Created by [`read_archive`].
+#[derive(Debug)]
+pub struct ArchiveReader {
+    paths: std::vec::IntoIter<PathBuf>,
+    current: Option<(PathBuf, BufReader<File>)>,
+}
+
+impl Iterator for ArchiveReader {
+    type Item = anyhow::Result<BlockQueryData<SeqTypes>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.current.is_none() {
+                let path = self.paths.next()?;
+                match File::open(&path) {
+                    Ok(file) => self.current = Some((path, BufReader::new(file))),
+                    Err(err) => {
+                        return Some(
+                            Err(err).context(format!("opening archive file {}", path.display())),
+                        );
+                    }
+                }
+            }
+            let (path, reader) = self.current.as_mut().unwrap();
+            match read_record(reader) {
+                Ok(Some(block)) => return Some(Ok(block)),
+                Ok(None) => {
+                    // End of this file, move on to the next one.
+                    self.current = None;
+                }
+                Err(err) => {
+                    let err = err.context(format!("reading archive file {}", path.display()));
+                    self.current = None;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+/// Read the next record from an archive file.
+///
+/// Returns [`None`] at the end of the file, including if the file ends with an incomplete record,
+/// which happens if the archiver crashed while writing it.
+fn read_record(reader: &mut impl Read) -> anyhow::Result<Option<BlockQueryData<SeqTypes>>> {
+    let Some(len) = read_up_to(reader, LEN_PREFIX_BYTE_LEN)? else {
+        return Ok(None);
+    };
+    let len = u64::from_le_bytes(len.try_into().unwrap());
+    let Some(bytes) = read_up_to(reader, len)? else {
+        return Ok(None);
+    };
+    let block = bincode::deserialize(&bytes).context("deserializing block")?;
+    Ok(Some(block))
+}
+
+/// Read exactly `len` bytes, or [`None`] if the reader ends first.
+fn read_up_to(reader: &mut impl Read, len: u64) -> anyhow::Result<Option<Vec<u8>>> {
+    // Read via `take` rather than allocating `len` bytes up front, in case a corrupted length
+    // prefix is very large.
+    let mut bytes = vec![];
+    reader.take(len).read_to_end(&mut bytes)?;
+    if (bytes.len() as u64) < len {
+        return Ok(None);
+    }
+    Ok(Some(bytes))
+}
+
+pub(super) async fn archive_loop(
+    mut archiver: BlockArchiver,
+    mut events: impl Stream<Item = Event<SeqTypes>> + Unpin,
+) {
+    tracing::info!(dir = %archiver.dir.display(), "archiving decided blocks");
+
+    while let Some(event) = events.next().await {
+        let EventType::Decide { leaf_chain, .. } = event.event else {
+            continue;
+        };
+
+        // The leaf chain is ordered from newest to oldest; archive in order of increasing height.
+        for LeafInfo { leaf, .. } in leaf_chain.iter().rev() {
+            let height = leaf.height();
+            let Some(payload) = leaf.block_payload() else {
+                tracing::warn!(
+                    height,
+                    "decided leaf is missing its payload, leaving a gap in the archive"
+                );
+                continue;
+            };
+            let block = BlockQueryData::new(leaf.block_header().clone(), payload);
+
+            // Retry until the block is archived, rather than leaving a gap in the archive.
+            let mut delay = MIN_RETRY_DELAY;
+            while let Err(err) = archiver.append(&block).await {
+                tracing::error!(height, ?delay, "failed to archive block, retrying: {err:#}");
+                sleep(delay).await;
+                delay = backoff(delay);
+            }
+        }
+    }
+    tracing::warn!("end of HotShot event stream, block archive task will exit");
+}
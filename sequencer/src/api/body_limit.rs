@@ -0,0 +1,60 @@
+//! Limit the size of submission request bodies before they are read.
+//!
+//! Route handlers only see a request once the whole body has been read into memory, so a limit
+//! enforced by a handler does not stop a client from making the server buffer an arbitrarily large
+//! body. Instead, [`SubmitBodyLimit`] is added as middleware to the
+//! [`ApiListener`](super::listener::ApiListener) serving the API, so it runs in front of every
+//! handler. Submission requests whose declared `Content-Length` exceeds the limit are rejected
+//! without reading the body. Bodies without a declared length are cut off after one byte more than
+//! the limit, leaving the handler to reject the oversized body.
+
+use super::listener::api_module;
+use async_trait::async_trait;
+use futures::{future::BoxFuture, AsyncReadExt};
+use std::{
+    fmt::{self, Debug, Formatter},
+    sync::Arc,
+};
+use tide::{http::Body, Middleware, Next, Request, Response, StatusCode};
+
+/// The maximum size in bytes of the body of a submission request.
+///
+/// The limit is looked up for each request, since it may depend on the current chain config.
+#[derive(Clone)]
+pub(super) struct SubmitBodyLimit(Arc<dyn Fn() -> BoxFuture<'static, u64> + Send + Sync>);
+
+impl SubmitBodyLimit {
+    pub(super) fn new(max: impl Fn() -> BoxFuture<'static, u64> + Send + Sync + 'static) -> Self {
+        Self(Arc::new(max))
+    }
+}
+
+impl Debug for SubmitBodyLimit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SubmitBodyLimit").finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl<State: Clone + Send + Sync + 'static> Middleware<State> for SubmitBodyLimit {
+    async fn handle(&self, mut req: Request<State>, next: Next<'_, State>) -> tide::Result {
+        if api_module(req.url().path()) != Some("submit") {
+            return Ok(next.run(req).await);
+        }
+
+        let max = (self.0)().await;
+        match req.len() {
+            Some(len) if len as u64 > max => {
+                let mut res = Response::new(StatusCode::PayloadTooLarge);
+                res.set_body(format!("request body of {len} bytes exceeds the maximum of {max}"));
+                return Ok(res);
+            }
+            Some(_) => {}
+            None => {
+                let body = req.take_body();
+                req.set_body(Body::from_reader(body.take(max + 1), None));
+            }
+        }
+        Ok(next.run(req).await)
+    }
+}
@@ -3,17 +3,22 @@ use std::{num::NonZeroUsize, time::Duration};
 use super::{
     fs,
     options::{Options, Query},
-    sql, AccountQueryData, BlocksFrontier,
+    sql, AccountQueryData, BlockMerkleProofQueryData, BlocksFrontier, LatestAccountQueryData,
+    RejectedTransaction, SignedAccountQueryData,
 };
 use crate::{
     network,
     persistence::{self, SequencerPersistence},
-    PubKey, SeqTypes, Transaction,
+    state::BlockMerkleCommitment,
+    state_signature::StateSignatureBundle,
+    ChainConfig, PubKey, SeqTypes, Transaction, TransactionSubmission,
 };
 use anyhow::bail;
 use async_trait::async_trait;
+use committable::Commitment;
 use ethers::prelude::Address;
-use futures::future::Future;
+use futures::{future::Future, stream::BoxStream};
+use hotshot_contract_adapter::light_client::ParsedLightClientState;
 use hotshot_query_service::{
     availability::AvailabilityDataSource,
     data_source::{MetricsDataSource, UpdateDataSource, VersionedDataSource},
@@ -22,8 +27,8 @@ use hotshot_query_service::{
     status::StatusDataSource,
 };
 use hotshot_types::{
-    data::ViewNumber, light_client::StateSignatureRequestBody, ExecutionType, HotShotConfig,
-    PeerConfig, ValidatorConfig,
+    data::ViewNumber, light_client::StateSignatureRequestBody, utils::BuilderCommitment,
+    ExecutionType, HotShotConfig, PeerConfig, ValidatorConfig,
 };
 
 use serde::Serialize;
@@ -69,6 +74,18 @@ pub trait SequencerDataSource:
 
     /// Instantiate a data source from command line options.
     async fn create(opt: Self::Options, provider: Provider, reset: bool) -> anyhow::Result<Self>;
+
+    /// Get the height of the block whose header has the given builder commitment.
+    ///
+    /// Blocks with the same payload, such as empty blocks, share a builder commitment, in which
+    /// case the height of the latest such block is returned. This is only supported by data sources
+    /// which index headers by builder commitment.
+    async fn get_block_height_by_builder_commitment(
+        &self,
+        _commitment: &BuilderCommitment,
+    ) -> anyhow::Result<u64> {
+        bail!("lookup by builder commitment is not supported for this data source");
+    }
 }
 
 /// Provider for fetching missing data for the query service.
@@ -88,16 +105,54 @@ pub fn provider<Ver: StaticVersionType + 'static>(
 }
 
 pub(crate) trait SubmitDataSource<N: network::Type, P: SequencerPersistence> {
-    fn submit(&self, tx: Transaction) -> impl Send + Future<Output = anyhow::Result<()>>;
+    fn submit(
+        &self,
+        submission: TransactionSubmission,
+    ) -> impl Send + Future<Output = anyhow::Result<()>>;
+
+    /// Submit a transaction, unless it was recently submitted with the same idempotency `key`.
+    ///
+    /// Keys are scoped to the namespace of the transaction. If `key` was already used for the same
+    /// transaction, the transaction is not submitted again, and this waits for the original
+    /// submission if it is still in flight. Fails with [`IdempotencyKeyConflict`] if `key` was
+    /// already used for a different transaction in the same namespace.
+    ///
+    /// [`IdempotencyKeyConflict`]: super::IdempotencyKeyConflict
+    fn submit_idempotent(
+        &self,
+        key: String,
+        submission: TransactionSubmission,
+    ) -> impl Send + Future<Output = anyhow::Result<Commitment<Transaction>>>;
+
+    /// The most recently rejected submissions, oldest first, or `None` if they are not recorded.
+    fn rejections(&self) -> impl Send + Future<Output = Option<Vec<RejectedTransaction>>>;
+
+    /// The maximum size in bytes of the body of a submission request.
+    fn max_submit_body_size(&self) -> impl Send + Future<Output = u64>;
 }
 
 pub(crate) trait HotShotConfigDataSource {
     fn get_config(&self) -> impl Send + Future<Output = PublicHotShotConfig>;
+    fn get_chain_config(&self) -> impl Send + Future<Output = ChainConfig>;
+
+    /// Subscribe to the chain config, starting with the current one.
+    fn chain_config_stream(&self) -> impl Send + Future<Output = BoxStream<'static, ChainConfig>>;
+    fn get_light_client_genesis(&self) -> impl Send + Future<Output = ParsedLightClientState>;
+}
+
+pub(crate) trait ConsensusDataSource {
+    /// The number of blocks decided by consensus, which may be ahead of the blocks in storage.
+    fn decided_block_height(&self) -> impl Send + Future<Output = u64>;
+
+    /// The height and view of the most recently decided leaf.
+    fn last_decided(&self) -> impl Send + Future<Output = (u64, ViewNumber)>;
 }
 
 #[async_trait]
 pub(crate) trait StateSignatureDataSource<N: network::Type> {
     async fn get_state_signature(&self, height: u64) -> Option<StateSignatureRequestBody>;
+
+    async fn get_state_signature_bundle(&self, height: u64) -> Option<StateSignatureBundle>;
 }
 
 pub(crate) trait CatchupDataSource {
@@ -141,6 +196,88 @@ pub(crate) trait CatchupDataSource {
             bail!("merklized state catchup is not supported for this data source");
         }
     }
+
+    /// Get the state of the requested `account`, without forwarding the request to peers.
+    ///
+    /// This is like [`get_account`](Self::get_account), but only consults this node's own state.
+    /// Catchup requests from peers are served this way, so that a request is forwarded at most once
+    /// and cannot loop between nodes which catch up from each other.
+    fn get_local_account(
+        &self,
+        height: u64,
+        view: ViewNumber,
+        account: Address,
+    ) -> impl Send + Future<Output = anyhow::Result<AccountQueryData>> {
+        self.get_account(height, view, account)
+    }
+
+    /// Get the blocks Merkle tree frontier, without forwarding the request to peers.
+    ///
+    /// This is like [`get_frontier`](Self::get_frontier), but only consults this node's own state.
+    fn get_local_frontier(
+        &self,
+        height: u64,
+        view: ViewNumber,
+    ) -> impl Send + Future<Output = anyhow::Result<BlocksFrontier>> {
+        self.get_frontier(height, view)
+    }
+
+    /// Get a proof that the header at `height` is in the latest stored block Merkle tree.
+    fn get_block_proof(
+        &self,
+        _height: u64,
+    ) -> impl Send + Future<Output = anyhow::Result<BlockMerkleProofQueryData>> {
+        async {
+            bail!("block Merkle proofs are not supported for this data source");
+        }
+    }
+
+    /// Get the state of the requested `account` in the most recent state available.
+    ///
+    /// Unlike [`get_account`](Self::get_account), the caller does not need to know a view for
+    /// which state is available. The height and view of the state used are returned along with the
+    /// account.
+    fn get_latest_account(
+        &self,
+        _account: Address,
+    ) -> impl Send + Future<Output = anyhow::Result<LatestAccountQueryData>> {
+        // The latest state is only available from consensus memory.
+        async {
+            bail!("latest state catchup is not supported for this data source");
+        }
+    }
+
+    /// Get the state of the requested `account` in the state whose block Merkle tree has root
+    /// `root`.
+    ///
+    /// This lets a client which has verified `root` read state consistent with exactly that
+    /// version of the tree, even if the chain has moved on or a different block was decided at the
+    /// same height. The block height of the state is `root.size()`. Fails if the requested version
+    /// of the state is not retained.
+    fn get_account_at_root(
+        &self,
+        _root: BlockMerkleCommitment,
+        _account: Address,
+    ) -> impl Send + Future<Output = anyhow::Result<AccountQueryData>> {
+        async {
+            bail!("state catchup by root is not supported for this data source");
+        }
+    }
+
+    /// Get the state of the requested `account` in the most recent state signed by this node.
+    ///
+    /// This serves the best available verifiable state when consensus is not making progress and
+    /// catchup from recent views is failing. The state may be stale: the height of the signed
+    /// state is returned, along with the signature, so the caller can decide whether it is recent
+    /// enough.
+    fn get_signed_account(
+        &self,
+        _account: Address,
+    ) -> impl Send + Future<Output = anyhow::Result<SignedAccountQueryData>> {
+        async {
+            bail!("signed state catchup is not supported for this data source");
+        }
+    }
 }
 
 impl CatchupDataSource for MetricsDataSource {}
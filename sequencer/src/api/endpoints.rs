@@ -2,40 +2,72 @@
 
 use serde::de::Error as _;
 use std::{
-    collections::{BTreeSet, HashMap},
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet, HashMap},
     env,
+    time::Duration,
 };
 
 use super::{
     data_source::{
-        CatchupDataSource, HotShotConfigDataSource, SequencerDataSource, StateSignatureDataSource,
-        SubmitDataSource,
+        CatchupDataSource, ConsensusDataSource, HotShotConfigDataSource, SequencerDataSource,
+        StateSignatureDataSource, SubmitDataSource,
     },
-    StorageState,
+    account_balance_changes, AccountQueryData, BlocksFrontier, IdempotencyKeyConflict,
+    NamespaceRateLimited, NodeShuttingDown, StorageState, ACCOUNT_STREAM_MAX_ATTEMPTS,
+    ACCOUNT_STREAM_RETRY_DELAY, CATCHUP_FORMAT_VERSION, CATCHUP_NO_FORWARD_HEADER,
 };
 use crate::{
-    block::NsProof, network, persistence::SequencerPersistence, NamespaceId, SeqTypes, Transaction,
+    block::{Index, NsProof, PayloadByteLen, TxProof},
+    network,
+    persistence::SequencerPersistence,
+    state::{
+        validate_builder_fee, BlockMerkleCommitment, FeeAccount, FeeAmount, FeeInfo,
+        FeeMerkleTree,
+    },
+    Header, NamespaceId, Payload, PubKey, SeqTypes, Transaction, TransactionSubmission,
 };
-use anyhow::Result;
+use anyhow::{bail, ensure, Result};
 use async_std::sync::{Arc, RwLock};
-use committable::Committable;
-use futures::{try_join, FutureExt};
+use committable::{Commitment, Committable};
+use ethers::types::U256;
+use futures::{
+    stream::{self, Stream, StreamExt, TryStreamExt},
+    try_join, FutureExt, TryFutureExt,
+};
+use hotshot::types::{Event, EventType};
 use hotshot_query_service::{
-    availability::{self, AvailabilityDataSource, CustomSnafu, FetchBlockSnafu},
+    availability::{
+        self, AvailabilityDataSource, BlockQueryData, CustomSnafu, FetchBlockSnafu,
+        FetchLeafSnafu, FetchTransactionSnafu, LeafQueryData, QueryablePayload,
+        VidCommonQueryData,
+    },
     data_source::storage::ExplorerStorage,
-    explorer::{self},
+    explorer::{self, ExplorerHeader},
     merklized_state::{
         self, MerklizedState, MerklizedStateDataSource, MerklizedStateHeightPersistence,
     },
-    node, Error,
+    node::{self, NodeDataSource},
+    status,
+    types::HeightIndexed,
+    Error,
+};
+use hotshot_types::{
+    data::ViewNumber,
+    traits::{node_implementation::ConsensusTime, BlockPayload},
+    utils::BuilderCommitment,
+    vid::{VidCommon, VidSchemeType},
 };
-use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime};
+use jf_merkle_tree::MerkleTreeScheme;
+use jf_vid::VidScheme;
+use num_traits::CheckedSub;
+use sequencer_utils::commitment_to_u256;
 use serde::{Deserialize, Serialize};
 use snafu::OptionExt;
 use tagged_base64::TaggedBase64;
 use tide_disco::{
     method::{ReadState, WriteState},
-    Api, Error as _, StatusCode,
+    Api, Error as _, RequestError, RequestParams, StatusCode,
 };
 
 use vbs::version::StaticVersionType;
@@ -46,16 +78,366 @@ pub struct NamespaceProofQueryData {
     pub transactions: Vec<Transaction>,
 }
 
+/// A [`NamespaceProofQueryData`] with the raw bytes of each transaction.
+///
+/// This is a separate response type, rather than an optional field of [`NamespaceProofQueryData`],
+/// so that the binary encoding of namespace proof responses is unchanged for existing clients.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NamespaceRawProofQueryData {
+    pub proof: Option<NsProof>,
+    pub transactions: Vec<Transaction>,
+    /// The raw bytes of each transaction, in the same order as `transactions`.
+    pub raw_transactions: Vec<RawTransaction>,
+}
+
+/// The payload bytes of a transaction, exactly as stored in its namespace payload.
+///
+/// Clients which do not trust the decoding of [`Transaction`]s can recompute transaction
+/// commitments from these bytes.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RawTransaction(#[serde(with = "base64_bytes")] pub Vec<u8>);
+
+/// The fee paid by the builder of a block, with the header which proves it.
+///
+/// A header records its builder's fee in `fee_info`, signed by the builder's fee account, and is
+/// only valid if that fee was charged to the builder's account. So a client which trusts the
+/// commitment of a header can confirm the builder's payment by checking the header against it and
+/// checking the builder's signature over the fee.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BuilderFeeQueryData {
+    pub header: Header,
+    pub fee: FeeInfo,
+}
+
+impl BuilderFeeQueryData {
+    /// Verify this fee against a trusted header commitment.
+    ///
+    /// On success, returns the fee paid by the builder of the committed block.
+    pub fn verify(&self, header: Commitment<Header>) -> anyhow::Result<FeeInfo> {
+        ensure!(
+            self.header.commit() == header,
+            "header {} does not match commitment {header}",
+            self.header.commit()
+        );
+        ensure!(
+            self.fee == self.header.fee_info,
+            "fee {:?} does not match header fee {:?}",
+            self.fee,
+            self.header.fee_info
+        );
+        validate_builder_fee(&self.header)
+            .map_err(|err| anyhow::anyhow!("invalid builder fee: {err}"))?;
+        Ok(self.fee)
+    }
+}
+
+/// A proof that a block contains no transactions.
+///
+/// The VID common data of a block records the byte length of its payload and is bound to the
+/// payload commitment in the header. A payload of zero bytes has no transactions in any namespace,
+/// whatever the namespace table declares.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EmptyBlockProof {
+    pub header: Header,
+    pub vid_common: VidCommon,
+}
+
+impl EmptyBlockProof {
+    /// Verify that the block committed to by a trusted header commitment is empty.
+    pub fn verify(&self, header: Commitment<Header>) -> anyhow::Result<()> {
+        ensure!(
+            self.header.commit() == header,
+            "header {} does not match commitment {header}",
+            self.header.commit()
+        );
+        VidSchemeType::is_consistent(&self.header.payload_commitment, &self.vid_common)
+            .map_err(|err| {
+                anyhow::anyhow!("VID common data does not match payload commitment: {err:?}")
+            })?;
+        let payload_byte_len = PayloadByteLen::from_vid_common(&self.vid_common).as_usize();
+        ensure!(
+            payload_byte_len == 0,
+            "block payload has {payload_byte_len} bytes"
+        );
+        Ok(())
+    }
+}
+
+/// The size of a block, as reported by its VID common data and namespace table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockSizeQueryData {
+    /// Total byte length of the payload, excluding the namespace table.
+    pub payload_byte_len: PayloadByteLen,
+    /// The number of distinct namespaces in the block.
+    pub num_namespaces: usize,
+}
+
+/// A namespace proof bundled with the data needed to verify it against a header commitment.
+///
+/// A [`NamespaceProofQueryData`] verifies against fields of the block header and the VID common
+/// data. This bundle carries both, so a client which trusts only the commitment of a header can
+/// check the inclusion of a namespace in that block or, if `proof` is `None`, its absence.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NamespaceProofBundle {
+    pub header: Header,
+    pub vid_common: VidCommon,
+    pub proof: Option<NsProof>,
+    pub transactions: Vec<Transaction>,
+}
+
+impl NamespaceProofBundle {
+    /// Verify this bundle against a trusted header commitment.
+    ///
+    /// On success, returns the transactions in namespace `ns_id` of the committed block, which is
+    /// empty if the namespace is not present in the block.
+    pub fn verify(
+        &self,
+        header: Commitment<Header>,
+        ns_id: NamespaceId,
+    ) -> anyhow::Result<Vec<Transaction>> {
+        ensure!(
+            self.header.commit() == header,
+            "header {} does not match commitment {header}",
+            self.header.commit()
+        );
+        match (&self.proof, self.header.ns_table.find_ns_id(&ns_id)) {
+            (Some(proof), Some(_)) => {
+                let (transactions, proven_ns_id) = proof
+                    .verify(
+                        &self.header.ns_table,
+                        &self.header.payload_commitment,
+                        &self.vid_common,
+                    )
+                    .ok_or_else(|| anyhow::anyhow!("invalid namespace proof"))?;
+                ensure!(
+                    proven_ns_id == ns_id,
+                    "proof is for namespace {proven_ns_id}, not {ns_id}"
+                );
+                ensure!(transactions == self.transactions, "transactions do not match proof");
+                Ok(transactions)
+            }
+            (None, None) => {
+                ensure!(
+                    self.transactions.is_empty(),
+                    "transactions given for namespace {ns_id} which is not in the block"
+                );
+                Ok(vec![])
+            }
+            (Some(_), None) => bail!("proof given for namespace {ns_id} which is not in the block"),
+            (None, Some(_)) => bail!("missing proof for namespace {ns_id}"),
+        }
+    }
+}
+
+/// A transaction in a block, along with its namespace and its position in the block.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BlockTransactionQueryData {
+    pub namespace: NamespaceId,
+    pub index: Index,
+    pub transaction: Transaction,
+}
+
+/// A transaction along with its position in a decided block and a proof of its inclusion.
+///
+/// `proof` verifies against the namespace table and payload commitment of the header at `height`,
+/// using the VID common data for that block.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TransactionProofQueryData {
+    pub transaction: Transaction,
+    pub index: Index,
+    pub height: u64,
+    pub proof: TxProof,
+}
+
+/// A decided block along with the VID common data needed to verify proofs about it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BlockWithVidQueryData {
+    pub block: BlockQueryData<SeqTypes>,
+    pub vid_common: VidCommonQueryData<SeqTypes>,
+}
+
+/// A block which has been proposed but not necessarily decided.
+///
+/// Proposed blocks may never be decided. Clients must treat them as unconfirmed until the same
+/// block is decided.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProposedBlockQueryData {
+    pub view: ViewNumber,
+    pub header: Header,
+    /// The block payload, if it was received before the proposal.
+    pub payload: Option<Payload>,
+    /// Whether this block is known to be decided. Always `false` for blocks from the proposal
+    /// stream.
+    pub decided: bool,
+}
+
+/// Proof that two consecutive headers are linked.
+///
+/// Each header commits to all earlier headers via its `block_merkle_tree_root`. `proof` shows that
+/// the commitment of `parent` is the leaf at index `parent.height` of the tree committed to by
+/// `header`, whose height is `parent.height + 1`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HeaderLinkQueryData {
+    pub parent: Header,
+    pub header: Header,
+    pub proof: BlocksFrontier,
+}
+
+/// Fees collected by the fee recipient over a range of blocks.
+///
+/// `from` and `to` are the balances of `fee_recipient` after blocks `from.height` and `to.height`,
+/// with proofs relative to the fee merkle tree roots in the headers of those blocks. `revenue` is
+/// the difference between the two.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FeeRevenueQueryData {
+    pub fee_recipient: FeeAccount,
+    pub from: FeeRevenueEndpoint,
+    pub to: FeeRevenueEndpoint,
+    pub revenue: FeeAmount,
+}
+
+/// The balance of the fee recipient at one end of a [`FeeRevenueQueryData`] range.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FeeRevenueEndpoint {
+    pub height: u64,
+    pub account: AccountQueryData,
+}
+
+/// The stake of a validator in the stake table active at a given block height.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ValidatorStakeQueryData {
+    pub height: u64,
+    pub stake_key: PubKey,
+    /// The stake of `stake_key`, or 0 if it is not in the stake table.
+    pub stake: U256,
+    /// The total stake of all validators in the stake table.
+    pub total_stake: U256,
+}
+
+/// Maximum number of leaves which can be fetched in a single request to `availability/leaves`.
+const MAX_LEAF_RANGE: usize = 100;
+
+/// How long to wait for a leaf needed to compute fee revenue before giving up.
+const REVENUE_FETCH_TIMEOUT: Duration = Duration::from_secs(1);
+
 pub(super) type AvailState<N, P, D, Ver> = Arc<RwLock<StorageState<N, P, D, Ver>>>;
 
 type AvailabilityApi<N, P, D, Ver> = Api<AvailState<N, P, D, Ver>, availability::Error, Ver>;
 
+async fn fetch_block_and_vid_common<S>(
+    state: &S,
+    height: usize,
+    timeout: Duration,
+) -> Result<(BlockQueryData<SeqTypes>, VidCommonQueryData<SeqTypes>), availability::Error>
+where
+    S: AvailabilityDataSource<SeqTypes> + Send + Sync,
+{
+    try_join!(
+        async move {
+            state
+                .get_block(height)
+                .await
+                .with_timeout(timeout)
+                .await
+                .context(FetchBlockSnafu {
+                    resource: height.to_string(),
+                })
+        },
+        async move {
+            state
+                .get_vid_common(height)
+                .await
+                .with_timeout(timeout)
+                .await
+                .context(FetchBlockSnafu {
+                    resource: height.to_string(),
+                })
+        }
+    )
+}
+
+async fn fetch_leaf_and_vid_common<S>(
+    state: &S,
+    height: usize,
+    timeout: Duration,
+) -> Result<(LeafQueryData<SeqTypes>, VidCommonQueryData<SeqTypes>), availability::Error>
+where
+    S: AvailabilityDataSource<SeqTypes> + Send + Sync,
+{
+    try_join!(
+        async move {
+            state
+                .get_leaf(height)
+                .await
+                .with_timeout(timeout)
+                .await
+                .context(FetchLeafSnafu {
+                    resource: height.to_string(),
+                })
+        },
+        async move {
+            state
+                .get_vid_common(height)
+                .await
+                .with_timeout(timeout)
+                .await
+                .context(FetchBlockSnafu {
+                    resource: height.to_string(),
+                })
+        }
+    )
+}
+
+fn namespace_proof(
+    block: &BlockQueryData<SeqTypes>,
+    common: &VidCommon,
+    ns_id: NamespaceId,
+) -> Result<NamespaceProofQueryData, availability::Error> {
+    let Some(ns_index) = block.payload().ns_table().find_ns_id(&ns_id) else {
+        // ns_id not found in ns_table
+        return Ok(NamespaceProofQueryData {
+            proof: None,
+            transactions: Vec::new(),
+        });
+    };
+    let proof = NsProof::new(block.payload(), &ns_index, common).context(CustomSnafu {
+        message: format!("failed to make proof for namespace {ns_id}"),
+        status: StatusCode::NOT_FOUND,
+    })?;
+    Ok(NamespaceProofQueryData {
+        transactions: proof.export_all_txs(&ns_id),
+        proof: Some(proof),
+    })
+}
+
+fn namespace_raw_proof(
+    block: &BlockQueryData<SeqTypes>,
+    common: &VidCommon,
+    ns_id: NamespaceId,
+) -> Result<NamespaceRawProofQueryData, availability::Error> {
+    let NamespaceProofQueryData {
+        proof,
+        transactions,
+    } = namespace_proof(block, common, ns_id)?;
+    let raw_transactions = proof
+        .iter()
+        .flat_map(NsProof::export_all_tx_bytes)
+        .map(RawTransaction)
+        .collect();
+    Ok(NamespaceRawProofQueryData {
+        proof,
+        transactions,
+        raw_transactions,
+    })
+}
+
 pub(super) fn availability<N, P, D, Ver: StaticVersionType + 'static>(
     bind_version: Ver,
 ) -> Result<AvailabilityApi<N, P, D, Ver>>
 where
     N: network::Type,
-    D: SequencerDataSource + Send + Sync + 'static,
+    D: SequencerDataSource + CatchupDataSource + Send + Sync + 'static,
     P: SequencerPersistence,
 {
     let mut options = availability::Options::default();
@@ -72,48 +454,666 @@ where
         async move {
             let height: usize = req.integer_param("height")?;
             let ns_id = NamespaceId::from(req.integer_param::<_, u32>("namespace")?);
-            let (block, common) = try_join!(
-                async move {
-                    state
-                        .get_block(height)
-                        .await
-                        .with_timeout(timeout)
-                        .await
-                        .context(FetchBlockSnafu {
-                            resource: height.to_string(),
-                        })
-                },
-                async move {
-                    state
-                        .get_vid_common(height)
-                        .await
-                        .with_timeout(timeout)
-                        .await
-                        .context(FetchBlockSnafu {
-                            resource: height.to_string(),
-                        })
-                }
-            )?;
-
-            if let Some(ns_index) = block.payload().ns_table().find_ns_id(&ns_id) {
-                let proof = NsProof::new(block.payload(), &ns_index, common.common()).context(
-                    CustomSnafu {
-                        message: format!("failed to make proof for namespace {ns_id}"),
-                        status: StatusCode::NOT_FOUND,
-                    },
-                )?;
-
-                Ok(NamespaceProofQueryData {
-                    transactions: proof.export_all_txs(&ns_id),
-                    proof: Some(proof),
+            let (block, common) = fetch_block_and_vid_common(state, height, timeout).await?;
+            namespace_proof(&block, common.common(), ns_id)
+        }
+        .boxed()
+    })?;
+
+    api.get("getnamespaceproofraw", move |req, state| {
+        async move {
+            let height: usize = req.integer_param("height")?;
+            let ns_id = NamespaceId::from(req.integer_param::<_, u32>("namespace")?);
+            let (block, common) = fetch_block_and_vid_common(state, height, timeout).await?;
+            namespace_raw_proof(&block, common.common(), ns_id)
+        }
+        .boxed()
+    })?;
+
+    api.get("getnamespaceproofbundle", move |req, state| {
+        async move {
+            let height: usize = req.integer_param("height")?;
+            let ns_id = NamespaceId::from(req.integer_param::<_, u32>("namespace")?);
+            let (block, common) = fetch_block_and_vid_common(state, height, timeout).await?;
+            let NamespaceProofQueryData {
+                proof,
+                transactions,
+            } = namespace_proof(&block, common.common(), ns_id)?;
+            Ok(NamespaceProofBundle {
+                header: block.header().clone(),
+                vid_common: common.common().clone(),
+                proof,
+                transactions,
+            })
+        }
+        .boxed()
+    })?;
+
+    api.stream("streamproposedblocks", move |_req, state| {
+        let state = state.clone();
+        async move {
+            let events = state.read().await.as_ref().event_stream();
+            Ok::<_, availability::Error>(proposed_block_stream(events).map(Ok))
+        }
+        .try_flatten_stream()
+        .boxed()
+    })?;
+
+    api.stream("streamblockswithvid", move |req, state| {
+        let state = state.clone();
+        async move {
+            let height = req.integer_param("height")?;
+            let state = state.read().await;
+            let blocks = state.subscribe_blocks(height).await;
+            let vid_common = state.subscribe_vid_common(height).await;
+            let stream = blocks
+                .zip(vid_common)
+                .map(|(block, vid_common)| Ok(BlockWithVidQueryData { block, vid_common }));
+            Ok::<_, availability::Error>(stream)
+        }
+        .try_flatten_stream()
+        .boxed()
+    })?;
+
+    api.get("getblocktransactions", move |req, state| {
+        async move {
+            let height: usize = req.integer_param("height")?;
+            let block = state
+                .get_block(height)
+                .await
+                .with_timeout(timeout)
+                .await
+                .context(FetchBlockSnafu {
+                    resource: height.to_string(),
+                })?;
+            let payload = block.payload();
+            let transactions = payload
+                .enumerate(payload.ns_table())
+                .map(|(index, transaction)| BlockTransactionQueryData {
+                    namespace: transaction.namespace(),
+                    index,
+                    transaction,
                 })
-            } else {
-                // ns_id not found in ns_table
-                Ok(NamespaceProofQueryData {
-                    proof: None,
-                    transactions: Vec::new(),
+                .collect::<Vec<_>>();
+            Ok(transactions)
+        }
+        .boxed()
+    })?;
+
+    api.get("gettransactionproof", move |req, state| {
+        async move {
+            let commitment = req.tagged_base64_param("commitment")?;
+            let hash = Commitment::<Transaction>::try_from(commitment).map_err(|err| {
+                availability::Error::Custom {
+                    message: format!("malformed transaction commitment {commitment}: {err}"),
+                    status: StatusCode::BAD_REQUEST,
+                }
+            })?;
+            let tx = state
+                .get_transaction(hash)
+                .await
+                .with_timeout(timeout)
+                .await
+                .context(FetchTransactionSnafu {
+                    resource: hash.to_string(),
+                })?;
+
+            let height = tx.block_height();
+            let (block, common) =
+                fetch_block_and_vid_common(state, height as usize, timeout).await?;
+            let (transaction, proof) = TxProof::new(tx.index(), block.payload(), common.common())
+                .context(CustomSnafu {
+                    message: format!("failed to make proof for transaction {hash}"),
+                    status: StatusCode::NOT_FOUND,
+                })?;
+            Ok(TransactionProofQueryData {
+                transaction,
+                index: tx.index().clone(),
+                height,
+                proof,
+            })
+        }
+        .boxed()
+    })?;
+
+    api.get("getblockbybuildercommitment", move |req, state| {
+        async move {
+            let commitment = req.tagged_base64_param("commitment")?;
+            let commitment = BuilderCommitment::try_from(commitment).map_err(|err| {
+                availability::Error::Custom {
+                    message: format!("malformed builder commitment {commitment}: {err}"),
+                    status: StatusCode::BAD_REQUEST,
+                }
+            })?;
+            let height = state
+                .inner()
+                .get_block_height_by_builder_commitment(&commitment)
+                .await
+                .map_err(|err| availability::Error::Custom {
+                    message: format!("{err:#}"),
+                    status: StatusCode::NOT_FOUND,
+                })?;
+            let block = state
+                .get_block(height as usize)
+                .await
+                .with_timeout(timeout)
+                .await
+                .context(FetchBlockSnafu {
+                    resource: height.to_string(),
+                })?;
+
+            // Don't rely on the index alone: the payload must actually have this commitment.
+            let payload = block.payload();
+            if payload.builder_commitment(payload.ns_table()) != commitment {
+                return Err(availability::Error::Custom {
+                    message: format!(
+                        "block {height} does not have builder commitment {commitment}"
+                    ),
+                    status: StatusCode::NOT_FOUND,
+                });
+            }
+            Ok(block)
+        }
+        .boxed()
+    })?;
+
+    api.get("getblockmerkleproof", move |req, state| {
+        async move {
+            let height = req.integer_param("height")?;
+            state
+                .get_block_proof(height)
+                .await
+                .map_err(|err| availability::Error::Custom {
+                    message: format!("{err:#}"),
+                    status: StatusCode::NOT_FOUND,
                 })
+        }
+        .boxed()
+    })?;
+
+    api.get("getleafqc", move |req, state| {
+        async move {
+            let height: usize = req.integer_param("height")?;
+            let leaf = state
+                .get_leaf(height)
+                .await
+                .with_timeout(timeout)
+                .await
+                .context(FetchLeafSnafu {
+                    resource: height.to_string(),
+                })?;
+            Ok(leaf.qc().clone())
+        }
+        .boxed()
+    })?;
+
+    api.get("getleaves", move |req, state| {
+        async move {
+            let from: usize = req.integer_param("from")?;
+            let to: usize = req.integer_param("to")?;
+            if to < from || to - from > MAX_LEAF_RANGE {
+                return Err(availability::Error::Custom {
+                    message: format!(
+                        "invalid range {from}..{to}; at most {MAX_LEAF_RANGE} leaves may be \
+                         requested"
+                    ),
+                    status: StatusCode::BAD_REQUEST,
+                });
+            }
+
+            let mut leaves = Vec::with_capacity(to - from);
+            for height in from..to {
+                let leaf = state
+                    .get_leaf(height)
+                    .await
+                    .with_timeout(timeout)
+                    .await
+                    .context(FetchLeafSnafu {
+                        resource: height.to_string(),
+                    })?;
+                leaves.push(leaf);
+            }
+            Ok(leaves)
+        }
+        .boxed()
+    })?;
+
+    api.get("getheaderlink", move |req, state| {
+        async move {
+            let height: usize = req.integer_param("height")?;
+            let parent = state
+                .get_leaf(height)
+                .await
+                .with_timeout(timeout)
+                .await
+                .context(FetchLeafSnafu {
+                    resource: height.to_string(),
+                })?;
+            let leaf = state
+                .get_leaf(height + 1)
+                .await
+                .with_timeout(timeout)
+                .await
+                .context(FetchLeafSnafu {
+                    resource: (height + 1).to_string(),
+                })?;
+
+            // The frontier of the tree at `height + 1` is the path to the leaf at `height`.
+            let proof = state
+                .get_frontier(leaf.height(), leaf.leaf().view_number())
+                .await
+                .map_err(|err| availability::Error::Custom {
+                    message: format!("{err:#}"),
+                    status: StatusCode::NOT_FOUND,
+                })?;
+            Ok(HeaderLinkQueryData {
+                parent: parent.header().clone(),
+                header: leaf.header().clone(),
+                proof,
+            })
+        }
+        .boxed()
+    })?;
+
+    api.get("getnamespacecommitment", move |req, state| {
+        async move {
+            let height: usize = req.integer_param("height")?;
+            let ns_id = NamespaceId::from(req.integer_param::<_, u32>("namespace")?);
+            let block = state
+                .get_block(height)
+                .await
+                .with_timeout(timeout)
+                .await
+                .context(FetchBlockSnafu {
+                    resource: height.to_string(),
+                })?;
+            block.payload().ns_payload_commitment(&ns_id).context(CustomSnafu {
+                message: format!("namespace {ns_id} not found in block {height}"),
+                status: StatusCode::NOT_FOUND,
+            })
+        }
+        .boxed()
+    })?;
+
+    api.get("getbuilderfee", move |req, state| {
+        async move {
+            let height: usize = req.integer_param("height")?;
+            let leaf = state
+                .get_leaf(height)
+                .await
+                .with_timeout(timeout)
+                .await
+                .context(FetchLeafSnafu {
+                    resource: height.to_string(),
+                })?;
+            let header = leaf.header().clone();
+            Ok(BuilderFeeQueryData {
+                fee: header.fee_info,
+                header,
+            })
+        }
+        .boxed()
+    })?;
+
+    api.get("getemptyblockproof", move |req, state| {
+        async move {
+            let height: usize = req.integer_param("height")?;
+            let (leaf, common) = fetch_leaf_and_vid_common(state, height, timeout).await?;
+            let payload_byte_len = PayloadByteLen::from_vid_common(common.common()).as_usize();
+            if payload_byte_len != 0 {
+                return Err(availability::Error::Custom {
+                    message: format!(
+                        "block {height} is not empty: its payload has {payload_byte_len} bytes"
+                    ),
+                    status: StatusCode::NOT_FOUND,
+                });
             }
+            Ok(EmptyBlockProof {
+                header: leaf.header().clone(),
+                vid_common: common.common().clone(),
+            })
+        }
+        .boxed()
+    })?;
+
+    api.get("getblocksize", move |req, state| {
+        async move {
+            let height: usize = req.integer_param("height")?;
+            // The payload length is recorded in the VID common data and the namespace count in the
+            // header, so neither requires fetching the payload itself.
+            let (leaf, common) = fetch_leaf_and_vid_common(state, height, timeout).await?;
+            Ok(BlockSizeQueryData {
+                payload_byte_len: PayloadByteLen::from_vid_common(common.common()),
+                num_namespaces: leaf.header().ns_table.iter().count(),
+            })
+        }
+        .boxed()
+    })?;
+
+    Ok(api)
+}
+
+/// Follow consensus events and yield each quorum proposal as a [`ProposedBlockQueryData`].
+///
+/// DA proposals usually arrive before the quorum proposal for the same view, so their payloads are
+/// held until the corresponding quorum proposal and attached to it if they are consistent with the
+/// proposed header.
+fn proposed_block_stream(
+    events: impl Stream<Item = Event<SeqTypes>> + Send + Unpin + 'static,
+) -> impl Stream<Item = ProposedBlockQueryData> + Send {
+    stream::unfold(
+        (events, BTreeMap::<ViewNumber, Payload>::new()),
+        |(mut events, mut payloads)| async move {
+            loop {
+                let event = events.next().await?;
+                match event.event {
+                    EventType::DaProposal { proposal, .. } => {
+                        let payload = Payload::from_bytes(
+                            &proposal.data.encoded_transactions,
+                            &proposal.data.metadata,
+                        );
+                        payloads.insert(proposal.data.view_number, payload);
+                    }
+                    EventType::QuorumProposal { proposal, .. } => {
+                        let view = proposal.data.view_number;
+                        let header = proposal.data.block_header;
+
+                        // Payloads for earlier views will never be needed.
+                        payloads = payloads.split_off(&view);
+                        let payload = payloads.remove(&view).filter(|payload| {
+                            payload.builder_commitment(&header.ns_table)
+                                == header.builder_commitment
+                        });
+
+                        let block = ProposedBlockQueryData {
+                            view,
+                            header,
+                            payload,
+                            decided: false,
+                        };
+                        return Some((block, (events, payloads)));
+                    }
+                    _ => continue,
+                }
+            }
+        },
+    )
+}
+
+/// Maximum number of blocks whose proposers can be fetched in a single request.
+const MAX_PROPOSERS_RANGE: usize = 1000;
+
+/// The proposer of a single block, as returned by the `status/proposers` endpoint.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BlockProposer {
+    pub height: u64,
+    pub view: ViewNumber,
+    pub proposer: FeeAccount,
+}
+
+/// Maximum number of blocks whose view timeouts can be fetched in a single request.
+const MAX_VIEW_TIMEOUTS_RANGE: usize = 1000;
+
+/// The views which failed before a block was decided, as returned by the `status/view-timeouts`
+/// endpoint.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BlockViewTimeouts {
+    pub height: u64,
+    /// The view in which this block was proposed.
+    pub view: ViewNumber,
+    /// The view in which the parent of this block was proposed, or `None` for the genesis block.
+    pub parent_view: Option<ViewNumber>,
+    /// The number of views strictly between `parent_view` and `view`, none of which produced a
+    /// block.
+    pub timeouts: u64,
+}
+
+/// The status of a view, as returned by the `status/view` endpoint.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ViewStatus {
+    /// A block was decided in this view, at the given height.
+    Decided { height: u64 },
+    /// No block has been decided in this view yet.
+    ///
+    /// The view is later than the last decided view, so it may still be in progress, or it may not
+    /// have started yet.
+    Pending,
+    /// No block was decided in this view, and none ever will be, since a later view has been
+    /// decided. This is usually because the view timed out.
+    Failed,
+}
+
+/// How far the query store is behind consensus, as returned by the `status/storage-lag` endpoint.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StorageLag {
+    /// The number of blocks decided by consensus.
+    pub consensus_height: u64,
+    /// The number of blocks in the query store.
+    pub storage_height: u64,
+    /// The number of decided blocks not yet in the query store.
+    pub lag: u64,
+}
+
+/// A node's progress catching up to the chain tip after startup, as returned by the
+/// `status/catchup-progress` endpoint.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CatchupProgress {
+    /// The number of blocks in the query store.
+    pub block_height: u64,
+    /// The highest block height most recently reported by this node's peers, if any.
+    pub tip: Option<u64>,
+    /// Whether the node has caught up.
+    pub caught_up: bool,
+    /// Whether the node gave up waiting to catch up after the startup catchup timeout.
+    ///
+    /// The node serves its full API once it has either caught up or timed out.
+    pub timed_out: bool,
+}
+
+type StatusApi<N, P, D, Ver> = Api<AvailState<N, P, D, Ver>, status::Error, Ver>;
+
+pub(super) fn status<N, P, D, Ver: StaticVersionType + 'static>(
+    bind_version: Ver,
+) -> Result<StatusApi<N, P, D, Ver>>
+where
+    N: network::Type,
+    D: SequencerDataSource + Send + Sync + 'static,
+    P: SequencerPersistence,
+{
+    let mut options = status::Options::default();
+    let extension = toml::from_str(include_str!("../../api/status.toml"))?;
+    options.extensions.push(extension);
+
+    let mut api = status::define_api::<AvailState<N, P, D, Ver>, Ver>(&options, bind_version)?;
+
+    api.get("getproposers", |req, state| {
+        async move {
+            let from: usize = req
+                .integer_param("from")
+                .map_err(status::Error::from_request_error)?;
+            let until: usize = req
+                .integer_param("until")
+                .map_err(status::Error::from_request_error)?;
+            if until < from || until - from > MAX_PROPOSERS_RANGE {
+                return Err(status::Error::catch_all(
+                    StatusCode::BAD_REQUEST,
+                    format!(
+                        "invalid range {from}..{until}; at most {MAX_PROPOSERS_RANGE} blocks may \
+                         be requested"
+                    ),
+                ));
+            }
+
+            let mut proposers = Vec::with_capacity(until - from);
+            for height in from..until {
+                let leaf = state.get_leaf(height).await.try_resolve().map_err(|_| {
+                    status::Error::catch_all(
+                        StatusCode::NOT_FOUND,
+                        format!("leaf {height} is not available"),
+                    )
+                })?;
+                proposers.push(BlockProposer {
+                    height: leaf.height(),
+                    view: leaf.leaf().view_number(),
+                    proposer: leaf.header().proposer_id(),
+                });
+            }
+            Ok(proposers)
+        }
+        .boxed()
+    })?;
+
+    api.get("getheightview", |req, state| {
+        async move {
+            let height: usize = req
+                .integer_param("height")
+                .map_err(status::Error::from_request_error)?;
+            let leaf = state.get_leaf(height).await.try_resolve().map_err(|_| {
+                status::Error::catch_all(
+                    StatusCode::NOT_FOUND,
+                    format!("leaf {height} is not available"),
+                )
+            })?;
+            Ok(leaf.leaf().view_number())
+        }
+        .boxed()
+    })?;
+
+    api.get("getl1commitment", |req, state| {
+        async move {
+            let height: usize = req
+                .integer_param("height")
+                .map_err(status::Error::from_request_error)?;
+            let leaf = state.get_leaf(height).await.try_resolve().map_err(|_| {
+                status::Error::catch_all(
+                    StatusCode::NOT_FOUND,
+                    format!("leaf {height} is not available"),
+                )
+            })?;
+            Ok(commitment_to_u256(leaf.block_hash()))
+        }
+        .boxed()
+    })?;
+
+    api.get("getviewstatus", |req, state| {
+        async move {
+            let view: u64 = req
+                .integer_param("view")
+                .map_err(status::Error::from_request_error)?;
+            let view = ViewNumber::new(view);
+            let (decided_height, decided_view) = state.last_decided().await;
+            if view > decided_view {
+                return Ok(ViewStatus::Pending);
+            }
+            if view == decided_view {
+                return Ok(ViewStatus::Decided {
+                    height: decided_height,
+                });
+            }
+
+            // Views increase with block height, so search the decided chain for a leaf from this
+            // view. If there is none, the view was skipped.
+            let (mut start, mut end) = (0, decided_height as usize);
+            while start < end {
+                let height = start + (end - start) / 2;
+                let leaf = state.get_leaf(height).await.try_resolve().map_err(|_| {
+                    status::Error::catch_all(
+                        StatusCode::NOT_FOUND,
+                        format!("leaf {height} is not available"),
+                    )
+                })?;
+                match leaf.leaf().view_number().cmp(&view) {
+                    Ordering::Equal => {
+                        return Ok(ViewStatus::Decided {
+                            height: height as u64,
+                        })
+                    }
+                    Ordering::Less => start = height + 1,
+                    Ordering::Greater => end = height,
+                }
+            }
+            Ok(ViewStatus::Failed)
+        }
+        .boxed()
+    })?;
+
+    api.get("getviewtimeouts", |req, state| {
+        async move {
+            let from: usize = req
+                .integer_param("from")
+                .map_err(status::Error::from_request_error)?;
+            let until: usize = req
+                .integer_param("until")
+                .map_err(status::Error::from_request_error)?;
+            if until < from || until - from > MAX_VIEW_TIMEOUTS_RANGE {
+                return Err(status::Error::catch_all(
+                    StatusCode::BAD_REQUEST,
+                    format!(
+                        "invalid range {from}..{until}; at most {MAX_VIEW_TIMEOUTS_RANGE} blocks \
+                         may be requested"
+                    ),
+                ));
+            }
+
+            let leaf_view = |height: usize| async move {
+                let leaf = state.get_leaf(height).await.try_resolve().map_err(|_| {
+                    status::Error::catch_all(
+                        StatusCode::NOT_FOUND,
+                        format!("leaf {height} is not available"),
+                    )
+                })?;
+                Ok::<_, status::Error>(leaf.leaf().view_number())
+            };
+
+            let mut parent_view = match from.checked_sub(1) {
+                Some(parent) => Some(leaf_view(parent).await?),
+                None => None,
+            };
+            let mut blocks = Vec::with_capacity(until - from);
+            for height in from..until {
+                let view = leaf_view(height).await?;
+                blocks.push(BlockViewTimeouts {
+                    height: height as u64,
+                    view,
+                    parent_view,
+                    timeouts: parent_view
+                        .map(|parent| view.u64().saturating_sub(parent.u64() + 1))
+                        .unwrap_or(0),
+                });
+                parent_view = Some(view);
+            }
+            Ok(blocks)
+        }
+        .boxed()
+    })?;
+
+    api.get("getstoragelag", |_, state| {
+        async move {
+            let consensus_height = state.decided_block_height().await;
+            let storage_height = state.block_height().await.map_err(|err| {
+                status::Error::catch_all(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("failed to get block height: {err}"),
+                )
+            })? as u64;
+            Ok(StorageLag {
+                consensus_height,
+                storage_height,
+                lag: consensus_height.saturating_sub(storage_height),
+            })
+        }
+        .boxed()
+    })?;
+
+    api.get("getcatchupprogress", |_, state| {
+        async move {
+            let block_height = state.block_height().await.map_err(|err| {
+                status::Error::catch_all(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("failed to get block height: {err}"),
+                )
+            })? as u64;
+            Ok(state.as_ref().catchup_progress(block_height).await)
         }
         .boxed()
     })?;
@@ -151,6 +1151,13 @@ where
     )?;
     Ok(api)
 }
+
+/// Header which clients can set to make retried submissions idempotent.
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+/// Maximum length in bytes of an idempotency key.
+const MAX_IDEMPOTENCY_KEY_LEN: usize = 256;
+
 pub(super) fn submit<N, P, S, Ver: StaticVersionType + 'static>() -> Result<Api<S, Error, Ver>>
 where
     N: network::Type,
@@ -163,16 +1170,28 @@ where
 
     api.at("submit", |req, state| {
         async move {
-            let tx = req
-                .body_auto::<Transaction, Ver>(Ver::instance())
-                .map_err(Error::from_request_error)?;
-
-            let hash = tx.commit();
-            state
-                .read(|state| state.submit(tx).boxed())
-                .await
-                .map_err(|err| Error::internal(err.to_string()))?;
-            Ok(hash)
+            submit_request::<N, P, S, Ver>(req, state, |req| {
+                Ok(req.body_auto::<Transaction, Ver>(Ver::instance())?.into())
+            })
+            .await
+        }
+        .boxed()
+    })?
+    .at("submit_with_fee", |req, state| {
+        async move {
+            submit_request::<N, P, S, Ver>(req, state, |req| {
+                req.body_auto::<TransactionSubmission, Ver>(Ver::instance())
+            })
+            .await
+        }
+        .boxed()
+    })?
+    .get("rejections", |_, state| {
+        async move {
+            state.rejections().await.ok_or(tide_disco::Error::catch_all(
+                StatusCode::NOT_FOUND,
+                "Rejection log is not enabled.".to_owned(),
+            ))
         }
         .boxed()
     })?;
@@ -180,6 +1199,69 @@ where
     Ok(api)
 }
 
+/// Handle a request to submit the transaction parsed from the request body by `parse`.
+async fn submit_request<N, P, S, Ver: StaticVersionType + 'static>(
+    req: RequestParams,
+    state: &S,
+    parse: impl FnOnce(&RequestParams) -> Result<TransactionSubmission, RequestError>,
+) -> Result<Commitment<Transaction>, Error>
+where
+    N: network::Type,
+    S: 'static + Send + Sync + WriteState,
+    P: SequencerPersistence,
+    S::State: Send + Sync + SubmitDataSource<N, P>,
+{
+    // Requests declaring an oversized body are rejected before the body is read (see
+    // `body_limit`), but a body of undeclared length is only cut off once it exceeds the limit.
+    // Check the size of the body before deserializing it, so such a request cannot make us
+    // allocate an even larger transaction.
+    let max_body_size = state.read(|state| state.max_submit_body_size().boxed()).await;
+    let body_size = req.body_bytes().len();
+    if body_size as u64 > max_body_size {
+        return Err(Error::catch_all(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("request body of {body_size} bytes exceeds the maximum of {max_body_size}"),
+        ));
+    }
+
+    let submission = parse(&req).map_err(Error::from_request_error)?;
+
+    let key = req
+        .header(IDEMPOTENCY_KEY_HEADER)
+        .map(|values| values.as_str().to_owned());
+    let res = match key {
+        Some(key) => {
+            if key.len() > MAX_IDEMPOTENCY_KEY_LEN {
+                return Err(Error::catch_all(
+                    StatusCode::BAD_REQUEST,
+                    format!("idempotency key is longer than {MAX_IDEMPOTENCY_KEY_LEN} bytes"),
+                ));
+            }
+            state
+                .read(|state| state.submit_idempotent(key, submission).boxed())
+                .await
+        }
+        None => {
+            let hash = submission.transaction().commit();
+            state
+                .read(|state| state.submit(submission).boxed())
+                .await
+                .map(|()| hash)
+        }
+    };
+    res.map_err(|err| {
+        if err.is::<NamespaceRateLimited>() {
+            Error::catch_all(StatusCode::TOO_MANY_REQUESTS, err.to_string())
+        } else if err.is::<IdempotencyKeyConflict>() {
+            Error::catch_all(StatusCode::CONFLICT, err.to_string())
+        } else if err.is::<NodeShuttingDown>() {
+            Error::catch_all(StatusCode::SERVICE_UNAVAILABLE, err.to_string())
+        } else {
+            Error::internal(err.to_string())
+        }
+    })
+}
+
 pub(super) fn state_signature<N, S, Ver: StaticVersionType + 'static>(
     _: Ver,
 ) -> Result<Api<S, Error, Ver>>
@@ -207,9 +1289,47 @@ where
         .boxed()
     })?;
 
+    api.get("get_state_signature_bundle", |req, state| {
+        async move {
+            let height = req
+                .integer_param("height")
+                .map_err(Error::from_request_error)?;
+            state
+                .get_state_signature_bundle(height)
+                .await
+                .ok_or(tide_disco::Error::catch_all(
+                    StatusCode::NOT_FOUND,
+                    "Signature bundle not found.".to_owned(),
+                ))
+        }
+        .boxed()
+    })?;
+
     Ok(api)
 }
 
+/// Ensure that this node serves catchup proofs in the format requested by `req`.
+///
+/// Requests to the unversioned routes are for format 0.
+fn check_catchup_format(req: &RequestParams) -> Result<(), Error> {
+    let version = req
+        .opt_integer_param::<_, u32>("format_version")
+        .map_err(Error::from_request_error)?
+        .unwrap_or(0);
+    if version != CATCHUP_FORMAT_VERSION {
+        return Err(Error::catch_all(
+            StatusCode::NOT_FOUND,
+            format!("catchup format version {version} is not supported"),
+        ));
+    }
+    Ok(())
+}
+
+/// Whether `req` was made by a peer catching up, which must not be forwarded to this node's peers.
+fn is_from_peer(req: &RequestParams) -> bool {
+    req.header(CATCHUP_NO_FORWARD_HEADER).is_some()
+}
+
 pub(super) fn catchup<S, Ver: StaticVersionType + 'static>(_: Ver) -> Result<Api<S, Error, Ver>>
 where
     S: 'static + Send + Sync + ReadState,
@@ -220,6 +1340,7 @@ where
 
     api.get("account", |req, state| {
         async move {
+            check_catchup_format(&req)?;
             let height = req
                 .integer_param("height")
                 .map_err(Error::from_request_error)?;
@@ -236,15 +1357,19 @@ where
                 )
             })?;
 
-            state
-                .get_account(height, ViewNumber::new(view), account)
-                .await
-                .map_err(|err| Error::catch_all(StatusCode::NOT_FOUND, format!("{err:#}")))
+            let view = ViewNumber::new(view);
+            let res = if is_from_peer(&req) {
+                state.get_local_account(height, view, account).await
+            } else {
+                state.get_account(height, view, account).await
+            };
+            res.map_err(|err| Error::catch_all(StatusCode::NOT_FOUND, format!("{err:#}")))
         }
         .boxed()
     })?
     .get("blocks", |req, state| {
         async move {
+            check_catchup_format(&req)?;
             let height = req
                 .integer_param("height")
                 .map_err(Error::from_request_error)?;
@@ -252,8 +1377,77 @@ where
                 .integer_param("view")
                 .map_err(Error::from_request_error)?;
 
+            let view = ViewNumber::new(view);
+            let res = if is_from_peer(&req) {
+                state.get_local_frontier(height, view).await
+            } else {
+                state.get_frontier(height, view).await
+            };
+            res.map_err(|err| Error::catch_all(StatusCode::NOT_FOUND, format!("{err:#}")))
+        }
+        .boxed()
+    })?
+    .get("latest_account", |req, state| {
+        async move {
+            let account = req
+                .string_param("address")
+                .map_err(Error::from_request_error)?;
+            let account = account.parse().map_err(|err| {
+                Error::catch_all(
+                    StatusCode::BAD_REQUEST,
+                    format!("malformed account {account}: {err}"),
+                )
+            })?;
+
+            state
+                .get_latest_account(account)
+                .await
+                .map_err(|err| Error::catch_all(StatusCode::NOT_FOUND, format!("{err:#}")))
+        }
+        .boxed()
+    })?
+    .get("account_at_root", |req, state| {
+        async move {
+            let root = req
+                .tagged_base64_param("root")
+                .map_err(Error::from_request_error)?;
+            let root = BlockMerkleCommitment::try_from(root).map_err(|err| {
+                Error::catch_all(
+                    StatusCode::BAD_REQUEST,
+                    format!("malformed block Merkle root {root}: {err}"),
+                )
+            })?;
+            let account = req
+                .string_param("address")
+                .map_err(Error::from_request_error)?;
+            let account = account.parse().map_err(|err| {
+                Error::catch_all(
+                    StatusCode::BAD_REQUEST,
+                    format!("malformed account {account}: {err}"),
+                )
+            })?;
+
             state
-                .get_frontier(height, ViewNumber::new(view))
+                .get_account_at_root(root, account)
+                .await
+                .map_err(|err| Error::catch_all(StatusCode::NOT_FOUND, format!("{err:#}")))
+        }
+        .boxed()
+    })?
+    .get("signed_account", |req, state| {
+        async move {
+            let account = req
+                .string_param("address")
+                .map_err(Error::from_request_error)?;
+            let account = account.parse().map_err(|err| {
+                Error::catch_all(
+                    StatusCode::BAD_REQUEST,
+                    format!("malformed account {account}: {err}"),
+                )
+            })?;
+
+            state
+                .get_signed_account(account)
                 .await
                 .map_err(|err| Error::catch_all(StatusCode::NOT_FOUND, format!("{err:#}")))
         }
@@ -284,10 +1478,155 @@ where
     Ok(api)
 }
 
+/// The fee state API, extended with a subscription to changes in account balances.
+pub(super) fn fee_state<N, P, D, Ver: StaticVersionType + 'static>(
+    _: Ver,
+) -> Result<MerklizedStateApi<N, P, D, Ver>>
+where
+    N: network::Type,
+    D: SequencerDataSource
+        + CatchupDataSource
+        + MerklizedStateDataSource<SeqTypes, FeeMerkleTree, { FeeMerkleTree::ARITY }>
+        + MerklizedStateHeightPersistence
+        + Send
+        + Sync
+        + 'static,
+    P: SequencerPersistence,
+{
+    let mut options = merklized_state::Options::default();
+    let extension = toml::from_str(include_str!("../../api/fee_state.toml"))?;
+    options.extensions.push(extension);
+
+    let mut api = merklized_state::define_api::<
+        AvailState<N, P, D, Ver>,
+        SeqTypes,
+        FeeMerkleTree,
+        Ver,
+        { FeeMerkleTree::ARITY },
+    >(&options)?;
+
+    api.stream("getaccountstream", move |req, state| {
+        let state = state.clone();
+        async move {
+            let account = req.string_param("account")?;
+            let account: FeeAccount = account.parse().map_err(|err| {
+                merklized_state::Error::Custom {
+                    message: format!("malformed account {account}: {err:#}"),
+                    status: StatusCode::BAD_REQUEST,
+                }
+            })?;
+            let height = state.read().await.block_height().await.map_err(|err| {
+                merklized_state::Error::Custom {
+                    message: format!("failed to get block height: {err}"),
+                    status: StatusCode::INTERNAL_SERVER_ERROR,
+                }
+            })?;
+
+            // Start from the latest decided block, so the client gets its current balance first.
+            let leaves = state
+                .read()
+                .await
+                .subscribe_leaves(height.saturating_sub(1))
+                .await;
+            let blocks = leaves.map(|leaf| (leaf.height(), leaf.leaf().view_number()));
+            let get_account = move |height, view| {
+                let state = state.clone();
+                async move {
+                    state
+                        .read()
+                        .await
+                        .get_account(height, view, account.address())
+                        .await
+                }
+            };
+            let balances = account_balance_changes(
+                blocks,
+                get_account,
+                ACCOUNT_STREAM_RETRY_DELAY,
+                ACCOUNT_STREAM_MAX_ATTEMPTS,
+            )
+            .map_err(|err| merklized_state::Error::Custom {
+                message: format!("{err:#}"),
+                status: StatusCode::NOT_FOUND,
+            });
+            Ok(balances)
+        }
+        .try_flatten_stream()
+        .boxed()
+    })?;
+
+    api.get("getrevenue", move |req, state| {
+        async move {
+            let from: u64 = req.integer_param("from")?;
+            let to: u64 = req.integer_param("to")?;
+            if from > to {
+                return Err(merklized_state::Error::Custom {
+                    message: format!("invalid range: {from} > {to}"),
+                    status: StatusCode::BAD_REQUEST,
+                });
+            }
+
+            let fee_recipient = state.get_chain_config().await.fee_recipient;
+            let from = fee_recipient_balance(state, from, fee_recipient).await?;
+            let to = fee_recipient_balance(state, to, fee_recipient).await?;
+
+            let from_balance = FeeAmount::from(from.account.balance);
+            let to_balance = FeeAmount::from(to.account.balance);
+            let revenue = to_balance.checked_sub(&from_balance).ok_or_else(|| {
+                merklized_state::Error::Custom {
+                    message: format!(
+                        "fee recipient balance decreased from {from_balance} to {to_balance}"
+                    ),
+                    status: StatusCode::CONFLICT,
+                }
+            })?;
+            Ok(FeeRevenueQueryData {
+                fee_recipient,
+                from,
+                to,
+                revenue,
+            })
+        }
+        .boxed()
+    })?;
+
+    Ok(api)
+}
+
+/// Get the balance of `fee_recipient` after the block at `height`, with a proof.
+async fn fee_recipient_balance<N, P, D, Ver: StaticVersionType + 'static>(
+    state: &StorageState<N, P, D, Ver>,
+    height: u64,
+    fee_recipient: FeeAccount,
+) -> Result<FeeRevenueEndpoint, merklized_state::Error>
+where
+    N: network::Type,
+    D: SequencerDataSource + CatchupDataSource + Send + Sync + 'static,
+    P: SequencerPersistence,
+{
+    let leaf = state
+        .get_leaf(height as usize)
+        .await
+        .with_timeout(REVENUE_FETCH_TIMEOUT)
+        .await
+        .ok_or_else(|| merklized_state::Error::Custom {
+            message: format!("block {height} not available"),
+            status: StatusCode::NOT_FOUND,
+        })?;
+    let account = state
+        .get_account(height, leaf.leaf().view_number(), fee_recipient.address())
+        .await
+        .map_err(|err| merklized_state::Error::Custom {
+            message: format!("fee state at block {height} not available: {err:#}"),
+            status: StatusCode::NOT_FOUND,
+        })?;
+    Ok(FeeRevenueEndpoint { height, account })
+}
+
 pub(super) fn config<S, Ver: StaticVersionType + 'static>(_: Ver) -> Result<Api<S, Error, Ver>>
 where
     S: 'static + Send + Sync + ReadState,
-    S::State: Send + Sync + HotShotConfigDataSource,
+    S::State: Send + Sync + HotShotConfigDataSource + ConsensusDataSource,
 {
     let toml = toml::from_str::<toml::Value>(include_str!("../../api/config.toml"))?;
     let mut api = Api::<S, Error, Ver>::new(toml)?;
@@ -298,6 +1637,62 @@ where
     api.get("hotshot", |_, state| {
         async move { Ok(state.get_config().await) }.boxed()
     })?
+    .get("chain", |_, state| {
+        async move { Ok(state.get_chain_config().await) }.boxed()
+    })?
+    .stream("stream", |_, state| {
+        async move {
+            let configs = state.read(|state| state.chain_config_stream().boxed()).await;
+            Ok::<_, Error>(configs.map(Ok))
+        }
+        .try_flatten_stream()
+        .boxed()
+    })?
+    .get("light_client_genesis", |_, state| {
+        async move { Ok(state.get_light_client_genesis().await) }.boxed()
+    })?
+    .get("stake", |req, state| {
+        async move {
+            let key = req
+                .tagged_base64_param("pubkey")
+                .map_err(Error::from_request_error)?;
+            let stake_key = PubKey::try_from(key).map_err(|err| {
+                Error::catch_all(
+                    StatusCode::BAD_REQUEST,
+                    format!("malformed public key {key}: {err}"),
+                )
+            })?;
+            let height = req
+                .integer_param("height")
+                .map_err(Error::from_request_error)?;
+
+            // The stake table is fixed at genesis, so it is the same at every height that exists.
+            let block_height = state.decided_block_height().await;
+            if height >= block_height {
+                return Err(Error::catch_all(
+                    StatusCode::NOT_FOUND,
+                    format!("block {height} is not decided yet; block height is {block_height}"),
+                ));
+            }
+            let stake_table = state.get_config().await.known_nodes_with_stake;
+            let stake = stake_table
+                .iter()
+                .filter(|peer| peer.stake_table_entry.stake_key == stake_key)
+                .map(|peer| peer.stake_table_entry.stake_amount)
+                .fold(U256::zero(), |total, stake| total + stake);
+            let total_stake = stake_table
+                .iter()
+                .map(|peer| peer.stake_table_entry.stake_amount)
+                .fold(U256::zero(), |total, stake| total + stake);
+            Ok(ValidatorStakeQueryData {
+                height,
+                stake_key,
+                stake,
+                total_stake,
+            })
+        }
+        .boxed()
+    })?
     .get("env", move |_, _| {
         {
             let env_variables = env_variables.clone();
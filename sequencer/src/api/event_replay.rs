@@ -0,0 +1,125 @@
+//! Replay of recent HotShot events to new subscribers of the events API.
+//!
+//! A builder which loses its connection to the events API would otherwise miss every event emitted
+//! while it was disconnected. To paper over short disconnects, the events API keeps the most recent
+//! events in a bounded buffer, and each new subscription starts by replaying the buffer before
+//! switching to live events. Replayed events may have been seen by the subscriber before it
+//! disconnected, so subscribers must tolerate duplicates.
+
+use super::ApiState;
+use crate::{network, persistence::SequencerPersistence, SeqTypes};
+use async_std::sync::{Arc, Mutex};
+use async_trait::async_trait;
+use futures::{
+    channel::mpsc,
+    stream::{self, BoxStream, StreamExt},
+};
+use hotshot_events_service::events_source::{BuilderEvent, EventsSource};
+use std::collections::VecDeque;
+use vbs::version::StaticVersionType;
+
+type SharedEvent = Arc<BuilderEvent<SeqTypes>>;
+
+/// A bounded buffer of recent events, shared with all live subscribers.
+pub(super) struct EventReplay {
+    capacity: usize,
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    buffer: VecDeque<SharedEvent>,
+    subscribers: Vec<mpsc::UnboundedSender<SharedEvent>>,
+}
+
+impl EventReplay {
+    pub(super) fn new(capacity: usize) -> Arc<Self> {
+        Arc::new(Self {
+            capacity,
+            inner: Default::default(),
+        })
+    }
+
+    /// Record a new event and forward it to all live subscribers.
+    async fn record(&self, event: SharedEvent) {
+        let mut inner = self.inner.lock().await;
+        if self.capacity > 0 {
+            if inner.buffer.len() == self.capacity {
+                inner.buffer.pop_front();
+            }
+            inner.buffer.push_back(event.clone());
+        }
+        // Forward the event, dropping any subscribers which have disconnected.
+        inner
+            .subscribers
+            .retain(|subscriber| subscriber.unbounded_send(event.clone()).is_ok());
+    }
+
+    /// Subscribe to buffered events followed by live events.
+    ///
+    /// The buffer is read and the subscriber registered under the same lock used to record new
+    /// events, so there is no gap or overlap between the replayed and the live events.
+    async fn subscribe(&self) -> impl futures::Stream<Item = SharedEvent> {
+        let mut inner = self.inner.lock().await;
+        let (sender, receiver) = mpsc::unbounded();
+        inner.subscribers.push(sender);
+        stream::iter(inner.buffer.clone()).chain(receiver)
+    }
+}
+
+/// Follow the events produced by consensus and record them in `replay`.
+pub(super) async fn record_events_loop<N, P, Ver>(
+    replay: Arc<EventReplay>,
+    state: ApiState<N, P, Ver>,
+) where
+    N: network::Type,
+    P: SequencerPersistence,
+    Ver: StaticVersionType + 'static,
+{
+    // Every event stream starts with a startup event specific to that subscription, which is not
+    // worth replaying.
+    let mut events = state.get_event_stream().await.skip(1);
+    while let Some(event) = events.next().await {
+        replay.record(event).await;
+    }
+    tracing::warn!("end of HotShot event stream, event replay task will exit");
+}
+
+/// An [`EventsSource`] which replays recent events to each new subscriber.
+pub(super) struct ReplayingEventsSource<N, P, Ver>
+where
+    N: network::Type,
+    P: SequencerPersistence,
+    Ver: StaticVersionType,
+{
+    state: ApiState<N, P, Ver>,
+    replay: Arc<EventReplay>,
+}
+
+impl<N, P, Ver> ReplayingEventsSource<N, P, Ver>
+where
+    N: network::Type,
+    P: SequencerPersistence,
+    Ver: StaticVersionType,
+{
+    pub(super) fn new(state: ApiState<N, P, Ver>, replay: Arc<EventReplay>) -> Self {
+        Self { state, replay }
+    }
+}
+
+#[async_trait]
+impl<N, P, Ver> EventsSource<SeqTypes> for ReplayingEventsSource<N, P, Ver>
+where
+    N: network::Type,
+    P: SequencerPersistence,
+    Ver: StaticVersionType + 'static,
+{
+    type EventStream = BoxStream<'static, SharedEvent>;
+
+    async fn get_event_stream(&self) -> Self::EventStream {
+        // Take the startup event from a fresh subscription, since it is specific to each
+        // subscriber, then switch to the replayed and live events.
+        let startup = self.state.get_event_stream().await.take(1);
+        startup.chain(self.replay.subscribe().await).boxed()
+    }
+}
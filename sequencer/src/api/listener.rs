@@ -0,0 +1,416 @@
+//! The TCP listener serving the HTTP API.
+//!
+//! [`ApiListener`] accepts connections itself, rather than using the listeners provided by tide or
+//! tide-disco, so that it can limit WebSocket subscriptions per peer across every streaming route,
+//! including those defined by the query service. It also lets the API add middleware which runs
+//! before every route handler, which tide-disco does not otherwise expose.
+//!
+//! A subscription holds its connection open for as long as it lasts, but the handler serving it
+//! only sees the request which opened it. So each connection carries a shared [`Connection`]
+//! handle. When a request upgrades the connection to a WebSocket, middleware charges a
+//! subscription to the peer through this handle. The subscription is released once the last
+//! reference to the connection is dropped, when the WebSocket closes.
+
+use async_std::{
+    net::{TcpListener, TcpStream},
+    task::{sleep, spawn},
+};
+use async_trait::async_trait;
+use futures::{
+    io::{AsyncRead, AsyncWrite},
+    stream::StreamExt,
+};
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Display, Formatter},
+    io,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+use tide::{
+    http::{self, headers::UPGRADE},
+    listener::{ListenInfo, Listener},
+    Middleware, Next, Request, Response, Server, StatusCode,
+};
+
+/// Limits the number of concurrent subscriptions held open by each peer.
+#[derive(Debug, Default)]
+struct SubscriptionLimiter {
+    max: Option<usize>,
+    active: Mutex<HashMap<IpAddr, usize>>,
+}
+
+impl SubscriptionLimiter {
+    fn new(max: Option<usize>) -> Self {
+        Self {
+            max,
+            active: Default::default(),
+        }
+    }
+
+    /// Reserve a slot for a new subscription opened by `peer`.
+    ///
+    /// Fails if `peer` already has the maximum number of subscriptions open.
+    fn acquire(self: &Arc<Self>, peer: IpAddr) -> anyhow::Result<SubscriptionPermit> {
+        let max = self.max.unwrap_or(usize::MAX);
+        let mut active = self.active.lock().unwrap();
+        let count = active.entry(peer).or_default();
+        if *count >= max {
+            anyhow::bail!(
+                "too many subscriptions: {count} of {max} allowed are open, try again later"
+            );
+        }
+        *count += 1;
+        Ok(SubscriptionPermit {
+            limiter: self.clone(),
+            peer,
+        })
+    }
+}
+
+/// A slot in a peer's subscription limit, released when dropped.
+#[derive(Debug)]
+struct SubscriptionPermit {
+    limiter: Arc<SubscriptionLimiter>,
+    peer: IpAddr,
+}
+
+impl Drop for SubscriptionPermit {
+    fn drop(&mut self) {
+        let mut active = self.limiter.active.lock().unwrap();
+        if let Some(count) = active.get_mut(&self.peer) {
+            *count -= 1;
+            if *count == 0 {
+                active.remove(&self.peer);
+            }
+        }
+    }
+}
+
+/// State shared by every reference to an accepted connection.
+#[derive(Debug)]
+struct Connection {
+    peer: IpAddr,
+    subscriptions: Arc<SubscriptionLimiter>,
+    subscription: Mutex<Option<SubscriptionPermit>>,
+    open: Arc<AtomicUsize>,
+}
+
+impl Connection {
+    /// Charge a subscription on this connection to its peer.
+    fn subscribe(&self) -> anyhow::Result<()> {
+        let mut subscription = self.subscription.lock().unwrap();
+        if subscription.is_none() {
+            *subscription = Some(self.subscriptions.acquire(self.peer)?);
+        }
+        Ok(())
+    }
+
+    /// Release the subscription charged on this connection, if any.
+    fn unsubscribe(&self) {
+        self.subscription.lock().unwrap().take();
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.open.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// A TCP stream which keeps its [`Connection`] alive for as long as any clone of it is in use.
+#[derive(Clone, Debug)]
+struct ConnectionStream {
+    stream: TcpStream,
+    _conn: Arc<Connection>,
+}
+
+impl AsyncRead for ConnectionStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for ConnectionStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_close(cx)
+    }
+}
+
+/// Middleware charging WebSocket subscriptions to the peer of the connection they are opened on.
+#[derive(Clone, Copy, Debug)]
+struct SubscriptionMiddleware;
+
+#[async_trait]
+impl<State: Clone + Send + Sync + 'static> Middleware<State> for SubscriptionMiddleware {
+    async fn handle(&self, req: Request<State>, next: Next<'_, State>) -> tide::Result {
+        let upgrade = req
+            .header(UPGRADE)
+            .is_some_and(|values| values.as_str().eq_ignore_ascii_case("websocket"));
+        let conn = match req.ext::<Arc<Connection>>() {
+            Some(conn) if upgrade => conn.clone(),
+            _ => return Ok(next.run(req).await),
+        };
+
+        if let Err(err) = conn.subscribe() {
+            let mut res = Response::new(StatusCode::TooManyRequests);
+            res.set_body(format!("{err:#}"));
+            return Ok(res);
+        }
+        let res = next.run(req).await;
+        if res.status() != StatusCode::SwitchingProtocols {
+            // The subscription was refused, and the connection may be reused for other requests.
+            conn.unsubscribe();
+        }
+        Ok(res)
+    }
+}
+
+/// The API module serving the route a request path refers to.
+///
+/// Modules are served with or without a version prefix, such as `v0/status/block-height`.
+pub(super) fn api_module(path: &str) -> Option<&str> {
+    let mut segments = path.trim_start_matches('/').split('/');
+    let first = segments.next()?;
+    let is_version = first
+        .strip_prefix('v')
+        .is_some_and(|v| !v.is_empty() && v.bytes().all(|b| b.is_ascii_digit()));
+    if is_version {
+        segments.next()
+    } else {
+        Some(first)
+    }
+}
+
+type AddMiddleware<State> = Box<dyn FnOnce(&mut Server<State>) + Send + Sync>;
+
+/// A listener serving the API on a TCP port.
+///
+/// Limits the number of open connections to `max_connections` and the number of WebSocket
+/// subscriptions open at once by each peer IP address to `max_subscriptions`. Connections beyond
+/// the limit receive an immediate 429 response and are closed. Subscriptions beyond the limit are
+/// rejected with a 429 response.
+pub(super) struct ApiListener<State> {
+    addr: SocketAddr,
+    max_connections: Option<usize>,
+    subscriptions: Arc<SubscriptionLimiter>,
+    open: Arc<AtomicUsize>,
+    middleware: Vec<AddMiddleware<State>>,
+    listener: Option<TcpListener>,
+    server: Option<Server<State>>,
+}
+
+impl<State> ApiListener<State> {
+    pub(super) fn new(
+        port: u16,
+        max_connections: Option<usize>,
+        max_subscriptions: Option<usize>,
+    ) -> Self {
+        Self {
+            addr: SocketAddr::from((Ipv4Addr::UNSPECIFIED, port)),
+            max_connections,
+            subscriptions: Arc::new(SubscriptionLimiter::new(max_subscriptions)),
+            open: Default::default(),
+            middleware: vec![],
+            listener: None,
+            server: None,
+        }
+    }
+}
+
+impl<State: Clone + Send + Sync + 'static> ApiListener<State> {
+    /// Run `middleware` before every route handler of the server this listener serves.
+    pub(super) fn with(mut self, middleware: impl Middleware<State>) -> Self {
+        self.middleware.push(Box::new(move |app: &mut Server<State>| {
+            app.with(middleware);
+        }));
+        self
+    }
+}
+
+impl<State> Debug for ApiListener<State> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ApiListener")
+            .field("addr", &self.addr)
+            .field("max_connections", &self.max_connections)
+            .field("subscriptions", &self.subscriptions)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<State> Display for ApiListener<State> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "http://{}", self.addr)
+    }
+}
+
+impl<State: Clone + Send + Sync + 'static> ApiListener<State> {
+    fn handle(&self, server: Server<State>, stream: TcpStream) {
+        let peer_addr = match stream.peer_addr() {
+            Ok(addr) => addr,
+            Err(err) => {
+                tracing::warn!("dropping connection with unknown peer: {err}");
+                return;
+            }
+        };
+        let local_addr = stream.local_addr().ok();
+
+        let max_connections = self.max_connections.unwrap_or(usize::MAX);
+        let open = self
+            .open
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |open| {
+                (open < max_connections).then_some(open + 1)
+            });
+        if open.is_err() {
+            spawn(async move {
+                let res = async_h1::accept(stream, |_| async {
+                    Ok(http::Response::new(StatusCode::TooManyRequests))
+                })
+                .await;
+                if let Err(err) = res {
+                    tracing::debug!(%peer_addr, "error rejecting connection: {err}");
+                }
+            });
+            return;
+        }
+
+        let conn = Arc::new(Connection {
+            peer: peer_addr.ip(),
+            subscriptions: self.subscriptions.clone(),
+            subscription: Default::default(),
+            open: self.open.clone(),
+        });
+        let stream = ConnectionStream {
+            stream,
+            _conn: conn.clone(),
+        };
+        spawn(async move {
+            let res = async_h1::accept(stream, |mut req| {
+                req.set_local_addr(local_addr);
+                req.set_peer_addr(Some(peer_addr));
+                req.ext_mut().insert(conn.clone());
+                let server = server.clone();
+                async move { server.respond(req).await }
+            })
+            .await;
+            if let Err(err) = res {
+                tracing::debug!(%peer_addr, "connection error: {err}");
+            }
+        });
+    }
+}
+
+#[async_trait]
+impl<State: Clone + Send + Sync + 'static> Listener<State> for ApiListener<State> {
+    async fn bind(&mut self, mut app: Server<State>) -> io::Result<()> {
+        app.with(SubscriptionMiddleware);
+        for add in self.middleware.drain(..) {
+            add(&mut app);
+        }
+        self.listener = Some(TcpListener::bind(self.addr).await?);
+        self.server = Some(app);
+        Ok(())
+    }
+
+    async fn accept(&mut self) -> io::Result<()> {
+        let server = self
+            .server
+            .take()
+            .expect("`Listener::bind` must be called before `Listener::accept`");
+        let listener = self
+            .listener
+            .take()
+            .expect("`Listener::bind` must be called before `Listener::accept`");
+
+        let mut incoming = listener.incoming();
+        while let Some(stream) = incoming.next().await {
+            match stream {
+                Ok(stream) => self.handle(server.clone(), stream),
+                Err(err) => {
+                    // Errors accepting a connection, such as running out of file descriptors, are
+                    // usually temporary, so keep listening after a short delay.
+                    tracing::warn!("error accepting connection: {err}");
+                    sleep(Duration::from_millis(500)).await;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn info(&self) -> Vec<ListenInfo> {
+        vec![ListenInfo::new(self.to_string(), "tcp".into(), false)]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
+
+    #[test]
+    fn test_subscription_limiter() {
+        setup_logging();
+        setup_backtrace();
+
+        let peer = IpAddr::from([1, 2, 3, 4]);
+        let other_peer = IpAddr::from([5, 6, 7, 8]);
+        let limiter = Arc::new(SubscriptionLimiter::new(Some(2)));
+        let first = limiter.acquire(peer).unwrap();
+        let _second = limiter.acquire(peer).unwrap();
+
+        // Further subscriptions from the same peer are rejected.
+        limiter.acquire(peer).unwrap_err();
+
+        // Other peers have their own limit.
+        let other = limiter.acquire(other_peer).unwrap();
+        let _other = limiter.acquire(other_peer).unwrap();
+        limiter.acquire(other_peer).unwrap_err();
+
+        // Closing a subscription frees up a slot for its peer only.
+        drop(first);
+        limiter.acquire(other_peer).unwrap_err();
+        let _third = limiter.acquire(peer).unwrap();
+        limiter.acquire(peer).unwrap_err();
+        drop(other);
+        limiter.acquire(other_peer).unwrap();
+
+        // With no maximum, subscriptions are unlimited.
+        let limiter = Arc::new(SubscriptionLimiter::default());
+        let _permits = (0..100)
+            .map(|_| limiter.acquire(peer).unwrap())
+            .collect::<Vec<_>>();
+    }
+
+    #[test]
+    fn test_api_module() {
+        assert_eq!(api_module("/submit/submit"), Some("submit"));
+        assert_eq!(api_module("/v0/submit/submit"), Some("submit"));
+        assert_eq!(api_module("/v1/status/block-height"), Some("status"));
+        assert_eq!(api_module("/version"), Some("version"));
+        assert_eq!(api_module("/v0/version"), Some("version"));
+        assert_eq!(api_module("/healthcheck"), Some("healthcheck"));
+        assert_eq!(api_module("/vid/submit"), Some("vid"));
+    }
+}
@@ -1,26 +1,36 @@
 //! Sequencer-specific API options and initialization.
 
 use super::{
+    archive::{archive_loop, BlockArchiver},
+    body_limit::SubmitBodyLimit,
     data_source::{
-        provider, CatchupDataSource, HotShotConfigDataSource, SequencerDataSource,
-        StateSignatureDataSource, SubmitDataSource,
+        provider, CatchupDataSource, ConsensusDataSource, HotShotConfigDataSource,
+        SequencerDataSource, StateSignatureDataSource, SubmitDataSource,
     },
-    endpoints, fs, sql,
+    endpoints,
+    event_replay::{record_events_loop, EventReplay, ReplayingEventsSource},
+    fs,
+    listener::ApiListener,
+    sink::{decide_sink_loop, HttpSink, RetryPolicy},
+    sql,
+    startup_gate::StartupGate,
     update::update_loop,
-    ApiState, StorageState,
+    wait_for_startup_catchup, ApiState, StorageState,
 };
 use crate::{
     context::{SequencerContext, TaskList},
     network,
+    options::{parse_duration, parse_size},
     persistence::{self, SequencerPersistence},
-    state::{update_state_storage_loop, BlockMerkleTree, FeeMerkleTree},
+    state::{update_state_storage_loop, BlockMerkleTree},
 };
 use anyhow::bail;
 use async_std::sync::{Arc, RwLock};
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use derive_more::Display;
 use futures::{
     channel::oneshot,
-    future::{BoxFuture, Future, FutureExt},
+    future::{self, BoxFuture, Either, Future, FutureExt},
 };
 use hotshot_query_service::{
     data_source::{ExtensibleDataSource, MetricsDataSource},
@@ -28,8 +38,8 @@ use hotshot_query_service::{
     Error,
 };
 use hotshot_types::traits::metrics::{Metrics, NoMetrics};
+use std::{path::PathBuf, time::Duration};
 use tide_disco::{
-    listener::RateLimitListener,
     method::{ReadState, WriteState},
     App, Url,
 };
@@ -48,6 +58,8 @@ pub struct Options {
     pub state: Option<State>,
     pub hotshot_events: Option<HotshotEvents>,
     pub explorer: Option<Explorer>,
+    pub decide_sink: Option<DecideSink>,
+    pub block_archive: Option<BlockArchive>,
     pub storage_fs: Option<persistence::fs::Options>,
     pub storage_sql: Option<persistence::sql::Options>,
 }
@@ -64,6 +76,8 @@ impl From<Http> for Options {
             state: None,
             hotshot_events: None,
             explorer: None,
+            decide_sink: None,
+            block_archive: None,
             storage_fs: None,
             storage_sql: None,
         }
@@ -132,6 +146,18 @@ impl Options {
         self
     }
 
+    /// Publish decided leaves to an external message queue.
+    pub fn decide_sink(mut self, opt: DecideSink) -> Self {
+        self.decide_sink = Some(opt);
+        self
+    }
+
+    /// Archive decided blocks to local files.
+    pub fn block_archive(mut self, opt: BlockArchive) -> Self {
+        self.block_archive = Some(opt);
+        self
+    }
+
     /// Whether these options will run the query API.
     pub fn has_query_module(&self) -> bool {
         self.query.is_some() && (self.storage_fs.is_some() || self.storage_sql.is_some())
@@ -160,7 +186,15 @@ impl Options {
             recv_ctx
                 .await
                 .expect("context initialized and sent over channel")
-        });
+        })
+        .with_submit_retry_timeout(self.submit.and_then(|opt| opt.retry_timeout))
+        .with_rejection_log(self.submit.and_then(|opt| opt.rejection_log_size))
+        .with_namespace_rate_limit(
+            self.submit.and_then(|opt| opt.namespace_rate_limit),
+            self.submit.and_then(|opt| opt.namespace_burst),
+        )
+        .with_max_submit_body_size(self.submit.and_then(|opt| opt.max_body_size))
+        .with_idempotency_key_capacity(self.submit.and_then(|opt| opt.idempotency_key_capacity));
         let init_context = move |metrics| {
             let fut = init_context(metrics);
             async move {
@@ -174,6 +208,22 @@ impl Options {
         };
         let mut tasks = TaskList::default();
 
+        if let Some(sink) = &self.decide_sink {
+            tasks.spawn(
+                "decide sink",
+                decide_sink_loop(
+                    HttpSink::<Ver>::new(sink.url.clone()),
+                    RetryPolicy::new(sink.max_attempts, sink.dead_letter_path.clone()),
+                    state.event_stream(),
+                ),
+            );
+        }
+        if let Some(archive) = &self.block_archive {
+            let archiver =
+                BlockArchiver::new(&archive.path, archive.max_file_size, archive.max_file_age)?;
+            tasks.spawn("block archive", archive_loop(archiver, state.event_stream()));
+        }
+
         // The server state type depends on whether we are running a query or status API or not, so
         // we handle the two cases differently.
         let metrics = if let Some(query_opt) = self.query.take() {
@@ -212,6 +262,7 @@ impl Options {
             app.register_module("status", status_api)?;
 
             self.init_hotshot_modules::<_, _, _, Ver>(&mut app)?;
+            let body_limit = self.submit_body_limit(&state);
 
             if self.hotshot_events.is_some() {
                 self.init_and_spawn_hotshot_event_streaming_module(
@@ -221,7 +272,10 @@ impl Options {
                 )?;
             }
 
-            tasks.spawn("API server", self.listen(self.http.port, app, bind_version));
+            tasks.spawn(
+                "API server",
+                self.listen(self.http.port, app, body_limit, None, bind_version),
+            );
 
             metrics
         } else {
@@ -234,6 +288,7 @@ impl Options {
             let mut app = App::<_, Error>::with_state(RwLock::new(state.clone()));
 
             self.init_hotshot_modules::<_, _, _, Ver>(&mut app)?;
+            let body_limit = self.submit_body_limit(&state);
 
             if self.hotshot_events.is_some() {
                 self.init_and_spawn_hotshot_event_streaming_module(
@@ -243,7 +298,10 @@ impl Options {
                 )?;
             }
 
-            tasks.spawn("API server", self.listen(self.http.port, app, bind_version));
+            tasks.spawn(
+                "API server",
+                self.listen(self.http.port, app, body_limit, None, bind_version),
+            );
 
             Box::new(NoMetrics)
         };
@@ -254,6 +312,7 @@ impl Options {
     async fn init_app_modules<N, P, D, Ver: StaticVersionType + 'static>(
         &self,
         ds: D,
+        query_opt: &Query,
         state: ApiState<N, P, Ver>,
         tasks: &mut TaskList,
         bind_version: Ver,
@@ -269,16 +328,25 @@ impl Options {
     {
         let metrics = ds.populate_metrics();
         let ds: endpoints::AvailState<N, P, D, Ver> =
-            Arc::new(RwLock::new(ExtensibleDataSource::new(ds, state.clone())));
+            Arc::new(RwLock::new(ExtensibleDataSource::new(
+                ds,
+                state
+                    .clone()
+                    .with_catchup_timeout(query_opt.catchup_timeout)
+                    .with_catchup_sources(query_opt.catchup_sources.clone())
+                    .with_startup_catchup(
+                        query_opt.startup_catchup_target,
+                        query_opt.startup_catchup_timeout,
+                    ),
+            )));
         let mut app = App::<_, Error>::with_state(ds.clone());
 
-        // Initialize status API
-        if self.status.is_some() {
-            let status_api = status::define_api::<endpoints::AvailState<N, P, D, Ver>, Ver>(
-                &Default::default(),
-                bind_version,
-            )?;
-            app.register_module("status", status_api)?;
+        // Initialize status API. With a query data source, the block height is served from
+        // storage, so nodes whose consensus is not (yet) running can still report the chain tip.
+        // A node waiting to catch up after startup always serves its status API, so operators can
+        // follow its progress.
+        if self.status.is_some() || query_opt.startup_catchup_target.is_some() {
+            app.register_module("status", endpoints::status(bind_version)?)?;
         }
 
         // Initialize availability and node APIs (these both use the same data source).
@@ -289,7 +357,12 @@ impl Options {
 
         tasks.spawn(
             "query storage updater",
-            update_loop(ds.clone(), state.event_stream()),
+            update_loop(
+                ds.clone(),
+                state.event_stream(),
+                query_opt.write_batch_size,
+                query_opt.write_batch_delay,
+            ),
         );
 
         Ok((metrics, ds, app))
@@ -309,14 +382,15 @@ impl Options {
     {
         let ds = <fs::DataSource as SequencerDataSource>::create(
             mod_opt,
-            provider(query_opt.peers, bind_version),
+            provider(query_opt.peers.clone(), bind_version),
             false,
         )
         .await?;
 
-        let (metrics, _, app) = self
-            .init_app_modules(ds, state.clone(), tasks, bind_version)
+        let (metrics, ds, app) = self
+            .init_app_modules(ds, &query_opt, state.clone(), tasks, bind_version)
             .await?;
+        let body_limit = self.submit_body_limit(&state);
 
         if self.hotshot_events.is_some() {
             self.init_and_spawn_hotshot_event_streaming_module(state, tasks, bind_version)?;
@@ -324,7 +398,7 @@ impl Options {
 
         tasks.spawn(
             "API server",
-            self.listen(self.http.port, app, Ver::instance()),
+            self.listen_after_startup_catchup(ds, app, body_limit, bind_version),
         );
         Ok(metrics)
     }
@@ -348,8 +422,9 @@ impl Options {
         )
         .await?;
         let (metrics, ds, mut app) = self
-            .init_app_modules(ds, state.clone(), tasks, bind_version)
+            .init_app_modules(ds, &query_opt, state.clone(), tasks, bind_version)
             .await?;
+        let body_limit = self.submit_body_limit(&state);
 
         if self.explorer.is_some() {
             app.register_module("explorer", endpoints::explorer(bind_version)?)?;
@@ -362,16 +437,13 @@ impl Options {
                 endpoints::merklized_state::<N, P, _, BlockMerkleTree, _, 3>(bind_version)?,
             )?;
             // Initialize merklized state module for fee merkle tree
-            app.register_module(
-                "fee-state",
-                endpoints::merklized_state::<N, P, _, FeeMerkleTree, _, 256>(bind_version)?,
-            )?;
+            app.register_module("fee-state", endpoints::fee_state::<N, P, _, _>(bind_version)?)?;
 
             let state = state.clone();
             let get_node_state = async move { state.node_state().await.clone() };
             tasks.spawn(
                 "merklized state storage update loop",
-                update_state_storage_loop(ds, get_node_state),
+                update_state_storage_loop(ds.clone(), get_node_state),
             );
         }
 
@@ -381,7 +453,7 @@ impl Options {
 
         tasks.spawn(
             "API server",
-            self.listen(self.http.port, app, Ver::instance()),
+            self.listen_after_startup_catchup(ds, app, body_limit, bind_version),
         );
         Ok(metrics)
     }
@@ -403,7 +475,8 @@ impl Options {
             + SubmitDataSource<N, P>
             + StateSignatureDataSource<N>
             + CatchupDataSource
-            + HotShotConfigDataSource,
+            + HotShotConfigDataSource
+            + ConsensusDataSource,
         N: network::Type,
     {
         let bind_version = Ver::instance();
@@ -449,7 +522,16 @@ impl Options {
         // EventsSource trait, which is currently intended not to implement to separate hotshot-query-service crate, and
         // hotshot-events-service crate.
 
-        let mut app = App::<_, EventStreamingError>::with_state(RwLock::new(state));
+        let opt = self.hotshot_events.unwrap();
+        let replay = EventReplay::new(opt.replay_buffer_size);
+        tasks.spawn(
+            "Hotshot Events replay buffer",
+            record_events_loop(replay.clone(), state.clone()),
+        );
+
+        let mut app = App::<_, EventStreamingError>::with_state(RwLock::new(
+            ReplayingEventsSource::new(state, replay),
+        ));
 
         tracing::info!("initializing hotshot events API");
         let hotshot_events_api = hotshot_events_service::events::define_api(
@@ -460,20 +542,74 @@ impl Options {
 
         tasks.spawn(
             "Hotshot Events Streaming API server",
-            self.listen(
-                self.hotshot_events.unwrap().events_service_port,
-                app,
-                bind_version,
-            ),
+            self.listen(opt.events_service_port, app, None, None, bind_version),
         );
 
         Ok(())
     }
 
+    /// Serve `app`, holding most of it back until this node has caught up to the chain tip if a
+    /// startup catchup target is configured.
+    ///
+    /// While the node is catching up, only the status API is served, so that clients do not get
+    /// stale responses but operators can still follow its progress. The rest of the API is served
+    /// by the same server as soon as the node is ready, so the port stays open throughout.
+    fn listen_after_startup_catchup<N, P, D, Ver>(
+        &self,
+        ds: endpoints::AvailState<N, P, D, Ver>,
+        app: App<endpoints::AvailState<N, P, D, Ver>, Error>,
+        body_limit: Option<SubmitBodyLimit>,
+        bind_version: Ver,
+    ) -> BoxFuture<'static, anyhow::Result<()>>
+    where
+        N: network::Type,
+        P: SequencerPersistence,
+        D: SequencerDataSource + Send + Sync + 'static,
+        Ver: StaticVersionType + 'static,
+    {
+        let gate = {
+            let ds = ds.clone();
+            StartupGate::new(move || {
+                let ds = ds.clone();
+                async move { ds.read().await.as_ref().startup_catchup.ready() }.boxed()
+            })
+        };
+        let serve = self.listen(self.http.port, app, body_limit, Some(gate), bind_version);
+        async move {
+            match future::select(serve.boxed(), wait_for_startup_catchup(ds).boxed()).await {
+                Either::Left((res, _)) => res,
+                Either::Right(((), serve)) => serve.await,
+            }
+        }
+        .boxed()
+    }
+
+    /// The limit on the body size of submission requests, if the submit API is enabled.
+    fn submit_body_limit<N, P, Ver>(&self, state: &ApiState<N, P, Ver>) -> Option<SubmitBodyLimit>
+    where
+        N: network::Type,
+        P: SequencerPersistence,
+        Ver: StaticVersionType + 'static,
+    {
+        let state = state.clone();
+        self.submit.map(|_| {
+            SubmitBodyLimit::new(move || {
+                let state = state.clone();
+                async move { state.max_submit_body_size().await }.boxed()
+            })
+        })
+    }
+
+    /// Serve `app` on `port`, limiting the body size of submission requests if `body_limit` is set
+    /// and holding back requests behind `startup_gate` if it is set.
+    ///
+    /// Connections and subscriptions are limited according to the HTTP options.
     fn listen<S, E, Ver>(
         &self,
         port: u16,
         app: App<S, E>,
+        body_limit: Option<SubmitBodyLimit>,
+        startup_gate: Option<StartupGate>,
         bind_version: Ver,
     ) -> impl Future<Output = anyhow::Result<()>>
     where
@@ -481,15 +617,17 @@ impl Options {
         E: Send + Sync + tide_disco::Error,
         Ver: StaticVersionType + 'static,
     {
-        let max_connections = self.http.max_connections;
+        let mut listener =
+            ApiListener::new(port, self.http.max_connections, self.http.max_subscriptions);
+        if let Some(startup_gate) = startup_gate {
+            listener = listener.with(startup_gate);
+        }
+        if let Some(body_limit) = body_limit {
+            listener = listener.with(body_limit);
+        }
 
         async move {
-            if let Some(limit) = max_connections {
-                app.serve(RateLimitListener::with_port(port, limit), bind_version)
-                    .await?;
-            } else {
-                app.serve(format!("0.0.0.0:{}", port), bind_version).await?;
-            }
+            app.serve(listener, bind_version).await?;
             Ok(())
         }
     }
@@ -512,6 +650,17 @@ pub struct Http {
     /// Leave unset for no connection limit.
     #[clap(long, env = "ESPRESSO_SEQUENCER_MAX_CONNECTIONS")]
     pub max_connections: Option<usize>,
+
+    /// Maximum number of concurrent subscriptions each peer may hold open.
+    ///
+    /// Each subscription to a streaming endpoint holds server resources for as long as it is open.
+    /// Subscriptions are counted per peer IP address across all streaming endpoints, including
+    /// those provided by the query service, so one client cannot exhaust the limit for others.
+    /// Subscriptions beyond this limit are rejected with a 429 response.
+    ///
+    /// Leave unset for no subscription limit.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_MAX_SUBSCRIPTIONS")]
+    pub max_subscriptions: Option<usize>,
 }
 
 impl Http {
@@ -520,13 +669,65 @@ impl Http {
         Self {
             port,
             max_connections: None,
+            max_subscriptions: None,
         }
     }
 }
 
 /// Options for the submission API module.
 #[derive(Parser, Clone, Copy, Debug, Default)]
-pub struct Submit;
+pub struct Submit {
+    /// Maximum total time to spend retrying a transaction submission which fails transiently.
+    ///
+    /// Transactions which fail validation are rejected immediately. Other failures to hand a
+    /// transaction to consensus are retried with exponential backoff until this much time has
+    /// passed, and only then reported to the client. If not set, failures are not retried.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_SUBMIT_RETRY_TIMEOUT",
+        value_parser = parse_duration
+    )]
+    pub retry_timeout: Option<Duration>,
+
+    /// Number of recently rejected transaction submissions to keep in memory.
+    ///
+    /// Rejected submissions are always logged. If this is set, the most recent ones are also kept,
+    /// with the reason for rejection, and served at `submit/rejections`.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_SUBMIT_REJECTION_LOG_SIZE")]
+    pub rejection_log_size: Option<usize>,
+
+    /// Maximum sustained rate of transaction submissions to each namespace, per second.
+    ///
+    /// Each namespace is limited separately, so one namespace submitting too quickly does not
+    /// affect others. Submissions beyond the limit are rejected with a 429 response. If not set,
+    /// submissions are not rate limited.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_SUBMIT_NAMESPACE_RATE_LIMIT")]
+    pub namespace_rate_limit: Option<u64>,
+
+    /// Number of transactions a namespace may submit at once before being rate limited.
+    ///
+    /// Defaults to the value of `namespace_rate_limit`. Has no effect unless `namespace_rate_limit`
+    /// is set.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_SUBMIT_NAMESPACE_BURST")]
+    pub namespace_burst: Option<u64>,
+
+    /// Maximum size in bytes of the body of a transaction submission request.
+    ///
+    /// Larger requests are rejected with a 413 response before the transaction is deserialized,
+    /// regardless of the size of the transaction they contain. Defaults to twice the maximum block
+    /// size in the chain config.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_SUBMIT_MAX_BODY_SIZE")]
+    pub max_body_size: Option<u64>,
+
+    /// Number of recent idempotency keys to remember.
+    ///
+    /// Keys are scoped to the namespace of the submitted transaction. A submission carrying an
+    /// `Idempotency-Key` header which matches a remembered key is not submitted again if it is the
+    /// same transaction, and is rejected with a 409 response otherwise. Once this many keys are
+    /// remembered, the oldest is forgotten. Defaults to 10000.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_SUBMIT_IDEMPOTENCY_KEY_CAPACITY")]
+    pub idempotency_key_capacity: Option<usize>,
+}
 
 /// Options for the status API module.
 #[derive(Parser, Clone, Copy, Debug, Default)]
@@ -546,6 +747,149 @@ pub struct Query {
     /// Peers for fetching missing data for the query service.
     #[clap(long, env = "ESPRESSO_SEQUENCER_API_PEERS")]
     pub peers: Vec<Url>,
+
+    /// Maximum time to wait for each source when serving a state catchup request.
+    ///
+    /// Catchup requests are served from each of `catchup_sources` in turn. If a source does not
+    /// respond within this timeout, it is abandoned and the next source is tried (or the request
+    /// fails, if there are no more sources). If not set, there is no timeout.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_API_CATCHUP_TIMEOUT", value_parser = parse_duration)]
+    pub catchup_timeout: Option<Duration>,
+
+    /// Comma-separated list of sources to consult, in order, when serving a state catchup request.
+    ///
+    /// The sources are `memory` (states held by consensus), `storage` (the local database) and
+    /// `peers` (the state peers this node itself catches up from). Each source is tried in turn
+    /// until one has the requested state. If empty, memory is tried first, then storage.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_API_CATCHUP_SOURCES",
+        value_delimiter = ',',
+        default_value = "memory,storage"
+    )]
+    pub catchup_sources: Vec<CatchupSource>,
+
+    /// Maximum number of decide events to write to query storage in a single transaction.
+    ///
+    /// Batching writes reduces write amplification when blocks are decided at a high rate. A batch
+    /// is held in memory until it is written, so readers never see part of a batch. Blocks in a
+    /// batch which has not yet been written are lost if the node crashes. Consensus does not decide
+    /// them again on restart, since it resumes after its last decided leaf, so they are only filled
+    /// in later by fetching them from peers. A value of 0 or 1 writes each decide as it arrives.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_API_WRITE_BATCH_SIZE", default_value = "1")]
+    pub write_batch_size: usize,
+
+    /// Maximum time a decided block may wait in a write batch before it is written.
+    ///
+    /// A partial batch is committed once its first block has waited this long, so that blocks are
+    /// persisted promptly even when they are decided at a low rate.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_API_WRITE_BATCH_DELAY",
+        value_parser = parse_duration,
+        default_value = "1s"
+    )]
+    pub write_batch_delay: Duration,
+
+    /// Hold back the API until the query store is within this many blocks of the chain tip.
+    ///
+    /// A restarting node may be far behind the chain, and would serve stale data until it catches
+    /// up. If this is set, the node only serves its status API, including
+    /// `status/catchup-progress`, until its block height is within this many blocks of the highest
+    /// block height reported by its state peers, or until `startup_catchup_timeout` expires. Other
+    /// requests fail with status 503 in the meantime. If not set, the full API is served
+    /// immediately.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_API_STARTUP_CATCHUP_TARGET")]
+    pub startup_catchup_target: Option<u64>,
+
+    /// Maximum time to hold back the API while catching up to the chain tip after startup.
+    ///
+    /// If the node has not caught up within `startup_catchup_target` blocks of the tip by this
+    /// time, for example because none of its state peers are reachable, it serves its full API
+    /// anyway. Only used if `startup_catchup_target` is set.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_API_STARTUP_CATCHUP_TIMEOUT",
+        value_parser = parse_duration,
+        default_value = "10m"
+    )]
+    pub startup_catchup_timeout: Duration,
+}
+
+/// A source of state for serving catchup requests.
+#[derive(Clone, Copy, Debug, Display, PartialEq, Eq, ValueEnum)]
+pub enum CatchupSource {
+    /// States held in memory by consensus.
+    #[display(fmt = "memory")]
+    Memory,
+    /// States in the local database.
+    #[display(fmt = "storage")]
+    Storage,
+    /// The state peers this node itself catches up from.
+    ///
+    /// Requests made by another node catching up are never forwarded to peers, so a request is
+    /// forwarded at most once, even if nodes with this source catch up from each other.
+    #[display(fmt = "peers")]
+    Peers,
+}
+
+/// Options for publishing decided leaves to an external message queue.
+#[derive(Parser, Clone, Debug)]
+pub struct DecideSink {
+    /// HTTP endpoint of the message queue to publish decided leaves to.
+    ///
+    /// Each decided leaf is POSTed as a JSON message. This is compatible with HTTP gateways for
+    /// common message queues, such as the Kafka REST proxy.
+    #[clap(long = "decide-sink-url", env = "ESPRESSO_SEQUENCER_DECIDE_SINK_URL")]
+    pub url: Url,
+
+    /// Maximum number of attempts to publish each decided leaf.
+    ///
+    /// Transient failures are retried with exponential backoff up to this many attempts. A leaf
+    /// which still has not been accepted, or which the endpoint rejects with a 4xx status other
+    /// than 408 or 429, is skipped so that it does not block later leaves.
+    #[clap(
+        long = "decide-sink-max-attempts",
+        env = "ESPRESSO_SEQUENCER_DECIDE_SINK_MAX_ATTEMPTS",
+        default_value = "10"
+    )]
+    pub max_attempts: u32,
+
+    /// File to which skipped leaves are appended, one JSON message per line.
+    ///
+    /// If not set, skipped leaves are only logged.
+    #[clap(
+        long = "decide-sink-dead-letter-path",
+        env = "ESPRESSO_SEQUENCER_DECIDE_SINK_DEAD_LETTER_PATH"
+    )]
+    pub dead_letter_path: Option<PathBuf>,
+}
+
+/// Options for archiving decided blocks to local files.
+#[derive(Parser, Clone, Debug)]
+pub struct BlockArchive {
+    /// Directory in which to store the archive.
+    #[clap(long = "block-archive-path", env = "ESPRESSO_SEQUENCER_BLOCK_ARCHIVE_PATH")]
+    pub path: PathBuf,
+
+    /// Start a new archive file once the current one reaches this size.
+    #[clap(
+        long = "block-archive-max-file-size",
+        env = "ESPRESSO_SEQUENCER_BLOCK_ARCHIVE_MAX_FILE_SIZE",
+        default_value = "1gb",
+        value_parser = parse_size
+    )]
+    pub max_file_size: u64,
+
+    /// Start a new archive file once the current one has been open this long.
+    ///
+    /// If not set, files are rotated only by size.
+    #[clap(
+        long = "block-archive-max-file-age",
+        env = "ESPRESSO_SEQUENCER_BLOCK_ARCHIVE_MAX_FILE_AGE",
+        value_parser = parse_duration
+    )]
+    pub max_file_age: Option<Duration>,
 }
 
 /// Options for the state API module.
@@ -558,6 +902,17 @@ pub struct HotshotEvents {
     /// Port that the HTTP Hotshot Event streaming API will use.
     #[clap(long, env = "ESPRESSO_SEQUENCER_HOTSHOT_EVENT_STREAMING_API_PORT")]
     pub events_service_port: u16,
+
+    /// Number of recent events to replay to each new subscriber.
+    ///
+    /// This allows builders which reconnect after a short disconnect to receive the events they
+    /// missed.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_HOTSHOT_EVENT_STREAMING_REPLAY_BUFFER_SIZE",
+        default_value = "100"
+    )]
+    pub replay_buffer_size: usize,
 }
 
 /// Options for the explorer API module.
@@ -0,0 +1,283 @@
+//! Push decided leaves to an external message queue.
+//!
+//! Some indexers prefer to have decided data pushed to them via a message queue (e.g. a Kafka REST
+//! proxy or a NATS HTTP gateway) rather than polling or subscribing to the query service. The sink
+//! in this module follows the HotShot event stream and publishes each decided leaf, in order of
+//! increasing height, to a configured HTTP endpoint.
+//!
+//! Delivery is at-least-once for leaves which the endpoint eventually accepts: a leaf is retried
+//! with exponential backoff, and later leaves are not published until earlier ones have been
+//! delivered or given up on. Consumers should therefore be prepared to deduplicate messages by
+//! height. A leaf which the endpoint rejects permanently (with a 4xx status other than 408 or 429),
+//! or which still fails after the maximum number of attempts, is skipped so that it cannot block
+//! later leaves. Skipped leaves are logged and, if configured, appended to a dead-letter file.
+
+use crate::{catchup::backoff, catchup::MIN_RETRY_DELAY, Leaf, SeqTypes};
+use anyhow::Context;
+use async_std::{fs::OpenOptions, io::WriteExt, task::sleep};
+use futures::{
+    future::Future,
+    stream::{Stream, StreamExt},
+};
+use hotshot::types::{Event, EventType};
+use hotshot_types::{data::ViewNumber, event::LeafInfo};
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use surf_disco::{Client, Url};
+use tide_disco::{error::ServerError, StatusCode};
+use vbs::version::StaticVersionType;
+
+/// A message published to the sink for each decided leaf.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DecidedLeafMessage {
+    pub height: u64,
+    pub view: ViewNumber,
+    pub leaf: Leaf,
+}
+
+impl From<&Leaf> for DecidedLeafMessage {
+    fn from(leaf: &Leaf) -> Self {
+        Self {
+            height: leaf.height(),
+            view: leaf.view_number(),
+            leaf: leaf.clone(),
+        }
+    }
+}
+
+/// A failure to publish a message to the sink.
+#[derive(Debug)]
+pub(super) enum PublishError {
+    /// The failure may be resolved by retrying.
+    Transient(anyhow::Error),
+    /// The sink will never accept this message.
+    Permanent(anyhow::Error),
+}
+
+/// A destination for decided leaf messages.
+pub(super) trait Sink {
+    fn publish(
+        &self,
+        msg: &DecidedLeafMessage,
+    ) -> impl Send + Future<Output = Result<(), PublishError>>;
+}
+
+/// A sink which POSTs each message as JSON to an HTTP endpoint.
+pub(super) struct HttpSink<Ver: StaticVersionType> {
+    client: Client<ServerError, Ver>,
+}
+
+impl<Ver: StaticVersionType> HttpSink<Ver> {
+    pub(super) fn new(url: Url) -> Self {
+        tracing::info!(%url, "publishing decided leaves to external sink");
+        Self {
+            client: Client::new(url),
+        }
+    }
+}
+
+impl<Ver: StaticVersionType> Sink for HttpSink<Ver> {
+    async fn publish(&self, msg: &DecidedLeafMessage) -> Result<(), PublishError> {
+        let req = self
+            .client
+            .post::<serde_json::Value>("")
+            .body_json(msg)
+            .map_err(|err| PublishError::Permanent(err.into()))?;
+        match req.send().await {
+            Ok(_) => Ok(()),
+            Err(err) if is_permanent(err.status) => Err(PublishError::Permanent(err.into())),
+            Err(err) => Err(PublishError::Transient(err.into())),
+        }
+    }
+}
+
+/// Whether a response status means the sink will never accept the request.
+fn is_permanent(status: StatusCode) -> bool {
+    status.is_client_error()
+        && status != StatusCode::REQUEST_TIMEOUT
+        && status != StatusCode::TOO_MANY_REQUESTS
+}
+
+/// How to handle messages which the sink does not accept.
+#[derive(Clone, Debug)]
+pub(super) struct RetryPolicy {
+    /// Maximum number of attempts to publish each message.
+    pub(super) max_attempts: u32,
+    /// Delay before the first retry, which grows with exponential backoff.
+    pub(super) min_delay: Duration,
+    /// File to which messages are appended, as JSON lines, when they are given up on.
+    pub(super) dead_letter: Option<PathBuf>,
+}
+
+impl RetryPolicy {
+    pub(super) fn new(max_attempts: u32, dead_letter: Option<PathBuf>) -> Self {
+        Self {
+            max_attempts,
+            min_delay: MIN_RETRY_DELAY,
+            dead_letter,
+        }
+    }
+}
+
+pub(super) async fn decide_sink_loop(
+    sink: impl Sink,
+    policy: RetryPolicy,
+    mut events: impl Stream<Item = Event<SeqTypes>> + Unpin,
+) {
+    while let Some(event) = events.next().await {
+        let EventType::Decide { leaf_chain, .. } = event.event else {
+            continue;
+        };
+
+        // The leaf chain is ordered from newest to oldest; publish in order of increasing height.
+        for LeafInfo { leaf, .. } in leaf_chain.iter().rev() {
+            publish(&sink, &policy, &DecidedLeafMessage::from(leaf)).await;
+        }
+    }
+    tracing::warn!("end of HotShot event stream, decide sink task will exit");
+}
+
+/// Publish a message to the sink, retrying transient failures up to the policy's limit.
+///
+/// Returns whether the message was delivered. Messages which are not delivered are dead-lettered.
+async fn publish(sink: &impl Sink, policy: &RetryPolicy, msg: &DecidedLeafMessage) -> bool {
+    let mut delay = policy.min_delay;
+    let mut attempt = 1;
+    let err = loop {
+        match sink.publish(msg).await {
+            Ok(()) => {
+                tracing::debug!(height = msg.height, "published decided leaf to sink");
+                return true;
+            }
+            Err(PublishError::Permanent(err)) => break err,
+            Err(PublishError::Transient(err)) if attempt >= policy.max_attempts => break err,
+            Err(PublishError::Transient(err)) => {
+                tracing::warn!(
+                    height = msg.height,
+                    attempt,
+                    "failed to publish decided leaf to sink, will retry in {delay:?}: {err:#}"
+                );
+                sleep(delay).await;
+                delay = backoff(delay);
+                attempt += 1;
+            }
+        }
+    };
+
+    tracing::error!(
+        height = msg.height,
+        attempt,
+        "giving up on publishing decided leaf to sink: {err:#}"
+    );
+    if let Some(path) = &policy.dead_letter {
+        if let Err(err) = dead_letter(path, msg).await {
+            tracing::error!(
+                height = msg.height,
+                "failed to dead-letter decided leaf: {err:#}"
+            );
+        }
+    }
+    false
+}
+
+/// Append a message which could not be delivered to the dead-letter file.
+async fn dead_letter(path: &Path, msg: &DecidedLeafMessage) -> anyhow::Result<()> {
+    let mut line = serde_json::to_vec(msg)?;
+    line.push(b'\n');
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+        .context(format!("opening {}", path.display()))?;
+    file.write_all(&line).await?;
+    file.flush().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::NodeState;
+    use async_std::sync::Mutex;
+    use std::collections::HashMap;
+
+    /// A sink which rejects some heights permanently and fails others a number of times.
+    #[derive(Default)]
+    struct FakeSink {
+        rejected: Vec<u64>,
+        failures: Mutex<HashMap<u64, u32>>,
+        delivered: Mutex<Vec<u64>>,
+    }
+
+    impl Sink for FakeSink {
+        async fn publish(&self, msg: &DecidedLeafMessage) -> Result<(), PublishError> {
+            if self.rejected.contains(&msg.height) {
+                return Err(PublishError::Permanent(anyhow::anyhow!("rejected")));
+            }
+            if let Some(failures) = self.failures.lock().await.get_mut(&msg.height) {
+                if *failures > 0 {
+                    *failures -= 1;
+                    return Err(PublishError::Transient(anyhow::anyhow!("unavailable")));
+                }
+            }
+            self.delivered.lock().await.push(msg.height);
+            Ok(())
+        }
+    }
+
+    #[async_std::test]
+    async fn test_decide_sink_skips_undeliverable_messages() {
+        let instance = NodeState::mock();
+        let leaf = Leaf::genesis(&instance.genesis_state, &instance).await;
+        let msg = |height| DecidedLeafMessage {
+            height,
+            ..DecidedLeafMessage::from(&leaf)
+        };
+
+        // Height 1 is rejected outright, height 2 recovers after transient failures and height 3
+        // never recovers.
+        let sink = FakeSink {
+            rejected: vec![1],
+            failures: Mutex::new([(2, 2), (3, u32::MAX)].into()),
+            ..Default::default()
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dead-letter.jsonl");
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            min_delay: Duration::from_millis(1),
+            dead_letter: Some(path.clone()),
+        };
+
+        let mut delivered = vec![];
+        for height in 0..5 {
+            delivered.push(publish(&sink, &policy, &msg(height)).await);
+        }
+        assert_eq!(delivered, [true, false, true, false, true]);
+        assert_eq!(*sink.delivered.lock().await, [0, 2, 4]);
+
+        // The undeliverable messages were dead-lettered, in order.
+        let dead = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<DecidedLeafMessage>(line).unwrap().height)
+            .collect::<Vec<_>>();
+        assert_eq!(dead, [1, 3]);
+
+        // Height 3 was attempted exactly `max_attempts` times.
+        assert_eq!(sink.failures.lock().await[&3], u32::MAX - 3);
+    }
+
+    #[test]
+    fn test_is_permanent() {
+        assert!(is_permanent(StatusCode::BAD_REQUEST));
+        assert!(is_permanent(StatusCode::PAYLOAD_TOO_LARGE));
+        assert!(!is_permanent(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_permanent(StatusCode::REQUEST_TIMEOUT));
+        assert!(!is_permanent(StatusCode::INTERNAL_SERVER_ERROR));
+    }
+}
@@ -1,24 +1,26 @@
 use super::{
     data_source::{CatchupDataSource, Provider, SequencerDataSource},
-    AccountQueryData, BlocksFrontier,
+    AccountQueryData, BlockMerkleProofQueryData, BlocksFrontier,
 };
 use crate::{
     persistence::sql::Options,
-    state::{BlockMerkleTree, FeeAccountProof, FeeMerkleTree},
+    state::{BlockMerkleCommitment, BlockMerkleTree, FeeAccountProof, FeeMerkleTree},
     SeqTypes,
 };
-use anyhow::{bail, Context};
+use anyhow::{bail, ensure, Context};
 use async_trait::async_trait;
 use ethers::prelude::Address;
 use hotshot_query_service::{
     data_source::{
         sql::{Config, SqlDataSource},
-        storage::SqlStorage,
+        storage::{sql::Query, SqlStorage},
     },
-    merklized_state::{MerklizedStateDataSource, Snapshot},
+    merklized_state::{MerklizedStateDataSource, MerklizedStateHeightPersistence, Snapshot},
 };
-use hotshot_types::data::ViewNumber;
-use jf_merkle_tree::{prelude::MerkleNode, MerkleTreeScheme};
+use hotshot_types::{
+    data::ViewNumber, traits::node_implementation::ConsensusTime, utils::BuilderCommitment,
+};
+use jf_merkle_tree::{prelude::MerkleNode, MerkleCommitment, MerkleTreeScheme};
 
 pub type DataSource = SqlDataSource<SeqTypes, Provider>;
 
@@ -50,6 +52,24 @@ impl SequencerDataSource for DataSource {
 
         builder.build().await
     }
+
+    async fn get_block_height_by_builder_commitment(
+        &self,
+        commitment: &BuilderCommitment,
+    ) -> anyhow::Result<u64> {
+        let row = self
+            .storage()
+            .await
+            .query_opt(
+                "SELECT height FROM header WHERE builder_commitment = $1
+                  ORDER BY height DESC LIMIT 1",
+                [&commitment.to_string()],
+            )
+            .await?
+            .context(format!("no block with builder commitment {commitment}"))?;
+        let height: i64 = row.get("height");
+        Ok(height as u64)
+    }
 }
 
 impl CatchupDataSource for SqlStorage {
@@ -70,15 +90,15 @@ impl CatchupDataSource for SqlStorage {
         match proof.proof.first().context(format!(
             "empty proof for account {account}; height {height}"
         ))? {
-            MerkleNode::Leaf { pos, elem, .. } => Ok(AccountQueryData {
-                balance: (*elem).into(),
-                proof: FeeAccountProof::presence(*pos, proof),
-            }),
-
-            MerkleNode::Empty => Ok(AccountQueryData {
-                balance: 0_u64.into(),
-                proof: FeeAccountProof::absence(account.into(), proof),
-            }),
+            MerkleNode::Leaf { pos, elem, .. } => Ok(AccountQueryData::new(
+                (*elem).into(),
+                FeeAccountProof::presence(*pos, proof),
+            )),
+
+            MerkleNode::Empty => Ok(AccountQueryData::new(
+                0_u64.into(),
+                FeeAccountProof::absence(account.into(), proof),
+            )),
             _ => {
                 bail!("Invalid proof");
             }
@@ -93,6 +113,49 @@ impl CatchupDataSource for SqlStorage {
         .await
         .context(format!("fetching frontier at height {height}"))
     }
+
+    async fn get_block_proof(&self, height: u64) -> anyhow::Result<BlockMerkleProofQueryData> {
+        let root_height = self.get_last_state_height().await? as u64;
+        ensure!(
+            height < root_height,
+            "block {height} is not yet in the block Merkle tree (latest state height is \
+             {root_height})"
+        );
+        let proof = self
+            .get_path(
+                Snapshot::<SeqTypes, BlockMerkleTree, { BlockMerkleTree::ARITY }>::Index(
+                    root_height,
+                ),
+                height,
+            )
+            .await
+            .context(format!("fetching proof for block {height} at height {root_height}"))?;
+        Ok(BlockMerkleProofQueryData { root_height, proof })
+    }
+
+    async fn get_account_at_root(
+        &self,
+        root: BlockMerkleCommitment,
+        account: Address,
+    ) -> anyhow::Result<AccountQueryData> {
+        // The block Merkle tree in the state for a block contains all blocks before it, so the size
+        // of the tree is the height of the state.
+        let height = root.size();
+        ensure!(height > 0, "no state is stored for an empty block Merkle tree");
+
+        // Looking up a path by commitment fails unless the stored state at this height has exactly
+        // this root.
+        self.get_path(
+            Snapshot::<SeqTypes, BlockMerkleTree, { BlockMerkleTree::ARITY }>::Commit(root),
+            height - 1,
+        )
+        .await
+        .context(format!("state with block Merkle root at height {height} not stored"))?;
+
+        // Storage is indexed by height, so the view is not needed.
+        self.get_account(height, ViewNumber::genesis(), account)
+            .await
+    }
 }
 
 impl CatchupDataSource for DataSource {
@@ -110,6 +173,21 @@ impl CatchupDataSource for DataSource {
     async fn get_frontier(&self, height: u64, view: ViewNumber) -> anyhow::Result<BlocksFrontier> {
         self.storage().await.get_frontier(height, view).await
     }
+
+    async fn get_block_proof(&self, height: u64) -> anyhow::Result<BlockMerkleProofQueryData> {
+        self.storage().await.get_block_proof(height).await
+    }
+
+    async fn get_account_at_root(
+        &self,
+        root: BlockMerkleCommitment,
+        account: Address,
+    ) -> anyhow::Result<AccountQueryData> {
+        self.storage()
+            .await
+            .get_account_at_root(root, account)
+            .await
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,53 @@
+//! Hold back the API while a node catches up to the chain tip after startup.
+//!
+//! A restarting node would serve stale data until it catches up. Rather than serving a separate
+//! status-only server in the meantime, and swapping it for the full API once caught up, the full
+//! API is served from the start with [`StartupGate`] in front of every handler. Until the node is
+//! ready, the gate answers requests for anything but the status, healthcheck and version routes
+//! with a 503, so the port never stops accepting connections.
+
+use super::listener::api_module;
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use std::{
+    fmt::{self, Debug, Formatter},
+    sync::Arc,
+};
+use tide::{Middleware, Next, Request, Response, StatusCode};
+
+/// Modules which are served while the node is still catching up.
+const UNGATED_MODULES: [&str; 3] = ["status", "healthcheck", "version"];
+
+/// Middleware rejecting requests to most of the API until the node is ready to serve them.
+///
+/// Whether the node is ready is looked up for each request.
+#[derive(Clone)]
+pub(super) struct StartupGate(Arc<dyn Fn() -> BoxFuture<'static, bool> + Send + Sync>);
+
+impl StartupGate {
+    pub(super) fn new(
+        ready: impl Fn() -> BoxFuture<'static, bool> + Send + Sync + 'static,
+    ) -> Self {
+        Self(Arc::new(ready))
+    }
+}
+
+impl Debug for StartupGate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StartupGate").finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl<State: Clone + Send + Sync + 'static> Middleware<State> for StartupGate {
+    async fn handle(&self, req: Request<State>, next: Next<'_, State>) -> tide::Result {
+        let ungated = api_module(req.url().path()).is_some_and(|m| UNGATED_MODULES.contains(&m));
+        if ungated || (self.0)().await {
+            return Ok(next.run(req).await);
+        }
+
+        let mut res = Response::new(StatusCode::ServiceUnavailable);
+        res.set_body("node is catching up to the chain tip, see status/catchup-progress");
+        Ok(res)
+    }
+}
@@ -2,50 +2,115 @@
 
 use super::{data_source::SequencerDataSource, StorageState};
 use crate::{network, persistence::SequencerPersistence, SeqTypes};
-use async_std::sync::{Arc, RwLock};
+use async_std::{
+    future::timeout,
+    sync::{Arc, RwLock},
+};
 use futures::stream::{Stream, StreamExt};
-use hotshot::types::Event;
+use hotshot::types::{Event, EventType};
 use hotshot_query_service::data_source::{UpdateDataSource, VersionedDataSource};
+use std::{
+    mem,
+    time::{Duration, Instant},
+};
 use vbs::version::StaticVersionType;
 
+/// Apply HotShot events to the query API state.
+///
+/// Decide events are held in memory and written to storage in batches of up to `max_batch_size`
+/// events. Each batch is applied and committed in a single database transaction while holding the
+/// lock on the state, so readers never see a partially written batch. A partial batch is written
+/// once its first event has been waiting for `max_batch_delay`. Other events are applied as soon
+/// as they arrive.
 pub(super) async fn update_loop<N, P, D, Ver: StaticVersionType>(
     state: Arc<RwLock<StorageState<N, P, D, Ver>>>,
     mut events: impl Stream<Item = Event<SeqTypes>> + Unpin,
+    max_batch_size: usize,
+    max_batch_delay: Duration,
 ) where
     N: network::Type,
     P: SequencerPersistence,
     D: SequencerDataSource + Send + Sync,
 {
+    // The decide events not yet written, and the time at which the first of them arrived.
+    let mut batch = vec![];
+    let mut batch_start = Instant::now();
+
     tracing::debug!("waiting for event");
-    while let Some(event) = events.next().await {
-        let mut state = state.write().await;
+    loop {
+        // If there is a pending batch, only wait for the next event until the batch is due.
+        let event = if batch.is_empty() {
+            events.next().await
+        } else {
+            let delay = max_batch_delay.saturating_sub(batch_start.elapsed());
+            match timeout(delay, events.next()).await {
+                Ok(event) => event,
+                Err(_) => {
+                    write_batch(&mut *state.write().await, mem::take(&mut batch)).await;
+                    continue;
+                }
+            }
+        };
+        let Some(event) = event else {
+            break;
+        };
 
-        // If update results in an error, revert to undo partial state changes. We will continue
-        // streaming events, as we can update our state based on future events and then filling in
-        // the missing part of the state later, by fetching from a peer.
-        if let Err(err) = update_state(&mut *state, &event).await {
-            tracing::error!(
-                ?event,
-                %err,
-                "failed to update API state",
-            );
-            state.revert().await;
+        if !matches!(event.event, EventType::Decide { .. }) {
+            write_batch(&mut *state.write().await, vec![event]).await;
+            continue;
+        }
+        if batch.is_empty() {
+            batch_start = Instant::now();
+        }
+        batch.push(event);
+        if batch.len() >= max_batch_size || batch_start.elapsed() >= max_batch_delay {
+            write_batch(&mut *state.write().await, mem::take(&mut batch)).await;
         }
     }
+
+    // Don't drop the last batch.
+    if !batch.is_empty() {
+        write_batch(&mut *state.write().await, batch).await;
+    }
     tracing::warn!("end of HotShot event stream, updater task will exit");
 }
 
-async fn update_state<N, P, D, Ver: StaticVersionType>(
+/// Apply `events` to the state and commit them in a single transaction.
+///
+/// If an event fails to apply, the transaction is reverted to undo partial state changes, and the
+/// batch is applied again without that event, so that only the blocks of the failed event are
+/// lost. We will continue streaming events, as we can update our state based on future events and
+/// then fill in the missing part of the state later, by fetching from a peer.
+async fn write_batch<N, P, D, Ver: StaticVersionType>(
     state: &mut StorageState<N, P, D, Ver>,
-    event: &Event<SeqTypes>,
-) -> anyhow::Result<()>
-where
+    mut events: Vec<Event<SeqTypes>>,
+) where
     N: network::Type,
     P: SequencerPersistence,
     D: SequencerDataSource + Send + Sync,
 {
-    state.update(event).await?;
-    state.commit().await?;
+    while !events.is_empty() {
+        let mut failed = None;
+        for (i, event) in events.iter().enumerate() {
+            if let Err(err) = state.update(event).await {
+                tracing::error!(?event, %err, "failed to update API state");
+                failed = Some(i);
+                break;
+            }
+        }
 
-    Ok(())
+        match failed {
+            Some(i) => {
+                state.revert().await;
+                events.remove(i);
+            }
+            None => {
+                if let Err(err) = state.commit().await {
+                    tracing::error!(%err, batch = events.len(), "failed to commit API state");
+                    state.revert().await;
+                }
+                return;
+            }
+        }
+    }
 }
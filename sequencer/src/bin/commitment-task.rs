@@ -1,11 +1,15 @@
 use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
-use async_std::task::spawn;
+use async_std::{sync::RwLock, task::spawn};
 use clap::Parser;
 use es_version::SEQUENCER_VERSION;
 use ethers::prelude::*;
 use futures::FutureExt;
-use sequencer::hotshot_commitment::{run_hotshot_commitment_task, CommitmentTaskOptions};
+use hotshot_query_service::metrics::PrometheusMetrics;
+use sequencer::hotshot_commitment::{
+    run_hotshot_commitment_task, CommitmentFinality, CommitmentTaskMetrics, CommitmentTaskOptions,
+};
 use sequencer::options::parse_duration;
+use std::borrow::Cow;
 use std::io;
 use std::time::Duration;
 use tide_disco::error::ServerError;
@@ -46,7 +50,7 @@ pub struct Options {
 
     /// If provided, the service will run a basic HTTP server on the given port.
     ///
-    /// The server provides healthcheck and version endpoints.
+    /// The server provides healthcheck, version, and metrics endpoints.
     #[clap(short, long, env = "ESPRESSO_COMMITMENT_TASK_PORT")]
     pub port: Option<u16>,
 
@@ -57,6 +61,19 @@ pub struct Options {
     /// If specified, sequencing attempts will be delayed by duration sampled from an exponential distribution with mean DELAY.
     #[clap(long, name = "DELAY", value_parser = parse_duration, env = "ESPRESSO_COMMITMENT_TASK_DELAY")]
     pub delay: Option<Duration>,
+
+    /// When a commitment submitted to L1 is considered final.
+    ///
+    /// One of `inclusion` (as soon as the transaction is mined), `finalized` (once the L1 block
+    /// including the transaction is finalized), or `confirmations:<N>` (once the L1 block including
+    /// the transaction is buried under N blocks). The next commitment is not submitted until the
+    /// previous one is final.
+    #[clap(
+        long,
+        env = "ESPRESSO_COMMITMENT_TASK_FINALITY",
+        default_value = "inclusion"
+    )]
+    pub finality: CommitmentFinality,
 }
 #[async_std::main]
 async fn main() {
@@ -64,9 +81,11 @@ async fn main() {
     setup_backtrace();
 
     let opt = Options::parse();
+    let metrics = PrometheusMetrics::default();
+    let task_metrics = CommitmentTaskMetrics::new(&metrics);
 
     if let Some(port) = opt.port {
-        start_http_server(port, opt.hotshot_address, SEQUENCER_VERSION).unwrap();
+        start_http_server(port, opt.hotshot_address, metrics, SEQUENCER_VERSION).unwrap();
     }
 
     let hotshot_contract_options = CommitmentTaskOptions {
@@ -78,26 +97,36 @@ async fn main() {
         sequencer_account_index: opt.hotshot_account_index,
         request_timeout: opt.request_timeout,
         query_service_url: Some(opt.sequencer_url),
+        finality: opt.finality,
     };
     tracing::info!("Launching HotShot commitment task..");
-    run_hotshot_commitment_task::<es_version::SequencerVersion>(&hotshot_contract_options).await;
+    run_hotshot_commitment_task::<es_version::SequencerVersion>(
+        &hotshot_contract_options,
+        task_metrics,
+    )
+    .await;
 }
 
 fn start_http_server<Ver: StaticVersionType + 'static>(
     port: u16,
     hotshot_address: Address,
+    metrics: PrometheusMetrics,
     bind_version: Ver,
 ) -> io::Result<()> {
-    let mut app = tide_disco::App::<(), ServerError>::with_state(());
+    let mut app = tide_disco::App::<_, ServerError>::with_state(RwLock::new(metrics));
     let toml = toml::from_str::<toml::value::Value>(include_str!("../../api/commitment_task.toml"))
         .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
 
-    let mut api = Api::<(), ServerError, Ver>::new(toml)
+    let mut api = Api::<RwLock<PrometheusMetrics>, ServerError, Ver>::new(toml)
         .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
 
     api.get("gethotshotcontract", move |_, _| {
         async move { Ok(hotshot_address) }.boxed()
     })
+    .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?
+    .metrics("metrics", |_, state| {
+        async move { Ok(Cow::Borrowed(state)) }.boxed()
+    })
     .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
 
     app.register_module("api", api)
@@ -116,6 +145,7 @@ mod test {
 
     use super::start_http_server;
     use super::Address;
+    use super::PrometheusMetrics;
     use super::ServerError;
 
     #[async_std::test]
@@ -127,8 +157,13 @@ mod test {
         let expected_addr = "0xED15E1FE0789c524398137a066ceb2EF9884E5D8"
             .parse::<Address>()
             .unwrap();
-        start_http_server(port, expected_addr, SEQUENCER_VERSION)
-            .expect("Failed to start the server");
+        start_http_server(
+            port,
+            expected_addr,
+            PrometheusMetrics::default(),
+            SEQUENCER_VERSION,
+        )
+        .expect("Failed to start the server");
 
         let client: Client<ServerError, SequencerVersion> =
             Client::new(format!("http://localhost:{port}").parse().unwrap());
@@ -45,6 +45,11 @@ struct Args {
     #[clap(long, env = "ESPRESSO_SEQUENCER_MAX_CONNECTIONS")]
     sequencer_api_max_connections: Option<usize>,
 
+    /// Maximum concurrent subscriptions to the streaming endpoints of the HTTP API server, per
+    /// client.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_MAX_SUBSCRIPTIONS")]
+    sequencer_api_max_subscriptions: Option<usize>,
+
     /// Port for connecting to the builder.
     #[clap(short, long, env = "ESPRESSO_BUILDER_PORT")]
     builder_port: Option<u16>,
@@ -62,6 +67,7 @@ async fn main() -> anyhow::Result<()> {
     let api_options = options::Options::from(options::Http {
         port: cli_params.sequencer_api_port,
         max_connections: cli_params.sequencer_api_max_connections,
+        max_subscriptions: cli_params.sequencer_api_max_subscriptions,
     })
     .status(Default::default())
     .state(Default::default())
@@ -0,0 +1,106 @@
+//! Utility program to recompute the validated state from decided blocks in storage.
+
+use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
+use async_std::sync::{Arc, RwLock};
+use clap::Parser;
+use es_version::SequencerVersion;
+use hotshot_query_service::node::NodeDataSource;
+use jf_merkle_tree::MerkleTreeScheme;
+use sequencer::{
+    api::{data_source::SequencerDataSource, sql},
+    catchup::StatePeers,
+    l1_client::L1Client,
+    persistence,
+    state::replay_state,
+    Genesis, NodeState, ValidatedState,
+};
+use std::path::PathBuf;
+use url::Url;
+
+/// Recompute the validated state by replaying decided blocks from query service storage.
+///
+/// Each replayed block is checked against the state commitments in its header. A mismatch
+/// indicates a bug in the state transition function or corrupted storage. Do not run this program
+/// against storage that is being reset.
+#[derive(Clone, Debug, Parser)]
+struct Options {
+    /// Replay blocks after block FROM.
+    ///
+    /// If 0, replay starts from the genesis state. Otherwise it starts from the state committed to
+    /// by block FROM, which must be available in the merklized state storage.
+    #[clap(long, name = "FROM", default_value = "0")]
+    from: u64,
+
+    /// Replay up to and including block TO.
+    ///
+    /// Defaults to the latest block in storage.
+    #[clap(long, name = "TO")]
+    to: Option<u64>,
+
+    /// Path to TOML file containing genesis state.
+    #[clap(
+        long,
+        name = "GENESIS_FILE",
+        env = "ESPRESSO_SEQUENCER_GENESIS_FILE",
+        default_value = "/genesis/demo.toml"
+    )]
+    genesis_file: PathBuf,
+
+    /// Url used for RPC communication with L1, to fetch fee deposits.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_L1_PROVIDER",
+        default_value = "http://localhost:8545"
+    )]
+    l1_provider_url: Url,
+
+    /// Maximum number of L1 blocks that can be scanned for events in a single query.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_L1_EVENTS_MAX_BLOCK_RANGE",
+        default_value = "10000"
+    )]
+    l1_events_max_block_range: u64,
+
+    #[clap(flatten)]
+    sql: persistence::sql::Options,
+}
+
+#[async_std::main]
+async fn main() -> anyhow::Result<()> {
+    setup_logging();
+    setup_backtrace();
+
+    let opt = Options::parse();
+    let genesis = Genesis::from_file(&opt.genesis_file)?;
+
+    let ds = sql::DataSource::create(opt.sql, Default::default(), false).await?;
+    let to = match opt.to {
+        Some(to) => to,
+        None => (NodeDataSource::block_height(&ds).await? as u64).saturating_sub(1),
+    };
+
+    let mut genesis_state = ValidatedState::default();
+    for (address, amount) in genesis.accounts {
+        genesis_state.prefund_account(address, amount);
+    }
+    let instance = NodeState::new(
+        0,
+        genesis.chain_config,
+        L1Client::new(opt.l1_provider_url, opt.l1_events_max_block_range),
+        // Replay fetches missing state from storage, never from peers.
+        StatePeers::<SequencerVersion>::from_urls(vec![]),
+    )
+    .with_genesis(genesis_state);
+
+    tracing::info!(from = opt.from, to, "replaying state");
+    let state = replay_state(Arc::new(RwLock::new(ds)), instance, opt.from, to).await?;
+    tracing::info!(
+        to,
+        block_merkle_tree_root = %state.block_merkle_tree.commitment(),
+        fee_merkle_tree_root = %state.fee_merkle_tree.commitment(),
+        "replayed state matches headers"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,54 @@
+//! Utility program to serve the query API from a snapshot of file system storage.
+
+use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
+use clap::Parser;
+use es_version::SEQUENCER_VERSION;
+use futures::future::{self, FutureExt};
+use sequencer::{
+    api::{self, options::Query},
+    network,
+    persistence::{self, no_storage::NoStorage},
+};
+use std::path::PathBuf;
+
+/// Serve the availability, node and status APIs from a snapshot of a node's storage.
+///
+/// A snapshot is a copy of the storage directory of a node using file system storage
+/// (`storage-fs`), taken while the node is stopped. The snapshot is served as is: consensus is not
+/// started, so no new data is added to it, and no missing data is fetched from peers. This makes it
+/// possible to inspect a captured chain deterministically, with no running network or database.
+///
+/// Merklized state is only stored by SQL storage, so the state APIs are not available from a
+/// snapshot.
+#[derive(Clone, Debug, Parser)]
+struct Options {
+    /// Path to the snapshot directory.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_SNAPSHOT_PATH")]
+    path: PathBuf,
+
+    /// Port that the HTTP API will use.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_API_PORT", default_value = "8080")]
+    port: u16,
+}
+
+#[async_std::main]
+async fn main() -> anyhow::Result<()> {
+    setup_logging();
+    setup_backtrace();
+
+    let opt = Options::parse();
+    tracing::info!(path = %opt.path.display(), port = opt.port, "serving snapshot");
+
+    let storage = persistence::fs::Options::new(opt.path);
+    api::Options::with_port(opt.port)
+        .query_fs(Query::default(), storage)
+        .status(Default::default())
+        // Consensus is never started, so the API serves only what is already in the snapshot.
+        .serve::<network::Memory, NoStorage, _, _>(
+            |_| future::pending().boxed(),
+            SEQUENCER_VERSION,
+        )
+        .await?;
+
+    Ok(())
+}
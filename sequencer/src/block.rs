@@ -2,7 +2,13 @@ mod full_payload;
 mod namespace_payload;
 mod uint_bytes;
 
-pub use full_payload::{NsProof, NsTable, Payload};
+pub use full_payload::{
+    NsProof, NsTable, NsTableDiff, NsTableLayout, Payload, PayloadByteLen, TruncatedTransactions,
+};
+pub use namespace_payload::{
+    Index, NsPayload, NsPayloadBuilder, NsPayloadCommitment, NsPayloadOwned, NsPayloadRange,
+    TxProof,
+};
 
 #[cfg(test)]
 mod test;
@@ -3,8 +3,7 @@ mod ns_table;
 mod payload;
 
 pub use ns_proof::NsProof;
-pub use ns_table::{NsIndex, NsTable};
-pub use payload::Payload;
+pub use ns_table::{NsIndex, NsTable, NsTableDiff, NsTableLayout};
+pub use payload::{Payload, PayloadByteLen, TruncatedTransactions};
 
 pub(in crate::block) use ns_table::NsIter;
-pub(in crate::block) use payload::PayloadByteLen;
@@ -1,7 +1,7 @@
 use crate::{
     block::{
         full_payload::{NsIndex, NsTable, Payload, PayloadByteLen},
-        namespace_payload::NsPayloadOwned,
+        namespace_payload::{NsPayloadCommitment, NsPayloadOwned},
     },
     NamespaceId, Transaction,
 };
@@ -161,4 +161,23 @@ impl NsProof {
     pub fn export_all_txs(&self, ns_id: &NamespaceId) -> Vec<Transaction> {
         self.ns_payload.export_all_txs(ns_id)
     }
+
+    /// Return the raw payload bytes of all transactions in the namespace
+    /// payload carried by `self`, in the same order as
+    /// [`Self::export_all_txs`].
+    ///
+    /// The same design warning as for [`Self::export_all_txs`] applies.
+    pub fn export_all_tx_bytes(&self) -> Vec<Vec<u8>> {
+        self.ns_payload.export_all_tx_bytes()
+    }
+
+    /// Content hash of the namespace payload carried by this proof.
+    ///
+    /// See
+    /// [`NsPayload::commitment`](crate::block::namespace_payload::NsPayload::commitment).
+    /// The hash is only meaningful for a proof which has passed
+    /// [`NsProof::verify`].
+    pub fn ns_payload_commitment(&self) -> NsPayloadCommitment {
+        self.ns_payload.commitment()
+    }
 }
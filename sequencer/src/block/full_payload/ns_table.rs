@@ -8,7 +8,7 @@
 use crate::{
     block::{
         full_payload::payload::PayloadByteLen,
-        namespace_payload::NsPayloadRange,
+        namespace_payload::{Index, NsPayloadRange, TxIndex},
         uint_bytes::{
             bytes_serde_impl, u32_from_bytes, u32_to_bytes, usize_from_bytes, usize_to_bytes,
         },
@@ -18,12 +18,15 @@ use crate::{
 use committable::{Commitment, Committable, RawCommitmentBuilder};
 use hotshot_types::traits::EncodeBytes;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use std::{collections::HashSet, sync::Arc};
+use std::{cmp::Ordering, collections::HashSet, ops::Range, sync::Arc};
 
 /// Byte lengths for the different items that could appear in a namespace table.
 const NUM_NSS_BYTE_LEN: usize = 4;
 const NS_OFFSET_BYTE_LEN: usize = 4;
 
+/// Bit of the number of entries which flags a table with layout [`NsTableLayout::Sorted`].
+const SORTED_FLAG: usize = 1 << (NUM_NSS_BYTE_LEN * 8 - 1);
+
 // TODO prefer [`NS_ID_BYTE_LEN`] set to `8` because [`NamespaceId`] is a `u64`
 // but we need to maintain serialization compatibility.
 // https://github.com/EspressoSystems/espresso-sequencer/issues/1574
@@ -57,6 +60,14 @@ const NS_ID_BYTE_LEN: usize = 4;
 ///
 /// See [`Self::in_bounds`] for clarification.
 ///
+/// ## Layout flag
+///
+/// The most significant bit of `n` ([`SORTED_FLAG`]) is set if the table has
+/// layout [`NsTableLayout::Sorted`]. The flag makes `n` larger than the number
+/// of entries that could fit in any table, so the number of entries is still
+/// the maximum number of whole entries that fit. Nodes which do not know about
+/// the flag therefore read a sorted table exactly like a flat one.
+///
 /// ## Namespace table entry
 ///
 /// ### Namespace ID
@@ -130,9 +141,52 @@ pub struct NsTable {
 
 impl NsTable {
     /// Search the namespace table for the ns_index belonging to `ns_id`.
+    ///
+    /// For a table with layout [`NsTableLayout::Sorted`] the namespace is found
+    /// by binary search, taking time logarithmic in the number of entries.
+    /// Otherwise the table is scanned. The result for a table which is flagged
+    /// as sorted but is not (see [`Self::is_consistent_with`]) is deterministic
+    /// but may not agree with [`Self::iter`].
     pub fn find_ns_id(&self, ns_id: &NamespaceId) -> Option<NsIndex> {
-        self.iter()
-            .find(|index| self.read_ns_id_unchecked(index) == *ns_id)
+        match self.layout() {
+            NsTableLayout::Flat => self
+                .iter()
+                .find(|index| self.read_ns_id_unchecked(index) == *ns_id),
+            NsTableLayout::Sorted => {
+                let (mut lo, mut hi) = (0, self.num_entries());
+                while lo < hi {
+                    let mid = lo + (hi - lo) / 2;
+                    match self.read_ns_id_unchecked(&NsIndex(mid)).cmp(ns_id) {
+                        Ordering::Less => lo = mid + 1,
+                        Ordering::Greater => hi = mid,
+                        Ordering::Equal => return Some(NsIndex(mid)),
+                    }
+                }
+                None
+            }
+        }
+    }
+
+    /// The layout this namespace table is flagged with.
+    pub fn layout(&self) -> NsTableLayout {
+        if self.read_num_nss() & SORTED_FLAG == 0 {
+            NsTableLayout::Flat
+        } else {
+            NsTableLayout::Sorted
+        }
+    }
+
+    /// Build the [`Index`] of the `tx_offset`th transaction in namespace `ns_id`.
+    ///
+    /// Returns `None` if `ns_id` is not in the namespace table. The namespace
+    /// table does not record how many transactions each namespace has, so
+    /// `tx_offset` is not checked here: [`Payload::transaction`] returns `None`
+    /// for an index past the last transaction in its namespace.
+    ///
+    /// [`Payload::transaction`]: crate::block::Payload::transaction
+    pub fn make_index(&self, ns_id: &NamespaceId, tx_offset: usize) -> Option<Index> {
+        let ns_index = self.find_ns_id(ns_id)?;
+        Some(Index::new(ns_index, TxIndex::from_usize(tx_offset)))
     }
 
     /// Iterator over all unique namespaces in the namespace table.
@@ -166,17 +220,35 @@ impl NsTable {
 
     /// Does the `index`th entry exist in the namespace table?
     pub fn in_bounds(&self, index: &NsIndex) -> bool {
-        // The number of entries in the namespace table, including all duplicate
-        // namespace IDs.
-        let num_nss_with_duplicates = std::cmp::min(
-            // Number of namespaces declared in the ns table
-            self.read_num_nss(),
-            // Max number of entries that could fit in the namespace table
-            self.bytes.len().saturating_sub(NUM_NSS_BYTE_LEN)
-                / NS_ID_BYTE_LEN.saturating_add(NS_OFFSET_BYTE_LEN),
-        );
+        index.0 < self.num_entries()
+    }
 
-        index.0 < num_nss_with_duplicates
+    /// Compare the namespace layout of this table against `other`.
+    ///
+    /// Namespace byte ranges are compared as declared in each table, without
+    /// clamping to a block payload byte length. See [`NsTableDiff`].
+    pub fn diff(&self, other: &NsTable) -> NsTableDiff {
+        let mut diff = NsTableDiff::default();
+        for index in self.iter() {
+            let ns_id = self.read_ns_id_unchecked(&index);
+            let range = self.declared_ns_range(&index);
+            match other.find_ns_id(&ns_id) {
+                Some(other_index) => {
+                    let other_range = other.declared_ns_range(&other_index);
+                    if range != other_range {
+                        diff.range_mismatches.push((ns_id, range, other_range));
+                    }
+                }
+                None => diff.only_in_self.push(ns_id),
+            }
+        }
+        for index in other.iter() {
+            let ns_id = other.read_ns_id_unchecked(&index);
+            if self.find_ns_id(&ns_id).is_none() {
+                diff.only_in_other.push(ns_id);
+            }
+        }
+        diff
     }
 
     // CRATE-VISIBLE HELPERS START HERE
@@ -198,11 +270,37 @@ impl NsTable {
         NsPayloadRange::new(start, end)
     }
 
+    /// Construct a namespace table directly from its raw bytes.
+    pub(in crate::block) fn from_bytes_vec(bytes: Vec<u8>) -> Self {
+        Self { bytes }
+    }
+
+    /// Are all namespace byte ranges declared in this table in-bounds and
+    /// well-ordered for a block payload of `payload_byte_len` bytes, and is a
+    /// table flagged as sorted actually sorted?
+    ///
+    /// Unlike [`Self::ns_range`], declared ranges are not clamped. Every
+    /// "honestly-prepared" namespace table satisfies this check.
+    pub(in crate::block) fn is_consistent_with(&self, payload_byte_len: &PayloadByteLen) -> bool {
+        let ranges_ok = self.iter().all(|index| {
+            let range = self.declared_ns_range(&index);
+            range.start <= range.end && range.end <= payload_byte_len.as_usize()
+        });
+        let layout_ok = match self.layout() {
+            NsTableLayout::Flat => true,
+            NsTableLayout::Sorted => (1..self.num_entries()).all(|i| {
+                self.read_ns_id_unchecked(&NsIndex(i - 1))
+                    < self.read_ns_id_unchecked(&NsIndex(i))
+            }),
+        };
+        ranges_ok && layout_ok
+    }
+
     // PRIVATE HELPERS START HERE
 
     /// Read the number of namespaces declared in the namespace table. This
     /// quantity might exceed the number of entries that could fit in the
-    /// namespace table.
+    /// namespace table, and includes the layout flag.
     ///
     /// For a correct count of the number of unique namespaces in this
     /// namespace table use `iter().count()`.
@@ -211,6 +309,30 @@ impl NsTable {
         usize_from_bytes::<NUM_NSS_BYTE_LEN>(&self.bytes[..num_nss_byte_len])
     }
 
+    /// The number of entries in the namespace table, including all duplicate
+    /// namespace IDs.
+    fn num_entries(&self) -> usize {
+        std::cmp::min(
+            // Number of namespaces declared in the ns table
+            self.read_num_nss(),
+            // Max number of entries that could fit in the namespace table
+            self.bytes.len().saturating_sub(NUM_NSS_BYTE_LEN)
+                / NS_ID_BYTE_LEN.saturating_add(NS_OFFSET_BYTE_LEN),
+        )
+    }
+
+    /// Like [`Self::ns_range`] except the range is not clamped to the byte
+    /// length of a block payload.
+    fn declared_ns_range(&self, index: &NsIndex) -> Range<usize> {
+        let end = self.read_ns_offset(index);
+        let start = if index.0 == 0 {
+            0
+        } else {
+            self.read_ns_offset(&NsIndex(index.0 - 1))
+        };
+        start..end
+    }
+
     /// Read the namespace offset from the `index`th entry from the namespace table.
     fn read_ns_offset(&self, index: &NsIndex) -> usize {
         let start =
@@ -237,9 +359,51 @@ impl Committable for NsTable {
     }
 }
 
+/// Return type for [`NsTable::diff`].
+///
+/// Namespaces are listed in the order in which they appear in their
+/// respective tables.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct NsTableDiff {
+    /// Namespaces present in `self` but not in `other`.
+    pub only_in_self: Vec<NamespaceId>,
+    /// Namespaces present in `other` but not in `self`.
+    pub only_in_other: Vec<NamespaceId>,
+    /// Namespaces present in both tables whose declared byte ranges differ,
+    /// as `(ns_id, range_in_self, range_in_other)`.
+    pub range_mismatches: Vec<(NamespaceId, Range<usize>, Range<usize>)>,
+}
+
+impl NsTableDiff {
+    /// Do the two tables have identical namespace layouts?
+    pub fn is_empty(&self) -> bool {
+        self.only_in_self.is_empty()
+            && self.only_in_other.is_empty()
+            && self.range_mismatches.is_empty()
+    }
+}
+
+/// Order of the entries in a namespace table.
+///
+/// Both layouts share the binary format described in [`NsTable`], with the
+/// layout recorded by a flag in the table header. Tables built with
+/// [`NsTableLayout::Sorted`] are readable by any node, while nodes which know
+/// about the flag look up namespaces in them in logarithmic time.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NsTableLayout {
+    /// Entries in any order, possibly with duplicate namespace IDs.
+    #[default]
+    Flat,
+    /// Each namespace appears exactly once, in increasing order of namespace
+    /// ID.
+    Sorted,
+}
+
 pub struct NsTableBuilder {
     bytes: Vec<u8>,
     num_entries: usize,
+    layout: NsTableLayout,
 }
 
 impl NsTableBuilder {
@@ -248,9 +412,19 @@ impl NsTableBuilder {
         Self {
             bytes: Vec::from([0; NUM_NSS_BYTE_LEN]),
             num_entries: 0,
+            layout: NsTableLayout::Flat,
         }
     }
 
+    /// Flag the namespace table with `layout`.
+    ///
+    /// For [`NsTableLayout::Sorted`], entries must be appended in increasing
+    /// order of namespace ID.
+    pub fn with_layout(mut self, layout: NsTableLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
     /// Add an entry to the namespace table.
     pub fn append_entry(&mut self, ns_id: NamespaceId, offset: usize) {
         // hack to serialize `NamespaceId` to `NS_ID_BYTE_LEN` bytes
@@ -264,9 +438,12 @@ impl NsTableBuilder {
     /// Serialize to bytes and consume self.
     pub fn into_ns_table(self) -> NsTable {
         let mut bytes = self.bytes;
-        // write the number of entries to the ns table header
-        bytes[..NUM_NSS_BYTE_LEN]
-            .copy_from_slice(&usize_to_bytes::<NUM_NSS_BYTE_LEN>(self.num_entries));
+        // write the number of entries and the layout flag to the ns table header
+        let num_nss = match self.layout {
+            NsTableLayout::Flat => self.num_entries,
+            NsTableLayout::Sorted => self.num_entries | SORTED_FLAG,
+        };
+        bytes[..NUM_NSS_BYTE_LEN].copy_from_slice(&usize_to_bytes::<NUM_NSS_BYTE_LEN>(num_nss));
         NsTable { bytes }
     }
 
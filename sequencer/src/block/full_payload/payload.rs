@@ -1,21 +1,59 @@
 use crate::{
     block::{
-        full_payload::ns_table::{NsIndex, NsTable, NsTableBuilder},
-        namespace_payload::{Index, Iter, NsPayload, NsPayloadBuilder, NsPayloadRange, TxProof},
+        full_payload::ns_table::{NsIndex, NsTable, NsTableBuilder, NsTableLayout},
+        namespace_payload::{
+            Index, Iter, NsPayload, NsPayloadBuilder, NsPayloadCommitment, NsPayloadRange, TxProof,
+        },
     },
-    NamespaceId, NodeState, SeqTypes, Transaction, ValidatedState,
+    ChainConfig, NamespaceId, NodeState, SeqTypes, Transaction, ValidatedState,
 };
+use anyhow::{ensure, Context};
 use async_trait::async_trait;
+use committable::{Commitment, Committable};
 use hotshot_query_service::availability::QueryablePayload;
 use hotshot_types::{
-    traits::{BlockPayload, EncodeBytes},
+    traits::{block_contents::vid_commitment, BlockPayload, EncodeBytes},
     utils::BuilderCommitment,
-    vid::{VidCommon, VidSchemeType},
+    vid::{VidCommitment, VidCommon, VidSchemeType},
 };
+use jf_merkle_tree::MerkleTreeScheme;
 use jf_vid::VidScheme;
 use serde::{Deserialize, Serialize};
 use sha2::Digest;
-use std::{collections::HashMap, fmt::Display, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    sync::Arc,
+    time::Instant,
+};
+
+/// Byte length of the namespace table length prefix in [`Payload::encode_full`].
+const FULL_PAYLOAD_LEN_PREFIX_BYTE_LEN: usize = 8;
+
+/// Maximum number of dropped transactions listed in a [`TruncatedTransactions`] event.
+const MAX_REPORTED_DROPPED_TXS: usize = 100;
+
+/// Transactions left out of a block because it reached the maximum block size.
+///
+/// Emitted by a node when it truncates a block it is building, so that the submitters of the
+/// dropped transactions can be notified or the transactions resubmitted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TruncatedTransactions {
+    /// Height of the block being built.
+    ///
+    /// This is derived from the state the block is built on, as one more than the number of
+    /// blocks committed to by its block Merkle tree.
+    pub height: u64,
+    /// Commitments of the first transactions dropped from the block, in the order they were
+    /// offered.
+    ///
+    /// Only the first few transactions are listed. The remaining transactions offered are not
+    /// consumed, so building a block does not wait on or buffer an arbitrarily long transaction
+    /// source.
+    pub dropped: Vec<Commitment<Transaction>>,
+    /// Whether more transactions were offered after those listed in `dropped`.
+    pub more_dropped: bool,
+}
 
 /// Raw payload data for an entire block.
 ///
@@ -61,6 +99,226 @@ impl Payload {
         ns_payload.export_tx(&ns_id, index.tx())
     }
 
+    /// The first transaction in canonical order (the order of
+    /// [`QueryablePayload::iter`]) satisfying `pred`, along with its index.
+    ///
+    /// Stops at the first match. Each namespace payload is read once, when
+    /// the search reaches it, and its transactions are decoded one at a time
+    /// as they are visited, so namespaces after the one containing the match
+    /// are never read.
+    pub fn find_transaction(
+        &self,
+        pred: impl FnMut(&Transaction) -> bool,
+    ) -> Option<(Index, Transaction)> {
+        self.find_transaction_with_reader(|range| self.read_ns_payload(range), pred)
+    }
+
+    /// Like [`Self::find_transaction`] except namespace payloads are read
+    /// with `read_ns_payload`, so that tests can observe which bytes of the
+    /// payload are read.
+    pub(in crate::block) fn find_transaction_with_reader<'a>(
+        &'a self,
+        mut read_ns_payload: impl FnMut(&NsPayloadRange) -> &'a NsPayload,
+        mut pred: impl FnMut(&Transaction) -> bool,
+    ) -> Option<(Index, Transaction)> {
+        self.ns_table.iter().find_map(|ns_index| {
+            let ns_id = self.ns_table.read_ns_id_unchecked(&ns_index);
+            let ns_range = self.ns_table.ns_range(&ns_index, &self.byte_len());
+            read_ns_payload(&ns_range)
+                .iter_txs(ns_id)
+                .find(|(_, tx)| pred(tx))
+                .map(|(tx_index, tx)| (Index::new(ns_index, tx_index), tx))
+        })
+    }
+
+    /// Byte range of the payload of the transaction at `index` within the
+    /// block payload.
+    ///
+    /// Returns `(start, end)` such that the transaction's payload bytes are
+    /// `encode()[start..end]`, or `None` if `index` is out of bounds.
+    pub fn transaction_bytes_range(&self, index: &Index) -> Option<(usize, usize)> {
+        // bounds check for the namespace
+        self.ns_table.read_ns_id(index.ns())?;
+        let ns_range = self.ns_table.ns_range(index.ns(), &self.byte_len());
+        let tx_range = self.read_ns_payload(&ns_range).tx_payload_range(index.tx())?;
+        let ns_start = ns_range.as_block_range().start;
+        Some((ns_start + tx_range.start, ns_start + tx_range.end))
+    }
+
+    /// Like [`QueryablePayload::transaction_with_proof`] except the
+    /// transaction is identified by its position `n` among all transactions in
+    /// the block, in canonical order (the order of [`QueryablePayload::iter`]),
+    /// and the proof is computed from `common`.
+    ///
+    /// Returns `None` if the block has `n` or fewer transactions.
+    pub fn transaction_with_proof_by_ordinal(
+        &self,
+        n: usize,
+        common: &VidCommon,
+    ) -> Option<(Transaction, TxProof)> {
+        let index = Iter::new(self).nth(n)?;
+        TxProof::new(&index, self, common)
+    }
+
+    /// Whether this payload, with namespace table `metadata`, has builder
+    /// commitment `expected`.
+    ///
+    /// This is a one-call integrity check for payloads received outside the
+    /// availability API, e.g. against the `builder_commitment` of a header.
+    pub fn verify_builder_commitment(
+        &self,
+        metadata: &NsTable,
+        expected: &BuilderCommitment,
+    ) -> bool {
+        BlockPayload::<SeqTypes>::builder_commitment(self, metadata) == *expected
+    }
+
+    /// Whether this payload matches the VID payload commitment `commit`.
+    ///
+    /// This is a bulk integrity check for a full block: it checks that
+    /// `common` is consistent with `commit`, that the payload bytes hash to
+    /// `commit` and that every namespace range declared in the namespace
+    /// table lies within the payload. A client which trusts `commit` can then
+    /// trust every transaction in the block without checking per-transaction
+    /// proofs.
+    pub fn verify_all(&self, common: &VidCommon, commit: &VidCommitment) -> bool {
+        if VidSchemeType::is_consistent(commit, common).is_err()
+            || self.byte_len().is_consistent(common).is_err()
+            || !self.ns_table.is_consistent_with(&self.byte_len())
+        {
+            return false;
+        }
+        let Ok(num_storage_nodes) = usize::try_from(VidSchemeType::get_num_storage_nodes(common))
+        else {
+            return false;
+        };
+        vid_commitment(&self.raw_payload, num_storage_nodes) == *commit
+    }
+
+    /// Byte length of a block containing exactly `transactions`, without
+    /// building it.
+    ///
+    /// This is the namespace table plus all namespace payloads, including
+    /// per-namespace and per-transaction overhead, measured the same way as
+    /// the size counted against `chain_config.max_block_size`. It assumes
+    /// every transaction is included: [`BlockPayload::from_transactions`]
+    /// may leave some out, for example if they are invalid, do not fit, or
+    /// arrive after the block building deadline. If all of `transactions` are
+    /// included, it equals the combined length of [`EncodeBytes::encode`] of
+    /// the built payload and of its namespace table.
+    pub fn compute_block_byte_len<'a>(
+        transactions: impl IntoIterator<Item = &'a Transaction>,
+    ) -> usize {
+        let mut block_byte_len = NsTableBuilder::fixed_overhead_byte_len();
+        let mut namespaces = HashSet::new();
+        for tx in transactions {
+            block_byte_len += Self::tx_byte_len(tx, namespaces.insert(tx.namespace()));
+        }
+        block_byte_len
+    }
+
+    /// Number of bytes still available in a block containing `transactions`
+    /// before reaching `chain_config.max_block_size`.
+    ///
+    /// Accounts for the namespace table and per-namespace overhead exactly as
+    /// [`BlockPayload::from_transactions`] does. Returns `0` if
+    /// `transactions` already exceed the maximum block size.
+    ///
+    /// Note that adding a transaction in a namespace not already present in
+    /// `transactions` consumes more than the transaction's own byte length.
+    pub fn remaining_capacity<'a>(
+        transactions: impl IntoIterator<Item = &'a Transaction>,
+        chain_config: &ChainConfig,
+    ) -> usize {
+        let max_block_byte_len =
+            usize::try_from(u64::from(chain_config.max_block_size)).unwrap_or(usize::MAX);
+        max_block_byte_len.saturating_sub(Self::compute_block_byte_len(transactions))
+    }
+
+    /// Each namespace in this block, in the order of the namespace table,
+    /// along with its payload and the byte range of that payload within the
+    /// block payload.
+    ///
+    /// This lets the data of each namespace be archived or forwarded on its
+    /// own, e.g. along with an [`NsProof`](crate::block::NsProof) for that
+    /// namespace.
+    pub fn split_namespaces(&self) -> Vec<(NamespaceId, &NsPayload, NsPayloadRange)> {
+        let byte_len = self.byte_len();
+        self.ns_table
+            .iter()
+            .map(|index| {
+                let ns_id = self.ns_table.read_ns_id_unchecked(&index);
+                let range = self.ns_table.ns_range(&index, &byte_len);
+                (ns_id, self.read_ns_payload(&range), range)
+            })
+            .collect()
+    }
+
+    /// Content hash of the payload of namespace `ns_id`, or `None` if `ns_id`
+    /// is not in this block.
+    ///
+    /// See [`NsPayload::commitment`]. Unlike
+    /// [`BlockPayload::builder_commitment`] it depends only on the contents of
+    /// a single namespace, so a rollup can commit to its own data without
+    /// reference to the rest of the block.
+    pub fn ns_payload_commitment(&self, ns_id: &NamespaceId) -> Option<NsPayloadCommitment> {
+        let index = self.ns_table.find_ns_id(ns_id)?;
+        Some(self.ns_payload(&index).commitment())
+    }
+
+    /// Serialize this payload, including its namespace table, into a single
+    /// self-contained byte blob.
+    ///
+    /// The format is the byte length of the namespace table as a little-endian
+    /// `u64`, followed by the namespace table bytes, followed by the
+    /// namespace payload bytes. Use [`Self::decode_full`] to recover the
+    /// payload.
+    pub fn encode_full(&self) -> Vec<u8> {
+        let ns_table_bytes = self.ns_table.encode();
+        let mut bytes = Vec::with_capacity(
+            FULL_PAYLOAD_LEN_PREFIX_BYTE_LEN + ns_table_bytes.len() + self.raw_payload.len(),
+        );
+        bytes.extend((ns_table_bytes.len() as u64).to_le_bytes());
+        bytes.extend(ns_table_bytes.iter());
+        bytes.extend(&self.raw_payload);
+        bytes
+    }
+
+    /// Inverse of [`Self::encode_full`].
+    ///
+    /// Fails if `bytes` is truncated or if the namespace table declares byte
+    /// ranges that are out of bounds for the namespace payload bytes.
+    pub fn decode_full(bytes: &[u8]) -> anyhow::Result<Self> {
+        ensure!(
+            bytes.len() >= FULL_PAYLOAD_LEN_PREFIX_BYTE_LEN,
+            "encoded payload of {} bytes is too short for a length prefix",
+            bytes.len()
+        );
+        let (prefix, rest) = bytes.split_at(FULL_PAYLOAD_LEN_PREFIX_BYTE_LEN);
+        let ns_table_byte_len = u64::from_le_bytes(prefix.try_into().unwrap());
+        let ns_table_byte_len = usize::try_from(ns_table_byte_len)
+            .ok()
+            .filter(|len| *len <= rest.len())
+            .with_context(|| {
+                format!(
+                    "namespace table byte length {ns_table_byte_len} exceeds remaining {} bytes",
+                    rest.len()
+                )
+            })?;
+        let (ns_table_bytes, raw_payload) = rest.split_at(ns_table_byte_len);
+
+        let payload = Self {
+            raw_payload: raw_payload.to_vec(),
+            ns_table: NsTable::from_bytes_vec(ns_table_bytes.to_vec()),
+        };
+        ensure!(
+            payload.ns_table.is_consistent_with(&payload.byte_len()),
+            "namespace table is inconsistent with payload of {} bytes",
+            payload.raw_payload.len()
+        );
+        Ok(payload)
+    }
+
     // CRATE-VISIBLE HELPERS START HERE
 
     pub(in crate::block) fn read_ns_payload(&self, range: &NsPayloadRange) -> &NsPayload {
@@ -79,12 +337,44 @@ impl Payload {
         PayloadByteLen(self.raw_payload.len())
     }
 
+    /// Assemble a block payload from already built namespaces.
+    ///
+    /// Each namespace may have been built in parts on separate threads and
+    /// combined with [`NsPayloadBuilder::merge`]. Namespaces appear in the
+    /// order given, or sorted by ID if `layout` is [`NsTableLayout::Sorted`].
+    /// Unlike [`BlockPayload::from_transactions`], this does not validate
+    /// transactions or enforce the maximum block size.
+    ///
+    /// Fails if a namespace ID appears more than once.
+    pub fn from_ns_payloads(
+        ns_payloads: impl IntoIterator<Item = (NamespaceId, NsPayloadBuilder)>,
+        layout: NsTableLayout,
+    ) -> anyhow::Result<Self> {
+        let mut ns_payloads: Vec<_> = ns_payloads.into_iter().collect();
+        let ns_ids: HashSet<_> = ns_payloads.iter().map(|(ns_id, _)| *ns_id).collect();
+        ensure!(ns_ids.len() == ns_payloads.len(), "duplicate namespace in block payload");
+        if layout == NsTableLayout::Sorted {
+            ns_payloads.sort_by_key(|(ns_id, _)| *ns_id);
+        }
+
+        let mut payload = Vec::new();
+        let mut ns_table_builder = NsTableBuilder::new().with_layout(layout);
+        for (ns_id, ns_builder) in ns_payloads {
+            payload.extend(ns_builder.into_bytes());
+            ns_table_builder.append_entry(ns_id, payload.len());
+        }
+        Ok(Self {
+            raw_payload: payload,
+            ns_table: ns_table_builder.into_ns_table(),
+        })
+    }
+
     // PRIVATE HELPERS START HERE
 
     /// Need a sync version of [`BlockPayload::from_transactions`] in order to impl [`BlockPayload::empty`].
     fn from_transactions_sync(
         transactions: impl IntoIterator<Item = <Self as BlockPayload<SeqTypes>>::Transaction> + Send,
-        _validated_state: &<Self as BlockPayload<SeqTypes>>::ValidatedState,
+        validated_state: &<Self as BlockPayload<SeqTypes>>::ValidatedState,
         instance_state: &<Self as BlockPayload<SeqTypes>>::Instance,
     ) -> Result<
         (Self, <Self as BlockPayload<SeqTypes>>::Metadata),
@@ -96,41 +386,88 @@ impl Payload {
             .map_err(|_| <Self as BlockPayload<SeqTypes>>::Error::BlockBuilding)?;
         let mut block_byte_len = NsTableBuilder::fixed_overhead_byte_len();
 
+        // accounting for block building deadline
+        let deadline = instance_state
+            .block_building_deadline
+            .map(|deadline| Instant::now() + deadline);
+
         // add each tx to its namespace
-        let mut ns_builders = HashMap::<NamespaceId, NsPayloadBuilder>::new();
-        for tx in transactions.into_iter() {
-            // accounting for block byte length limit
-            block_byte_len += tx.payload().len() + NsPayloadBuilder::tx_overhead_byte_len();
-            if !ns_builders.contains_key(&tx.namespace()) {
-                // each new namespace adds overhead
-                block_byte_len += NsTableBuilder::ns_overhead_byte_len()
-                    + NsPayloadBuilder::fixed_overhead_byte_len();
+        let mut ns_txs = HashMap::<NamespaceId, Vec<Transaction>>::new();
+        let mut transactions = transactions.into_iter();
+        while let Some(tx) = transactions.next() {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                // this tx and all remaining txs are left out of the block; don't consume the rest
+                // of the iterator, which may be what is taking so long
+                tracing::warn!(
+                    height = validated_state.block_merkle_tree.num_leaves() + 1,
+                    "block building deadline passed, building block from transactions so far"
+                );
+                break;
+            }
+
+            if let Err(err) = instance_state.validate_transaction(&tx) {
+                tracing::warn!("skipping transaction: {err:#}");
+                continue;
             }
+
+            // accounting for block byte length limit
+            block_byte_len += Self::tx_byte_len(&tx, !ns_txs.contains_key(&tx.namespace()));
             if block_byte_len > max_block_byte_len {
-                tracing::warn!("transactions truncated to fit in maximum block byte length {max_block_byte_len}");
+                // this tx and all remaining txs are left out of the block; report only the first
+                // few, without consuming the rest of the iterator
+                let mut dropped = std::iter::once(tx)
+                    .chain(transactions.take(MAX_REPORTED_DROPPED_TXS))
+                    .map(|tx| tx.commit())
+                    .collect::<Vec<_>>();
+                let more_dropped = dropped.len() > MAX_REPORTED_DROPPED_TXS;
+                dropped.truncate(MAX_REPORTED_DROPPED_TXS);
+                let event = TruncatedTransactions {
+                    height: validated_state.block_merkle_tree.num_leaves() + 1,
+                    dropped,
+                    more_dropped,
+                };
+                tracing::warn!(
+                    height = event.height,
+                    dropped = event.dropped.len(),
+                    more_dropped = event.more_dropped,
+                    "transactions truncated to fit in maximum block byte length {max_block_byte_len}"
+                );
+                if let Some(sender) = &instance_state.truncation_events {
+                    if sender.unbounded_send(event).is_err() {
+                        tracing::warn!("truncation event receiver dropped");
+                    }
+                }
                 break;
             }
 
-            let ns_builder = ns_builders.entry(tx.namespace()).or_default();
-            ns_builder.append_tx(tx);
+            ns_txs.entry(tx.namespace()).or_default().push(tx);
         }
 
         // build block payload and namespace table
-        let mut payload = Vec::new();
-        let mut ns_table_builder = NsTableBuilder::new();
-        for (ns_id, ns_builder) in ns_builders {
-            payload.extend(ns_builder.into_bytes());
-            ns_table_builder.append_entry(ns_id, payload.len());
+        let ns_payloads = ns_txs.into_iter().map(|(ns_id, mut txs)| {
+            instance_state.tx_ordering(&ns_id).sort(&mut txs);
+            let mut ns_builder = NsPayloadBuilder::default();
+            for tx in txs {
+                ns_builder.append_tx(tx);
+            }
+            (ns_id, ns_builder)
+        });
+        let payload = Self::from_ns_payloads(ns_payloads, instance_state.ns_table_layout)
+            .map_err(|_| <Self as BlockPayload<SeqTypes>>::Error::BlockBuilding)?;
+        let metadata = payload.ns_table.clone();
+        Ok((payload, metadata))
+    }
+
+    /// Number of bytes `tx` adds to a block, including overhead for a new
+    /// namespace if `new_ns` is true.
+    fn tx_byte_len(tx: &Transaction, new_ns: bool) -> usize {
+        let mut byte_len = tx.payload().len() + NsPayloadBuilder::tx_overhead_byte_len();
+        if new_ns {
+            // each new namespace adds overhead
+            byte_len += NsTableBuilder::ns_overhead_byte_len()
+                + NsPayloadBuilder::fixed_overhead_byte_len();
         }
-        let ns_table = ns_table_builder.into_ns_table();
-        let metadata = ns_table.clone();
-        Ok((
-            Self {
-                raw_payload: payload,
-                ns_table,
-            },
-            metadata,
-        ))
+        byte_len
     }
 }
 
@@ -248,7 +585,9 @@ impl EncodeBytes for Payload {
 
 /// Byte length of a block payload, which includes all namespaces but *not* the
 /// namespace table.
-pub(in crate::block) struct PayloadByteLen(usize);
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PayloadByteLen(usize);
 
 impl PayloadByteLen {
     /// Extract payload byte length from a [`VidCommon`] and construct a new [`Self`] from it.
@@ -266,7 +605,7 @@ impl PayloadByteLen {
         (self.0 == expected).then_some(()).ok_or(())
     }
 
-    pub(in crate::block::full_payload) fn as_usize(&self) -> usize {
+    pub fn as_usize(&self) -> usize {
         self.0
     }
 }
@@ -5,8 +5,9 @@ mod tx_proof;
 mod types;
 
 pub use iter::{Index, Iter};
+pub use ns_payload::{NsPayload, NsPayloadCommitment, NsPayloadOwned};
+pub use ns_payload_range::NsPayloadRange;
 pub use tx_proof::TxProof;
+pub use types::NsPayloadBuilder;
 
-pub(in crate::block) use ns_payload::{NsPayload, NsPayloadOwned};
-pub(in crate::block) use ns_payload_range::NsPayloadRange;
-pub(in crate::block) use types::NsPayloadBuilder;
+pub(in crate::block) use types::TxIndex;
@@ -12,6 +12,9 @@ pub struct Index {
 }
 
 impl Index {
+    pub(in crate::block) fn new(ns_index: NsIndex, tx_index: TxIndex) -> Self {
+        Self { ns_index, tx_index }
+    }
     pub fn ns(&self) -> &NsIndex {
         &self.ns_index
     }
@@ -38,6 +41,9 @@ impl Ord for Index {
 }
 
 /// Cartesian product of [`NsIter`], [`TxIter`].
+///
+/// Lazy: the transaction table of each namespace is read only when iteration
+/// reaches that namespace, so stopping early never touches later namespaces.
 pub struct Iter<'a> {
     ns_iter: Peekable<NsIter<'a>>,
     tx_iter: Option<TxIter>,
@@ -6,6 +6,8 @@ use crate::{
     NamespaceId, Transaction,
 };
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::ops::Range;
 
 /// Raw binary data for a single namespace's payload.
 ///
@@ -13,7 +15,7 @@ use serde::{Deserialize, Serialize};
 ///
 /// See module-level documentation [`types`](super::types) for a full
 /// specification of the binary format of a namespace.
-pub(in crate::block) struct NsPayload([u8]);
+pub struct NsPayload([u8]);
 
 impl NsPayload {
     pub fn from_bytes_slice(bytes: &[u8]) -> &NsPayload {
@@ -22,16 +24,26 @@ impl NsPayload {
     pub fn as_bytes_slice(&self) -> &[u8] {
         &self.0
     }
-    pub fn byte_len(&self) -> NsPayloadByteLen {
+    pub(in crate::block) fn byte_len(&self) -> NsPayloadByteLen {
         NsPayloadByteLen::from_usize(self.0.len())
     }
 
+    /// SHA-256 hash of the bytes of this namespace payload.
+    ///
+    /// This is a plain content hash, which can be computed without any VID
+    /// data. It is NOT a VID availability proof: it says nothing about
+    /// whether these bytes are part of a block committed to by consensus. Use
+    /// [`NsProof`](crate::block::NsProof) for that.
+    pub fn commitment(&self) -> NsPayloadCommitment {
+        NsPayloadCommitment(Sha256::digest(&self.0).into())
+    }
+
     /// Read and parse bytes from the ns payload.
     ///
     /// Arg `range: &R` is convertible into a `Range<usize>` via
     /// [`NsPayloadBytesRange`]. The payload bytes are parsed into a `R::Output`
     /// via [`FromNsPayloadBytes`].
-    pub fn read<'a, R>(&'a self, range: &R) -> R::Output
+    pub(in crate::block) fn read<'a, R>(&'a self, range: &R) -> R::Output
     where
         R: NsPayloadBytesRange<'a>,
     {
@@ -39,7 +51,7 @@ impl NsPayload {
     }
 
     /// Iterator over all transactions in this namespace.
-    pub fn iter(&self) -> TxIter {
+    pub(in crate::block) fn iter(&self) -> TxIter {
         self.iter_from_num_txs(&self.read_num_txs())
     }
 
@@ -52,11 +64,45 @@ impl NsPayload {
             .collect()
     }
 
+    /// Iterator over all transactions in this namespace, along with their
+    /// indices. The namespace ID for each returned [`Transaction`] is set to
+    /// `ns_id`.
+    ///
+    /// The transaction table header is read once, and each transaction is
+    /// decoded only when the iterator reaches it.
+    pub(in crate::block) fn iter_txs(
+        &self,
+        ns_id: NamespaceId,
+    ) -> impl Iterator<Item = (TxIndex, Transaction)> + '_ {
+        let num_txs = self.read_num_txs();
+        self.iter_from_num_txs(&num_txs).map(move |i| {
+            let tx = self.tx_from_num_txs(&ns_id, &i, &num_txs);
+            (i, tx)
+        })
+    }
+
+    /// Return the payload bytes of all transactions in this namespace, exactly
+    /// as they are stored in the namespace payload.
+    pub fn export_all_tx_bytes(&self) -> Vec<Vec<u8>> {
+        let num_txs = self.read_num_txs();
+        self.iter_from_num_txs(&num_txs)
+            .map(|i| {
+                let tx_table_entries = self.read(&TxTableEntriesRange::new(&i));
+                let tx_range = TxPayloadRange::new(&num_txs, &tx_table_entries, &self.byte_len());
+                self.0[tx_range.ns_payload_range()].to_vec()
+            })
+            .collect()
+    }
+
     /// Return a transaction from this namespace. Set its namespace ID to
     /// `ns_id`.
     ///
     /// Return `None` if `index` is out of bounds.
-    pub fn export_tx(&self, ns_id: &NamespaceId, index: &TxIndex) -> Option<Transaction> {
+    pub(in crate::block) fn export_tx(
+        &self,
+        ns_id: &NamespaceId,
+        index: &TxIndex,
+    ) -> Option<Transaction> {
         let num_txs_unchecked = self.read_num_txs();
         let num_txs = NumTxs::new(&num_txs_unchecked, &self.byte_len());
         if !num_txs.in_bounds(index) {
@@ -65,6 +111,21 @@ impl NsPayload {
         Some(self.tx_from_num_txs(ns_id, index, &num_txs_unchecked))
     }
 
+    /// Return the byte range of a transaction's payload, relative to the
+    /// start of this namespace.
+    ///
+    /// Return `None` if `index` is out of bounds.
+    pub(in crate::block) fn tx_payload_range(&self, index: &TxIndex) -> Option<Range<usize>> {
+        let num_txs_unchecked = self.read_num_txs();
+        let num_txs = NumTxs::new(&num_txs_unchecked, &self.byte_len());
+        if !num_txs.in_bounds(index) {
+            return None; // error: tx index out of bounds
+        }
+        let tx_table_entries = self.read(&TxTableEntriesRange::new(index));
+        let tx_range = TxPayloadRange::new(&num_txs_unchecked, &tx_table_entries, &self.byte_len());
+        Some(tx_range.ns_payload_range())
+    }
+
     /// Private helper. (Could be pub if desired.)
     fn read_num_txs(&self) -> NumTxsUnchecked {
         self.read(&NumTxsRange::new(&self.byte_len()))
@@ -93,10 +154,20 @@ impl NsPayload {
     }
 }
 
+/// Content hash of a namespace payload. See [`NsPayload::commitment`].
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct NsPayloadCommitment([u8; 32]);
+
+impl AsRef<[u8]> for NsPayloadCommitment {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
 #[repr(transparent)]
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 #[serde(transparent)]
-pub(in crate::block) struct NsPayloadOwned(#[serde(with = "base64_bytes")] Vec<u8>);
+pub struct NsPayloadOwned(#[serde(with = "base64_bytes")] Vec<u8>);
 
 /// Crazy boilerplate code to make it so that [`NsPayloadOwned`] is to
 /// [`NsPayload`] as [`Vec<T>`] is to `[T]`. See [How can I create newtypes for
@@ -3,11 +3,10 @@ use std::ops::Range;
 
 /// Index range for a namespace payload inside a block payload.
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
-pub(in crate::block) struct NsPayloadRange(Range<usize>);
+pub struct NsPayloadRange(Range<usize>);
 
 impl NsPayloadRange {
-    /// TODO restrict visibility?
-    pub fn new(start: usize, end: usize) -> Self {
+    pub(in crate::block) fn new(start: usize, end: usize) -> Self {
         Self(start..end)
     }
 
@@ -18,13 +17,13 @@ impl NsPayloadRange {
     }
 
     /// Return the byte length of this namespace.
-    pub fn byte_len(&self) -> NsPayloadByteLen {
+    pub(in crate::block) fn byte_len(&self) -> NsPayloadByteLen {
         NsPayloadByteLen::from_usize(self.0.len())
     }
 
     /// Convert a [`NsPayloadBytesRange`] into a range that's relative to the
     /// entire block payload.
-    pub fn block_range<'a, R>(&self, range: &R) -> Range<usize>
+    pub(in crate::block) fn block_range<'a, R>(&self, range: &R) -> Range<usize>
     where
         R: NsPayloadBytesRange<'a>,
     {
@@ -361,6 +361,9 @@ pub(in crate::block) struct TxIndex(usize);
 bytes_serde_impl!(TxIndex, to_bytes, [u8; NUM_TXS_BYTE_LEN], from_bytes);
 
 impl TxIndex {
+    pub fn from_usize(index: usize) -> Self {
+        Self(index)
+    }
     pub fn to_bytes(&self) -> [u8; NUM_TXS_BYTE_LEN] {
         usize_to_bytes::<NUM_TXS_BYTE_LEN>(self.0)
     }
@@ -391,8 +394,12 @@ impl Iterator for TxIter {
 /// Use [`Self::append_tx`] to add each transaction. Use [`Self::into_bytes`]
 /// when you're done. The returned bytes include a well-formed tx table and all
 /// tx payloads.
+///
+/// A namespace can be built in parts with separate builders, which are then
+/// combined with [`Self::merge`] and assembled into a block with
+/// [`Payload::from_ns_payloads`](crate::block::Payload::from_ns_payloads).
 #[derive(Default)]
-pub(in crate::block) struct NsPayloadBuilder {
+pub struct NsPayloadBuilder {
     tx_table_entries: Vec<u8>,
     tx_bodies: Vec<u8>,
 }
@@ -405,6 +412,24 @@ impl NsPayloadBuilder {
             .extend(usize_to_bytes::<TX_OFFSET_BYTE_LEN>(self.tx_bodies.len()));
     }
 
+    /// Append all transactions from `other` after the transactions in this
+    /// namespace.
+    ///
+    /// The result is identical to appending the transactions of `self`
+    /// followed by the transactions of `other` to a single builder, so a
+    /// namespace can be assembled in parts on separate threads.
+    pub fn merge(mut self, other: NsPayloadBuilder) -> Self {
+        // Offsets in `other` are relative to the start of its own tx bodies.
+        let base = self.tx_bodies.len();
+        for entry in other.tx_table_entries.chunks(TX_OFFSET_BYTE_LEN) {
+            let offset = usize_from_bytes::<TX_OFFSET_BYTE_LEN>(entry) + base;
+            self.tx_table_entries
+                .extend(usize_to_bytes::<TX_OFFSET_BYTE_LEN>(offset));
+        }
+        self.tx_bodies.extend(other.tx_bodies);
+        self
+    }
+
     /// Serialize to bytes and consume self.
     pub fn into_bytes(self) -> Vec<u8> {
         let mut result = Vec::with_capacity(
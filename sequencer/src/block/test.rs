@@ -1,18 +1,29 @@
 use crate::{
     block::{
-        full_payload::{NsProof, Payload},
-        namespace_payload::TxProof,
+        full_payload::{NsProof, NsTable, NsTableLayout, Payload},
+        namespace_payload::{NsPayloadBuilder, TxProof},
     },
     chain_config::BlockSize,
-    ChainConfig, NamespaceId, NodeState, Transaction,
+    state::{FeeAccount, FeeAmount},
+    testing, ChainConfig, NamespaceId, NodeState, SignedTxVerifier, Transaction,
+    TruncatedTransactions, TxOrdering,
 };
 use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
+use committable::Committable;
+use futures::{channel::mpsc, StreamExt};
 use hotshot::traits::BlockPayload;
 use hotshot_query_service::availability::QueryablePayload;
-use hotshot_types::{traits::EncodeBytes, vid::vid_scheme};
+use hotshot_types::{
+    traits::{signature_key::BuilderSignatureKey, EncodeBytes},
+    vid::vid_scheme,
+};
 use jf_vid::VidScheme;
-use rand::RngCore;
-use std::collections::HashMap;
+use rand::{rngs::StdRng, seq::SliceRandom, RngCore, SeedableRng};
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
 
 #[async_std::test]
 async fn basic_correctness() {
@@ -105,6 +116,34 @@ async fn basic_correctness() {
     }
 }
 
+#[async_std::test]
+async fn compute_block_byte_len() {
+    setup_logging();
+    setup_backtrace();
+    let test_cases = vec![
+        vec![],                                              // empty block
+        vec![vec![5, 8, 8], vec![7, 9, 11], vec![10, 5, 8]], // 3 non-empty namespaces
+        vec![vec![0, 0], vec![1000], vec![1, 2, 3, 4]],      // empty and large txs
+    ];
+    let mut rng = jf_utils::test_rng();
+
+    for test in ValidTest::many_from_tx_lengths(test_cases, &mut rng) {
+        // Shuffle, so transactions from the same namespace are not all adjacent.
+        let mut txs = test.all_txs();
+        txs.shuffle(&mut rng);
+
+        let block =
+            Payload::from_transactions(txs.clone(), &Default::default(), &Default::default())
+                .await
+                .unwrap()
+                .0;
+        assert_eq!(
+            Payload::compute_block_byte_len(&txs),
+            block.encode().len() + block.ns_table().encode().len()
+        );
+    }
+}
+
 #[async_std::test]
 async fn enforce_max_block_size() {
     setup_logging();
@@ -132,6 +171,16 @@ async fn enforce_max_block_size() {
     assert_eq!(block.encode().len(), payload_byte_len_expected);
     assert_eq!(block.ns_table().encode().len(), ns_table_byte_len_expected);
     assert_eq!(block.len(block.ns_table()), tx_count_expected);
+    assert_eq!(Payload::remaining_capacity(&test.all_txs(), &instance_state.chain_config), 0);
+
+    // test: remaining capacity accounts for all overhead
+    let chain_config = ChainConfig {
+        max_block_size: BlockSize::from(
+            (payload_byte_len_expected + ns_table_byte_len_expected + 10) as u64,
+        ),
+        ..Default::default()
+    };
+    assert_eq!(Payload::remaining_capacity(&test.all_txs(), &chain_config), 10);
 
     // test: actual block size exceeds max block size, so 1 tx is dropped
     // WARN log should be emitted
@@ -150,6 +199,825 @@ async fn enforce_max_block_size() {
     assert_eq!(block.len(block.ns_table()), tx_count_expected - 1);
 }
 
+#[async_std::test]
+async fn enforce_max_transaction_size() {
+    setup_logging();
+    setup_backtrace();
+
+    let ns = NamespaceId::from(1u32);
+    let small = Transaction::new(ns, vec![0; 10]);
+    let large = Transaction::new(ns, vec![1; 100]);
+
+    // the large transaction fits in a block but exceeds the per-transaction limit
+    let instance_state = NodeState::default()
+        .with_chain_config(ChainConfig {
+            max_block_size: BlockSize::from(1000),
+            ..Default::default()
+        })
+        .with_max_transaction_size(BlockSize::from(50));
+    instance_state.validate_transaction(&small).unwrap();
+    instance_state.validate_transaction(&large).unwrap_err();
+
+    // WARN log should be emitted
+    let block = Payload::from_transactions(
+        vec![small.clone(), large.clone()],
+        &Default::default(),
+        &instance_state,
+    )
+    .await
+    .unwrap()
+    .0;
+    let txs = block.transactions(block.ns_table()).collect::<Vec<_>>();
+    assert_eq!(txs, vec![small.clone()]);
+
+    // without a per-transaction limit both are included
+    let instance_state = NodeState::default().with_chain_config(ChainConfig {
+        max_block_size: BlockSize::from(1000),
+        ..Default::default()
+    });
+    instance_state.validate_transaction(&large).unwrap();
+    let block = Payload::from_transactions(vec![small, large], &Default::default(), &instance_state)
+        .await
+        .unwrap()
+        .0;
+    assert_eq!(block.len(block.ns_table()), 2);
+}
+
+#[async_std::test]
+async fn enforce_block_building_deadline() {
+    setup_logging();
+    setup_backtrace();
+    let mut rng = jf_utils::test_rng();
+    let test = ValidTest::from_tx_lengths(vec![vec![4; 10], vec![8; 10]], &mut rng);
+    let all_txs = test.all_txs();
+
+    // with a deadline of zero, the deadline has passed by the time the first transaction is
+    // yielded
+    let instance_state = NodeState::default().with_block_building_deadline(Duration::ZERO);
+    let yielded = AtomicUsize::new(0);
+    let counted_txs = all_txs.clone().into_iter().inspect(|_| {
+        yielded.fetch_add(1, Ordering::SeqCst);
+    });
+    let block = Payload::from_transactions(counted_txs, &Default::default(), &instance_state)
+        .await
+        .unwrap()
+        .0;
+
+    // building stops at the first transaction, without consuming the rest of the source, and the
+    // block is valid but empty
+    assert_eq!(yielded.load(Ordering::SeqCst), 1);
+    assert_eq!(block.transactions(block.ns_table()).count(), 0);
+    let decoded = Payload::decode_full(&block.encode_full()).unwrap();
+    assert_eq!(decoded, block);
+
+    // with a deadline that is not reached, all transactions are included
+    let instance_state =
+        NodeState::default().with_block_building_deadline(Duration::from_secs(3600));
+    let block = Payload::from_transactions(all_txs.clone(), &Default::default(), &instance_state)
+        .await
+        .unwrap()
+        .0;
+    let included: Vec<_> = block.transactions(block.ns_table()).collect();
+    assert_eq!(included.len(), all_txs.len());
+    for tx in &all_txs {
+        assert!(included.contains(tx));
+    }
+}
+
+#[test]
+fn enforce_min_fee() {
+    let min_fee = FeeAmount::from(100);
+    let instance_state = NodeState::default().with_min_fee(min_fee);
+    instance_state
+        .validate_fee(Some(FeeAmount::from(99)))
+        .unwrap_err();
+    instance_state.validate_fee(Some(min_fee)).unwrap();
+    instance_state.validate_fee(None).unwrap_err();
+
+    // without a minimum fee all are accepted
+    NodeState::default().validate_fee(None).unwrap();
+}
+
+#[async_std::test]
+async fn min_fee_not_enforced_when_building_blocks() {
+    setup_logging();
+    setup_backtrace();
+
+    // Transactions reach block building through consensus, in their binary encoding, which does not
+    // include the fee offered at submission. The minimum fee must not cause them to be dropped.
+    let ns = NamespaceId::from(1u32);
+    let txs = (0..5u8)
+        .map(|i| {
+            let tx = Transaction::new(ns, vec![i; 10]);
+            bincode::deserialize::<Transaction>(&bincode::serialize(&tx).unwrap()).unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    let instance_state = NodeState::default().with_min_fee(FeeAmount::from(100));
+    let block = Payload::from_transactions(txs.clone(), &Default::default(), &instance_state)
+        .await
+        .unwrap()
+        .0;
+    assert_eq!(block.transactions(block.ns_table()).collect::<Vec<_>>(), txs);
+}
+
+#[async_std::test]
+async fn verify_transactions() {
+    setup_logging();
+    setup_backtrace();
+
+    let ns = NamespaceId::from(1u32);
+    let (signer, key) = FeeAccount::generated_from_seed_indexed([0; 32], 0);
+    let (_, other_key) = FeeAccount::generated_from_seed_indexed([0; 32], 1);
+    let signed_tx = |key, msg: &[u8]| {
+        let sig = FeeAccount::sign_builder_message(key, msg).unwrap();
+        Transaction::new(ns, [sig.to_vec(), msg.to_vec()].concat())
+    };
+    let valid = signed_tx(&key, b"hello");
+    let invalid = signed_tx(&other_key, b"hello");
+    let malformed = Transaction::new(ns, b"hello".to_vec());
+
+    // without a verifier, all transactions are accepted
+    let instance_state = NodeState::default();
+    instance_state.validate_transaction(&valid).unwrap();
+    instance_state.validate_transaction(&invalid).unwrap();
+    instance_state.validate_transaction(&malformed).unwrap();
+
+    let instance_state = NodeState::default().with_tx_verifier(SignedTxVerifier::new([signer]));
+    instance_state.validate_transaction(&valid).unwrap();
+    instance_state.validate_transaction(&invalid).unwrap_err();
+    instance_state.validate_transaction(&malformed).unwrap_err();
+
+    // rejected transactions are left out of blocks
+    let block = Payload::from_transactions(
+        vec![valid.clone(), invalid, malformed],
+        &Default::default(),
+        &instance_state,
+    )
+    .await
+    .unwrap()
+    .0;
+    let txs = block.transactions(block.ns_table()).collect::<Vec<_>>();
+    assert_eq!(txs, vec![valid]);
+}
+
+#[async_std::test]
+async fn report_truncated_transactions() {
+    setup_logging();
+    setup_backtrace();
+
+    // each tx adds 44 bytes on top of 16 bytes of namespace table and namespace overhead, so only
+    // the first two txs fit in a block
+    let ns = NamespaceId::from(1u32);
+    let txs = (0..4u8)
+        .map(|i| Transaction::new(ns, vec![i; 40]))
+        .collect::<Vec<_>>();
+    let (sender, mut receiver) = mpsc::unbounded();
+    let instance_state = NodeState::default()
+        .with_chain_config(ChainConfig {
+            max_block_size: BlockSize::from(110),
+            ..Default::default()
+        })
+        .with_truncation_events(sender);
+
+    let block = Payload::from_transactions(txs.clone(), &Default::default(), &instance_state)
+        .await
+        .unwrap()
+        .0;
+    let included = block.transactions(block.ns_table()).collect::<Vec<_>>();
+    assert_eq!(included, txs[..2]);
+
+    // the dropped txs are reported, along with the height of the block being built
+    let event = receiver.next().await.unwrap();
+    assert_eq!(
+        event,
+        TruncatedTransactions {
+            height: 1,
+            dropped: txs[2..].iter().map(|tx| tx.commit()).collect(),
+            more_dropped: false,
+        }
+    );
+
+    // when many txs are dropped, only the first few are listed
+    let many_txs = (0..1000u16)
+        .map(|i| Transaction::new(ns, i.to_le_bytes().repeat(20)))
+        .collect::<Vec<_>>();
+    let block = Payload::from_transactions(many_txs.clone(), &Default::default(), &instance_state)
+        .await
+        .unwrap()
+        .0;
+    assert_eq!(block.transactions(block.ns_table()).count(), 2);
+    let event = receiver.next().await.unwrap();
+    assert!(event.more_dropped);
+    assert!(!event.dropped.is_empty() && event.dropped.len() < many_txs.len() - 2);
+    assert_eq!(
+        event.dropped,
+        many_txs[2..2 + event.dropped.len()]
+            .iter()
+            .map(|tx| tx.commit())
+            .collect::<Vec<_>>()
+    );
+
+    // nothing is reported when all txs fit
+    Payload::from_transactions(txs[..2].to_vec(), &Default::default(), &instance_state)
+        .await
+        .unwrap();
+    drop(instance_state);
+    assert_eq!(receiver.next().await, None);
+}
+
+#[async_std::test]
+async fn tx_ordering_within_namespace() {
+    setup_logging();
+    setup_backtrace();
+
+    let ns = NamespaceId::from(1u32);
+    let other_ns = NamespaceId::from(2u32);
+    let tx = |ns, priority: u64, tag: u8| {
+        let mut payload = priority.to_be_bytes().to_vec();
+        payload.push(tag);
+        Transaction::new(ns, payload)
+    };
+    let txs = vec![
+        tx(ns, 1, 0),
+        tx(other_ns, 1, 0),
+        tx(ns, 3, 0),
+        tx(other_ns, 3, 0),
+        tx(ns, 2, 0),
+        tx(ns, 3, 1),
+    ];
+    let instance_state = NodeState::default().with_tx_ordering(ns, TxOrdering::PriorityPrefix);
+    let block = Payload::from_transactions(txs.clone(), &Default::default(), &instance_state)
+        .await
+        .unwrap()
+        .0;
+    let block_txs = block.transactions(block.ns_table()).collect::<Vec<_>>();
+
+    // Transactions in `ns` are sorted by decreasing priority, with ties broken by arrival order.
+    let ns_txs = block_txs
+        .iter()
+        .filter(|tx| tx.namespace() == ns)
+        .cloned()
+        .collect::<Vec<_>>();
+    assert_eq!(ns_txs, vec![tx(ns, 3, 0), tx(ns, 3, 1), tx(ns, 2, 0), tx(ns, 1, 0)]);
+
+    // Transactions in other namespaces keep their arrival order.
+    let other_txs = block_txs
+        .iter()
+        .filter(|tx| tx.namespace() == other_ns)
+        .cloned()
+        .collect::<Vec<_>>();
+    assert_eq!(other_txs, vec![tx(other_ns, 1, 0), tx(other_ns, 3, 0)]);
+}
+
+#[async_std::test]
+async fn ns_table_diff() {
+    setup_logging();
+    setup_backtrace();
+
+    let ns1 = NamespaceId::from(1u32);
+    let ns2 = NamespaceId::from(2u32);
+    let block = |txs: Vec<Transaction>| async move {
+        Payload::from_transactions(txs, &Default::default(), &Default::default())
+            .await
+            .unwrap()
+            .0
+    };
+    let a = block(vec![Transaction::new(ns1, vec![0; 5])]).await;
+    let b = block(vec![Transaction::new(ns1, vec![0; 6])]).await;
+    let c = block(vec![Transaction::new(ns2, vec![0; 5])]).await;
+
+    // identical tables
+    assert!(a.ns_table().diff(a.ns_table()).is_empty());
+
+    // same namespace, different byte range
+    let diff = a.ns_table().diff(b.ns_table());
+    assert!(diff.only_in_self.is_empty());
+    assert!(diff.only_in_other.is_empty());
+    assert_eq!(diff.range_mismatches.len(), 1);
+    let (ns_id, range_a, range_b) = &diff.range_mismatches[0];
+    assert_eq!(*ns_id, ns1);
+    assert_eq!(range_a.len() + 1, range_b.len());
+
+    // disjoint namespaces
+    let diff = a.ns_table().diff(c.ns_table());
+    assert_eq!(diff.only_in_self, vec![ns1]);
+    assert_eq!(diff.only_in_other, vec![ns2]);
+    assert!(diff.range_mismatches.is_empty());
+}
+
+#[async_std::test]
+async fn encode_decode_full() {
+    setup_logging();
+    setup_backtrace();
+    let mut rng = jf_utils::test_rng();
+    let test = ValidTest::from_tx_lengths(vec![vec![5, 8], vec![7], vec![]], &mut rng);
+
+    let block =
+        Payload::from_transactions(test.all_txs(), &Default::default(), &Default::default())
+            .await
+            .unwrap()
+            .0;
+    let bytes = block.encode_full();
+    assert_eq!(Payload::decode_full(&bytes).unwrap(), block);
+
+    // truncated length prefix
+    assert!(Payload::decode_full(&bytes[..4]).is_err());
+
+    // namespace table length exceeds input
+    let mut bad = bytes.clone();
+    bad[..8].copy_from_slice(&u64::MAX.to_le_bytes());
+    assert!(Payload::decode_full(&bad).is_err());
+
+    // truncated namespace payloads are inconsistent with the namespace table
+    assert!(Payload::decode_full(&bytes[..bytes.len() - 1]).is_err());
+}
+
+#[async_std::test]
+async fn transaction_bytes_range() {
+    setup_logging();
+    setup_backtrace();
+    let mut rng = jf_utils::test_rng();
+    let test = ValidTest::from_tx_lengths(vec![vec![5, 0, 8], vec![7], vec![3]], &mut rng);
+
+    let block =
+        Payload::from_transactions(test.all_txs(), &Default::default(), &Default::default())
+            .await
+            .unwrap()
+            .0;
+    let bytes = block.encode();
+    let mut num_txs = 0;
+    for index in block.iter(block.ns_table()) {
+        let tx = block.transaction(&index).unwrap();
+        let (start, end) = block.transaction_bytes_range(&index).unwrap();
+        assert_eq!(&bytes[start..end], tx.payload());
+        num_txs += 1;
+    }
+    assert_eq!(num_txs, test.all_txs().len());
+}
+
+#[async_std::test]
+async fn transaction_with_proof_by_ordinal() {
+    setup_logging();
+    setup_backtrace();
+    let mut rng = jf_utils::test_rng();
+    let test = ValidTest::from_tx_lengths(vec![vec![5, 0, 8], vec![7], vec![3]], &mut rng);
+
+    let block =
+        Payload::from_transactions(test.all_txs(), &Default::default(), &Default::default())
+            .await
+            .unwrap()
+            .0;
+    let disperse_data = vid_scheme(10).disperse(block.encode()).unwrap();
+    let (vid_commit, vid_common) = (disperse_data.commit, disperse_data.common);
+
+    // ordinals follow the canonical iteration order across namespaces
+    let mut num_txs = 0;
+    for (n, index) in block.iter(block.ns_table()).enumerate() {
+        let (tx, proof) = block
+            .transaction_with_proof_by_ordinal(n, &vid_common)
+            .unwrap();
+        assert_eq!(tx, block.transaction(&index).unwrap());
+        assert!(proof
+            .verify(block.ns_table(), &tx, &vid_commit, &vid_common)
+            .unwrap());
+        num_txs += 1;
+    }
+    assert_eq!(num_txs, test.all_txs().len());
+    assert!(block
+        .transaction_with_proof_by_ordinal(num_txs, &vid_common)
+        .is_none());
+}
+
+#[async_std::test]
+async fn verify_all() {
+    setup_logging();
+    setup_backtrace();
+    let mut rng = jf_utils::test_rng();
+    let test = ValidTest::from_tx_lengths(vec![vec![5, 0, 8], vec![7], vec![]], &mut rng);
+    let other = ValidTest::from_tx_lengths(vec![vec![6, 9]], &mut rng);
+
+    let block =
+        Payload::from_transactions(test.all_txs(), &Default::default(), &Default::default())
+            .await
+            .unwrap()
+            .0;
+    let other_block =
+        Payload::from_transactions(other.all_txs(), &Default::default(), &Default::default())
+            .await
+            .unwrap()
+            .0;
+    let disperse_data = vid_scheme(10).disperse(block.encode()).unwrap();
+    let (vid_commit, vid_common) = (disperse_data.commit, disperse_data.common);
+    let other_disperse_data = vid_scheme(10).disperse(other_block.encode()).unwrap();
+
+    assert!(block.verify_all(&vid_common, &vid_commit));
+
+    // wrong commitment or VID common data
+    assert!(!other_block.verify_all(&vid_common, &vid_commit));
+    assert!(!block.verify_all(&other_disperse_data.common, &vid_commit));
+    assert!(!block.verify_all(&vid_common, &other_disperse_data.commit));
+
+    // the right payload bytes with a namespace table declaring an out-of-bounds
+    // namespace range
+    let mut ns_table_bytes = Vec::new();
+    ns_table_bytes.extend(1u32.to_le_bytes());
+    ns_table_bytes.extend(1u32.to_le_bytes());
+    ns_table_bytes.extend(u32::MAX.to_le_bytes());
+    let bad_block = Payload::from_bytes(&block.encode(), &NsTable::from_bytes_vec(ns_table_bytes));
+    assert!(!bad_block.verify_all(&vid_common, &vid_commit));
+
+    // the right payload bytes with a namespace table flagged as sorted whose
+    // namespace IDs are not sorted
+    let unsorted_ns_table = |num_nss: u32| {
+        let mut ns_table_bytes = Vec::new();
+        ns_table_bytes.extend(num_nss.to_le_bytes());
+        ns_table_bytes.extend(2u32.to_le_bytes());
+        ns_table_bytes.extend(0u32.to_le_bytes());
+        ns_table_bytes.extend(1u32.to_le_bytes());
+        ns_table_bytes.extend(0u32.to_le_bytes());
+        NsTable::from_bytes_vec(ns_table_bytes)
+    };
+    let flat_block = Payload::from_bytes(&block.encode(), &unsorted_ns_table(2));
+    assert!(flat_block.verify_all(&vid_common, &vid_commit));
+    let bad_block = Payload::from_bytes(&block.encode(), &unsorted_ns_table(2 | 1 << 31));
+    assert_eq!(bad_block.ns_table().layout(), NsTableLayout::Sorted);
+    assert_eq!(bad_block.ns_table().iter().count(), 2);
+    assert!(!bad_block.verify_all(&vid_common, &vid_commit));
+}
+
+#[async_std::test]
+async fn split_namespaces() {
+    setup_logging();
+    setup_backtrace();
+    let mut rng = jf_utils::test_rng();
+    let test = ValidTest::from_tx_lengths(vec![vec![5, 0, 8], vec![7], vec![], vec![3]], &mut rng);
+
+    let block =
+        Payload::from_transactions(test.all_txs(), &Default::default(), &Default::default())
+            .await
+            .unwrap()
+            .0;
+    let vid_common = vid_scheme(10).disperse(block.encode()).unwrap().common;
+
+    let nss = block.split_namespaces();
+    assert_eq!(nss.len(), test.nss.len());
+
+    // the namespace payloads tile the block payload, in namespace table order
+    let mut end = 0;
+    for ((ns_id, ns_payload, range), index) in nss.iter().zip(block.ns_table().iter()) {
+        assert_eq!(*ns_id, block.ns_table().read_ns_id(&index).unwrap());
+        let range = range.as_block_range();
+        assert_eq!(range.start, end);
+        assert_eq!(ns_payload.as_bytes_slice(), &block.encode()[range.clone()]);
+        end = range.end;
+
+        // each namespace payload holds exactly the transactions of its namespace
+        assert_eq!(ns_payload.export_all_txs(ns_id), test.nss[ns_id]);
+        let tx_bytes: Vec<_> = test.nss[ns_id]
+            .iter()
+            .map(|tx| tx.payload().to_vec())
+            .collect();
+        assert_eq!(ns_payload.export_all_tx_bytes(), tx_bytes);
+
+        // and matches the namespace proof for the same namespace
+        let proof = NsProof::new(&block, &index, &vid_common).unwrap();
+        assert_eq!(proof.ns_payload_commitment(), ns_payload.commitment());
+        assert_eq!(proof.export_all_tx_bytes(), tx_bytes);
+    }
+    assert_eq!(end, block.encode().len());
+}
+
+#[async_std::test]
+async fn find_transaction() {
+    setup_logging();
+    setup_backtrace();
+    let mut rng = jf_utils::test_rng();
+    let test = ValidTest::from_tx_lengths(
+        vec![vec![5, 0, 8], vec![7, 4], vec![3], vec![9, 2, 6], vec![1]],
+        &mut rng,
+    );
+
+    let block =
+        Payload::from_transactions(test.all_txs(), &Default::default(), &Default::default())
+            .await
+            .unwrap()
+            .0;
+    let all: Vec<_> = block.enumerate(block.ns_table()).collect();
+
+    for (n, (index, tx)) in all.iter().enumerate() {
+        // the search visits exactly the transactions up to and including the match
+        let mut visited = 0;
+        let found = block.find_transaction(|candidate| {
+            visited += 1;
+            candidate == tx
+        });
+        assert_eq!(found, Some((index.clone(), tx.clone())));
+        assert_eq!(visited, n + 1);
+    }
+
+    let mut visited = 0;
+    assert_eq!(
+        block.find_transaction(|_| {
+            visited += 1;
+            false
+        }),
+        None
+    );
+    assert_eq!(visited, all.len());
+}
+
+#[async_std::test]
+async fn find_transaction_reads_only_needed_namespaces() {
+    setup_logging();
+    setup_backtrace();
+    let mut rng = jf_utils::test_rng();
+    let test = ValidTest::from_tx_lengths(vec![vec![100, 200, 300]; 20], &mut rng);
+
+    let block =
+        Payload::from_transactions(test.all_txs(), &Default::default(), &Default::default())
+            .await
+            .unwrap()
+            .0;
+    let ns_table = block.ns_table();
+    let ns_ranges: Vec<_> = ns_table
+        .iter()
+        .map(|ns_index| {
+            ns_table
+                .ns_range(&ns_index, &block.byte_len())
+                .as_block_range()
+        })
+        .collect();
+    assert_eq!(ns_ranges.len(), 20);
+
+    for (index, tx) in block.enumerate(ns_table) {
+        // the search reads each namespace payload up to and including the one containing the
+        // match exactly once, and no other payload bytes
+        let mut read = vec![];
+        let found = block.find_transaction_with_reader(
+            |range| {
+                read.push(range.as_block_range());
+                block.read_ns_payload(range)
+            },
+            |candidate| *candidate == tx,
+        );
+        assert_eq!(found, Some((index.clone(), tx)));
+        let ns_position = ns_table.iter().position(|ns| ns == *index.ns()).unwrap();
+        assert_eq!(read, ns_ranges[..=ns_position]);
+    }
+}
+
+#[async_std::test]
+async fn make_index() {
+    setup_logging();
+    setup_backtrace();
+    let mut rng = jf_utils::test_rng();
+    let test = ValidTest::from_tx_lengths(vec![vec![5, 0, 8], vec![7], vec![3, 4]], &mut rng);
+
+    let block =
+        Payload::from_transactions(test.all_txs(), &Default::default(), &Default::default())
+            .await
+            .unwrap()
+            .0;
+    let ns_table = block.ns_table();
+    let all: Vec<_> = block.iter(ns_table).collect();
+
+    // each transaction is addressed by its namespace and its offset within the namespace
+    for (ns_id, txs) in test.nss.iter() {
+        for (offset, tx) in txs.iter().enumerate() {
+            let index = ns_table.make_index(ns_id, offset).unwrap();
+            assert!(all.contains(&index));
+            assert_eq!(block.transaction(&index).as_ref(), Some(tx));
+        }
+
+        // an offset past the end of the namespace addresses no transaction
+        let index = ns_table.make_index(ns_id, txs.len()).unwrap();
+        assert_eq!(block.transaction(&index), None);
+    }
+
+    // a namespace not in the block has no index
+    let ns_id = NamespaceId::random(&mut rng);
+    assert!(!test.nss.contains_key(&ns_id));
+    assert_eq!(ns_table.make_index(&ns_id, 0), None);
+}
+
+#[async_std::test]
+async fn verify_builder_commitment() {
+    setup_logging();
+    setup_backtrace();
+    let mut rng = jf_utils::test_rng();
+    let test = ValidTest::from_tx_lengths(vec![vec![5, 0, 8], vec![7], vec![3]], &mut rng);
+    let (block, ns_table) =
+        Payload::from_transactions(test.all_txs(), &Default::default(), &Default::default())
+            .await
+            .unwrap();
+    let commitment = block.builder_commitment(&ns_table);
+    assert!(block.verify_builder_commitment(&ns_table, &commitment));
+
+    // a different payload does not match
+    let (other, other_ns_table) = Payload::from_transactions(
+        test.all_txs().into_iter().skip(1),
+        &Default::default(),
+        &Default::default(),
+    )
+    .await
+    .unwrap();
+    assert!(!other.verify_builder_commitment(&other_ns_table, &commitment));
+    assert!(other.verify_builder_commitment(
+        &other_ns_table,
+        &other.builder_commitment(&other_ns_table)
+    ));
+}
+
+#[test]
+fn ns_payload_builder_merge() {
+    setup_logging();
+    setup_backtrace();
+    let mut rng = jf_utils::test_rng();
+    let ns_id = NamespaceId::from(1u32);
+    let txs: Vec<_> = [3, 0, 10, 1, 7]
+        .into_iter()
+        .map(|len| Transaction::new(ns_id, random_bytes(len, &mut rng)))
+        .collect();
+
+    let mut sequential = NsPayloadBuilder::default();
+    for tx in txs.clone() {
+        sequential.append_tx(tx);
+    }
+    let expect = sequential.into_bytes();
+
+    // Every split point, including empty builders on either side.
+    for split in 0..=txs.len() {
+        let mut first = NsPayloadBuilder::default();
+        for tx in txs[..split].iter().cloned() {
+            first.append_tx(tx);
+        }
+        let mut second = NsPayloadBuilder::default();
+        for tx in txs[split..].iter().cloned() {
+            second.append_tx(tx);
+        }
+        assert_eq!(first.merge(second).into_bytes(), expect, "split {split}");
+    }
+}
+
+#[async_std::test]
+async fn payload_from_ns_payloads() {
+    setup_logging();
+    setup_backtrace();
+    let mut rng = jf_utils::test_rng();
+    let txs: Vec<_> = [(2u32, 5), (1, 3), (2, 0), (1, 8), (2, 4)]
+        .into_iter()
+        .map(|(ns_id, len)| Transaction::new(ns_id.into(), random_bytes(len, &mut rng)))
+        .collect();
+    let instance_state = NodeState::mock().with_ns_table_layout(NsTableLayout::Sorted);
+    let (expect, _) = Payload::from_transactions(txs.clone(), &Default::default(), &instance_state)
+        .await
+        .unwrap();
+
+    // Build each namespace from two halves, as if on separate threads.
+    let ns_payloads = [1u32, 2].map(|ns_id| {
+        let ns_id = NamespaceId::from(ns_id);
+        let ns_txs: Vec<_> = txs
+            .iter()
+            .filter(|tx| tx.namespace() == ns_id)
+            .cloned()
+            .collect();
+        let (left, right) = ns_txs.split_at(ns_txs.len() / 2);
+        let mut first = NsPayloadBuilder::default();
+        for tx in left.iter().cloned() {
+            first.append_tx(tx);
+        }
+        let mut second = NsPayloadBuilder::default();
+        for tx in right.iter().cloned() {
+            second.append_tx(tx);
+        }
+        (ns_id, first.merge(second))
+    });
+
+    // Namespaces are sorted regardless of the order they are given in.
+    let [ns1, ns2] = ns_payloads;
+    let payload = Payload::from_ns_payloads([ns2, ns1], NsTableLayout::Sorted).unwrap();
+    assert_eq!(payload, expect);
+
+    let duplicate = [NamespaceId::from(1u32), NamespaceId::from(1u32)]
+        .map(|ns_id| (ns_id, NsPayloadBuilder::default()));
+    Payload::from_ns_payloads(duplicate, NsTableLayout::Flat).unwrap_err();
+}
+
+#[async_std::test]
+async fn ns_payload_commitment() {
+    setup_logging();
+    setup_backtrace();
+
+    let ns1 = NamespaceId::from(1u32);
+    let ns2 = NamespaceId::from(2u32);
+    let block = |txs: Vec<Transaction>| async move {
+        Payload::from_transactions(txs, &Default::default(), &Default::default())
+            .await
+            .unwrap()
+            .0
+    };
+    let a = block(vec![Transaction::new(ns1, vec![1; 5])]).await;
+    let b = block(vec![
+        Transaction::new(ns2, vec![1; 5]),
+        Transaction::new(ns1, vec![2; 7]),
+    ])
+    .await;
+
+    // the hash covers only the namespace payload bytes, not the namespace ID
+    // or the rest of the block
+    let commit = a.ns_payload_commitment(&ns1).unwrap();
+    assert_eq!(Some(commit), b.ns_payload_commitment(&ns2));
+    assert_ne!(Some(commit), b.ns_payload_commitment(&ns1));
+    assert_eq!(a.ns_payload_commitment(&ns2), None);
+    let c = block(vec![
+        Transaction::new(ns1, vec![1; 5]),
+        Transaction::new(ns2, vec![3; 7]),
+    ])
+    .await;
+    assert_eq!(Some(commit), c.ns_payload_commitment(&ns1));
+
+    // a namespace proof carries the same commitment, with no VID needed to compute it
+    let vid_common = vid_scheme(10).disperse(b.encode()).unwrap().common;
+    let ns_index = b.ns_table().find_ns_id(&ns1).unwrap();
+    let proof = NsProof::new(&b, &ns_index, &vid_common).unwrap();
+    assert_eq!(Some(proof.ns_payload_commitment()), b.ns_payload_commitment(&ns1));
+}
+
+#[async_std::test]
+async fn random_transactions() {
+    setup_logging();
+    setup_backtrace();
+    let namespaces = [NamespaceId::from(1), NamespaceId::from(2), NamespaceId::from(3)];
+
+    // the same seed yields the same transactions
+    let txs = testing::random_transactions(10, &namespaces, &mut StdRng::seed_from_u64(0));
+    assert_eq!(
+        txs,
+        testing::random_transactions(10, &namespaces, &mut StdRng::seed_from_u64(0))
+    );
+    assert_ne!(
+        txs,
+        testing::random_transactions(10, &namespaces, &mut StdRng::seed_from_u64(1))
+    );
+
+    // every namespace is used, and every transaction fits in a block
+    let block = Payload::from_transactions(txs.clone(), &Default::default(), &Default::default())
+        .await
+        .unwrap()
+        .0;
+    assert_eq!(block.ns_table().iter().count(), namespaces.len());
+    for ns_id in &namespaces {
+        assert!(block.ns_table().find_ns_id(ns_id).is_some());
+    }
+    assert_eq!(block.len(block.ns_table()), txs.len());
+}
+
+#[async_std::test]
+async fn sorted_ns_table_layout() {
+    setup_logging();
+    setup_backtrace();
+    let mut rng = jf_utils::test_rng();
+    let test = ValidTest::from_tx_lengths(vec![vec![4]; 500], &mut rng);
+    let all_txs = test.all_txs();
+
+    let flat = Payload::from_transactions(all_txs.clone(), &Default::default(), &Default::default())
+        .await
+        .unwrap()
+        .0;
+    let instance_state = NodeState::default().with_ns_table_layout(NsTableLayout::Sorted);
+    let sorted = Payload::from_transactions(all_txs, &Default::default(), &instance_state)
+        .await
+        .unwrap()
+        .0;
+    assert_eq!(flat.ns_table().layout(), NsTableLayout::Flat);
+    assert_eq!(sorted.ns_table().layout(), NsTableLayout::Sorted);
+    assert_eq!(sorted.ns_table().iter().count(), test.nss.len());
+
+    // the layout flag survives serialization
+    let bytes = bincode::serialize(sorted.ns_table()).unwrap();
+    let deserialized: NsTable = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(deserialized.layout(), NsTableLayout::Sorted);
+
+    // binary search agrees with a scan of the table
+    let ns_table = sorted.ns_table();
+    for (ns_id, txs) in &test.nss {
+        let index = ns_table.find_ns_id(ns_id).unwrap();
+        assert_eq!(
+            ns_table
+                .iter()
+                .find(|i| ns_table.read_ns_id(i) == Some(*ns_id)),
+            Some(index.clone())
+        );
+        assert_eq!(sorted.ns_payload(&index).export_all_txs(ns_id), *txs);
+    }
+    let missing = (0u32..)
+        .map(NamespaceId::from)
+        .find(|ns_id| !test.nss.contains_key(ns_id))
+        .unwrap();
+    assert_eq!(ns_table.find_ns_id(&missing), None);
+}
+
 // TODO lots of infra here that could be reused in other tests.
 struct ValidTest {
     nss: HashMap<NamespaceId, Vec<Transaction>>,
@@ -1,10 +1,17 @@
 use crate::{
-    api::{data_source::CatchupDataSource, AccountQueryData, BlocksFrontier},
+    api::{
+        catchup_path, data_source::CatchupDataSource, AccountQueryData, BlocksFrontier,
+        CATCHUP_NO_FORWARD_HEADER,
+    },
     persistence::PersistenceOptions,
     state::{BlockMerkleTree, FeeAccount, FeeMerkleCommitment},
 };
 use anyhow::{bail, Context};
-use async_std::{sync::RwLock, task::sleep};
+use async_std::{
+    channel::{self, Receiver, Sender},
+    sync::RwLock,
+    task::sleep,
+};
 use async_trait::async_trait;
 use derive_more::From;
 use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime as _};
@@ -17,14 +24,14 @@ use tide_disco::error::ServerError;
 use url::Url;
 use vbs::version::StaticVersionType;
 
-const MIN_RETRY_DELAY: Duration = Duration::from_millis(500);
+pub(crate) const MIN_RETRY_DELAY: Duration = Duration::from_millis(500);
 const MAX_RETRY_DELAY: Duration = Duration::from_secs(5);
 const BACKOFF_FACTOR: u32 = 2;
 // Exponential backoff jitter as a fraction of the backoff delay, (numerator, denominator).
 const BACKOFF_JITTER: (u64, u64) = (1, 10);
 
 #[must_use]
-fn backoff(delay: Duration) -> Duration {
+pub(crate) fn backoff(delay: Duration) -> Duration {
     if delay >= MAX_RETRY_DELAY {
         return MAX_RETRY_DELAY;
     }
@@ -65,6 +72,15 @@ impl<Ver: StaticVersionType> Client<ServerError, Ver> {
     pub fn get<T: DeserializeOwned>(&self, route: &str) -> Request<T, ServerError, Ver> {
         self.inner.get(route)
     }
+
+    /// Request catchup data which the peer must serve from its own state.
+    ///
+    /// The peer does not forward the request to its own peers, so that catchup requests cannot
+    /// loop between nodes which catch up from each other.
+    pub fn get_catchup<T: DeserializeOwned>(&self, route: &str) -> Request<T, ServerError, Ver> {
+        self.get(&catchup_path(route))
+            .header(CATCHUP_NO_FORWARD_HEADER, "true")
+    }
 }
 
 #[async_trait]
@@ -141,6 +157,13 @@ pub trait StateCatchup: Send + Sync + std::fmt::Debug {
 
         Ok(())
     }
+
+    /// Try to fetch the current block height of the chain, failing without retrying if unable.
+    ///
+    /// Providers which cannot tell the block height of the chain fail by default.
+    async fn try_fetch_block_height(&self) -> anyhow::Result<u64> {
+        bail!("provider does not support fetching the block height");
+    }
 }
 
 /// A catchup implementation that falls back to a remote provider, but prefers a local provider when
@@ -158,9 +181,50 @@ pub(crate) async fn local_and_remote(
     }
 }
 
+/// Limits the number of requests which are in flight at once.
+///
+/// Clones share the same set of permits.
+#[derive(Debug, Clone)]
+struct RequestLimiter {
+    permits: Receiver<()>,
+    returns: Sender<()>,
+}
+
+impl RequestLimiter {
+    fn new(n: usize) -> Self {
+        let n = n.max(1);
+        let (returns, permits) = channel::bounded(n);
+        for _ in 0..n {
+            returns.try_send(()).unwrap();
+        }
+        Self { permits, returns }
+    }
+
+    /// Wait until a permit is available, and hold it until the returned guard is dropped.
+    async fn acquire(&self) -> RequestPermit<'_> {
+        // Both ends of the channel are owned by `self`, so it can never be closed.
+        self.permits.recv().await.unwrap();
+        RequestPermit {
+            returns: &self.returns,
+        }
+    }
+}
+
+struct RequestPermit<'a> {
+    returns: &'a Sender<()>,
+}
+
+impl Drop for RequestPermit<'_> {
+    fn drop(&mut self) {
+        // There is always room to return a permit, since we took one out.
+        self.returns.try_send(()).ok();
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct StatePeers<Ver: StaticVersionType> {
     clients: Vec<Client<ServerError, Ver>>,
+    limiter: Option<RequestLimiter>,
 }
 
 impl<Ver: StaticVersionType> StatePeers<Ver> {
@@ -171,6 +235,24 @@ impl<Ver: StaticVersionType> StatePeers<Ver> {
 
         Self {
             clients: urls.into_iter().map(Client::new).collect(),
+            limiter: None,
+        }
+    }
+
+    /// Send at most `n` requests to peers at a time, or an unlimited number if `n` is `None`.
+    ///
+    /// The limit applies to all requests made through this object and its clones, so that a node
+    /// which is catching up does not overwhelm its peers.
+    pub fn with_max_in_flight_requests(mut self, n: Option<usize>) -> Self {
+        self.limiter = n.map(RequestLimiter::new);
+        self
+    }
+
+    /// Wait until another request may be sent to a peer.
+    async fn request_permit(&self) -> Option<RequestPermit<'_>> {
+        match &self.limiter {
+            Some(limiter) => Some(limiter.acquire().await),
+            None => None,
         }
     }
 }
@@ -186,16 +268,17 @@ impl<Ver: StaticVersionType> StateCatchup for StatePeers<Ver> {
         account: FeeAccount,
     ) -> anyhow::Result<AccountQueryData> {
         for client in self.clients.iter() {
+            let _permit = self.request_permit().await;
             tracing::info!("Fetching account {account:?} from {}", client.url);
             match client
-                .get::<AccountQueryData>(&format!(
-                    "catchup/{height}/{}/account/{account}",
+                .get_catchup::<AccountQueryData>(&format!(
+                    "{height}/{}/account/{account}",
                     view.u64(),
                 ))
                 .send()
                 .await
             {
-                Ok(res) => match res.proof.verify(&fee_merkle_tree_root) {
+                Ok(res) => match res.verify(&fee_merkle_tree_root) {
                     Ok(_) => return Ok(res),
                     Err(err) => tracing::warn!("Error verifying account proof: {}", err),
                 },
@@ -214,10 +297,16 @@ impl<Ver: StaticVersionType> StateCatchup for StatePeers<Ver> {
         view: ViewNumber,
         mt: &mut BlockMerkleTree,
     ) -> anyhow::Result<()> {
+        // The tree is empty at genesis, so there is no frontier to remember.
+        if mt.num_leaves() == 0 {
+            return Ok(());
+        }
+
         for client in self.clients.iter() {
+            let _permit = self.request_permit().await;
             tracing::info!("Fetching frontier from {}", client.url);
             match client
-                .get::<BlocksFrontier>(&format!("catchup/{height}/{}/blocks", view.u64()))
+                .get_catchup::<BlocksFrontier>(&format!("{height}/{}/blocks", view.u64()))
                 .send()
                 .await
             {
@@ -241,6 +330,22 @@ impl<Ver: StaticVersionType> StateCatchup for StatePeers<Ver> {
         }
         bail!("Could not fetch frontier from any peer");
     }
+
+    /// The highest block height reported by any peer.
+    #[tracing::instrument(skip(self))]
+    async fn try_fetch_block_height(&self) -> anyhow::Result<u64> {
+        let mut tip = None;
+        for client in self.clients.iter() {
+            let _permit = self.request_permit().await;
+            match client.get::<u64>("status/block-height").send().await {
+                Ok(height) => tip = tip.max(Some(height)),
+                Err(err) => {
+                    tracing::warn!("Error fetching block height from peer: {}", err);
+                }
+            }
+        }
+        tip.context("Could not fetch block height from any peer")
+    }
 }
 
 #[derive(Debug, From)]
@@ -338,6 +443,10 @@ impl<T: StateCatchup + ?Sized> StateCatchup for Box<T> {
     ) -> anyhow::Result<()> {
         (**self).remember_blocks_merkle_tree(height, view, mt).await
     }
+
+    async fn try_fetch_block_height(&self) -> anyhow::Result<u64> {
+        (**self).try_fetch_block_height().await
+    }
 }
 
 #[async_trait]
@@ -385,6 +494,10 @@ impl<T: StateCatchup + ?Sized> StateCatchup for Arc<T> {
     ) -> anyhow::Result<()> {
         (**self).remember_blocks_merkle_tree(height, view, mt).await
     }
+
+    async fn try_fetch_block_height(&self) -> anyhow::Result<u64> {
+        (**self).try_fetch_block_height().await
+    }
 }
 
 /// Catchup from multiple providers tries each provider in a round robin fashion until it succeeds.
@@ -434,6 +547,20 @@ impl<T: StateCatchup> StateCatchup for Vec<T> {
 
         bail!("could not fetch account from any provider");
     }
+
+    /// The highest block height reported by any provider.
+    async fn try_fetch_block_height(&self) -> anyhow::Result<u64> {
+        let mut tip = None;
+        for provider in self {
+            match provider.try_fetch_block_height().await {
+                Ok(height) => tip = tip.max(Some(height)),
+                Err(err) => {
+                    tracing::debug!(?provider, "failed to fetch block height: {err:#}");
+                }
+            }
+        }
+        tip.context("could not fetch block height from any provider")
+    }
 }
 
 #[cfg(any(test, feature = "testing"))]
@@ -446,12 +573,22 @@ pub mod mock {
     #[derive(Debug, Clone, Default)]
     pub struct MockStateCatchup {
         state: HashMap<ViewNumber, Arc<ValidatedState>>,
+        block_height: Option<u64>,
+    }
+
+    impl MockStateCatchup {
+        /// Report `block_height` as the block height of the chain.
+        pub fn with_block_height(mut self, block_height: u64) -> Self {
+            self.block_height = Some(block_height);
+            self
+        }
     }
 
     impl FromIterator<(ViewNumber, Arc<ValidatedState>)> for MockStateCatchup {
         fn from_iter<I: IntoIterator<Item = (ViewNumber, Arc<ValidatedState>)>>(iter: I) -> Self {
             Self {
                 state: iter.into_iter().collect(),
+                block_height: None,
             }
         }
     }
@@ -496,5 +633,88 @@ pub mod mock {
 
             Ok(())
         }
+
+        async fn try_fetch_block_height(&self) -> anyhow::Result<u64> {
+            self.block_height
+                .context("mock catchup provider has no block height")
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::state::ValidatedState;
+    use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
+    use async_std::task::spawn;
+    use es_version::SequencerVersion;
+    use ethers::types::Address;
+    use futures::{future::join_all, FutureExt};
+    use portpicker::pick_unused_port;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tide_disco::{App, Error as _, StatusCode};
+    use toml::toml;
+
+    #[async_std::test]
+    async fn test_max_in_flight_requests() {
+        setup_logging();
+        setup_backtrace();
+
+        // A peer which holds each request for a while before failing it, keeping track of how many
+        // requests it is handling at once.
+        #[derive(Debug, Default)]
+        struct Counter {
+            total: AtomicUsize,
+            in_flight: AtomicUsize,
+            max_in_flight: AtomicUsize,
+        }
+        let counter = Arc::new(Counter::default());
+
+        let port = pick_unused_port().unwrap();
+        let mut app = App::<_, ServerError>::with_state(());
+        let api = toml! {
+            [route.account]
+            PATH = ["/:height/:view/account/:address"]
+        };
+        app.module::<ServerError, SequencerVersion>("catchup", api)
+            .unwrap()
+            .get("account", {
+                let counter = counter.clone();
+                move |_req, _state| {
+                    let counter = counter.clone();
+                    async move {
+                        counter.total.fetch_add(1, Ordering::SeqCst);
+                        let n = counter.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                        counter.max_in_flight.fetch_max(n, Ordering::SeqCst);
+                        sleep(Duration::from_millis(100)).await;
+                        counter.in_flight.fetch_sub(1, Ordering::SeqCst);
+                        Err::<(), _>(ServerError::catch_all(
+                            StatusCode::NOT_FOUND,
+                            "no such account".into(),
+                        ))
+                    }
+                    .boxed()
+                }
+            })
+            .unwrap();
+        spawn(app.serve(format!("0.0.0.0:{port}"), SequencerVersion::instance()));
+
+        let url: Url = format!("http://localhost:{port}").parse().unwrap();
+        surf_disco::Client::<ServerError, SequencerVersion>::new(url.clone())
+            .connect(None)
+            .await;
+
+        // Many concurrent fetches, but only 2 requests in flight at a time.
+        let peers = StatePeers::<SequencerVersion>::from_urls(vec![url])
+            .with_max_in_flight_requests(Some(2));
+        let root = ValidatedState::default().fee_merkle_tree.commitment();
+        let account = FeeAccount::from(Address::random());
+        let results = join_all(
+            (0..10).map(|_| peers.try_fetch_account(1, ViewNumber::new(1), root, account)),
+        )
+        .await;
+        assert!(results.iter().all(Result::is_err));
+        assert_eq!(counter.total.load(Ordering::SeqCst), 10);
+        assert!(counter.max_in_flight.load(Ordering::SeqCst) <= 2);
     }
 }
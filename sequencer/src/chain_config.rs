@@ -1,6 +1,8 @@
 use crate::{
+    block::NsTable,
     options::parse_size,
     state::{FeeAccount, FeeAmount},
+    NamespaceId, Transaction,
 };
 use committable::{Commitment, Committable};
 use derive_more::{Deref, Display, From, Into};
@@ -10,7 +12,11 @@ use sequencer_utils::{
     impl_serde_from_string_or_integer, impl_to_fixed_bytes, ser::FromStringOrInteger,
 };
 use serde::{Deserialize, Serialize};
-use std::str::FromStr;
+use std::{
+    cmp::{min, Reverse},
+    fmt::Debug,
+    str::FromStr,
+};
 
 #[derive(Default, Hash, Copy, Clone, Debug, Display, PartialEq, Eq, From, Into)]
 #[display(fmt = "{_0}")]
@@ -180,9 +186,125 @@ impl From<ChainConfig> for ResolvableChainConfig {
     }
 }
 
+/// Computes the minimum fee a builder must pay for a block.
+///
+/// The policy is consulted when validating every proposal, so every node in a network must use the
+/// same policy, or they will disagree about which blocks are valid. The policy is not part of the
+/// [`ChainConfig`], which is committed to in every header. Nodes take it from the genesis file (see
+/// [`BuiltinFeePolicy`]), and operators embedding the sequencer can install their own with
+/// [`NodeState::with_fee_policy`](crate::NodeState::with_fee_policy).
+///
+/// A policy should charge nothing for an empty block, so that a leader can always propose one.
+pub trait FeePolicy: Debug + Send + Sync {
+    /// The minimum fee for a block with a payload of `block_size` bytes and namespace table
+    /// `ns_table`.
+    fn required_fee(
+        &self,
+        chain_config: &ChainConfig,
+        block_size: u64,
+        ns_table: &NsTable,
+    ) -> FeeAmount;
+
+    /// The fee per byte of payload builders offer to pay.
+    ///
+    /// This must be enough to pay [`required_fee`](Self::required_fee) for any block, or blocks
+    /// built by builders will be rejected.
+    fn builder_fee_per_byte(&self, chain_config: &ChainConfig) -> FeeAmount {
+        chain_config.base_fee
+    }
+}
+
+/// The fee policies which can be selected in the genesis file.
+///
+/// Each policy charges the chain's `base_fee` per unit of some measure of the block. Every policy
+/// charges nothing for an empty block, and at most `base_fee` per byte of payload, so builders
+/// offering `base_fee` per byte always pay enough.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BuiltinFeePolicy {
+    /// `base_fee` for each byte of the block payload.
+    #[default]
+    PerByte,
+
+    /// `base_fee` for each non-empty block, regardless of its size.
+    PerBlock,
+
+    /// `base_fee` for each namespace in the block.
+    ///
+    /// Each namespace occupies at least 4 bytes of the payload, so this never exceeds `base_fee`
+    /// per byte.
+    PerNamespace,
+}
+
+impl FeePolicy for BuiltinFeePolicy {
+    fn required_fee(
+        &self,
+        chain_config: &ChainConfig,
+        block_size: u64,
+        ns_table: &NsTable,
+    ) -> FeeAmount {
+        match self {
+            Self::PerByte => chain_config.base_fee * block_size,
+            Self::PerBlock if block_size == 0 => 0.into(),
+            Self::PerBlock => chain_config.base_fee,
+            Self::PerNamespace => chain_config.base_fee * ns_table.iter().count() as u64,
+        }
+    }
+}
+
+/// Order in which the transactions of a single namespace are included in a block.
+///
+/// This is a block-building policy rather than a validity rule: it is applied by nodes building
+/// blocks, but blocks with other orderings are not rejected. Hence it is configured alongside, but
+/// not committed as part of, the [`ChainConfig`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TxOrdering {
+    /// Transactions are included in the order they were received.
+    #[default]
+    Arrival,
+
+    /// Transactions are included in decreasing order of a client-supplied priority.
+    ///
+    /// The priority of a transaction is given by the first 8 bytes of its payload, interpreted as
+    /// a big-endian unsigned integer (zero-padded if the payload is shorter than 8 bytes).
+    /// Transactions with equal priority keep their arrival order.
+    PriorityPrefix,
+}
+
+impl TxOrdering {
+    /// Sort `txs`, which must all belong to the same namespace, according to this ordering.
+    pub fn sort(&self, txs: &mut [Transaction]) {
+        match self {
+            Self::Arrival => {}
+            Self::PriorityPrefix => {
+                // `sort_by_key` is stable, so transactions with equal priority keep their relative
+                // order.
+                txs.sort_by_key(|tx| Reverse(Self::priority(tx)))
+            }
+        }
+    }
+
+    fn priority(tx: &Transaction) -> u64 {
+        let mut bytes = [0; 8];
+        let prefix = &tx.payload()[..min(8, tx.payload().len())];
+        bytes[..prefix.len()].copy_from_slice(prefix);
+        u64::from_be_bytes(bytes)
+    }
+}
+
+/// A [`TxOrdering`] for a particular namespace.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct NsTxOrdering {
+    pub namespace: NamespaceId,
+    pub ordering: TxOrdering,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Payload;
+    use hotshot_types::traits::{BlockPayload, EncodeBytes};
 
     #[test]
     fn test_chainid_serde_json_as_decimal() {
@@ -272,6 +394,45 @@ mod tests {
         assert!(chain_config != other_config);
     }
 
+    #[async_std::test]
+    async fn test_builder_fee_covers_required_fee() {
+        let txs = (0..10u32)
+            .map(|i| Transaction::new(NamespaceId::from(i % 5), vec![i as u8; i as usize]))
+            .collect::<Vec<_>>();
+        let payload = Payload::from_transactions(txs, &Default::default(), &Default::default())
+            .await
+            .unwrap()
+            .0;
+        let block_size = payload.encode().len() as u64;
+        let ns_table = payload.ns_table();
+        let empty = Payload::from_transactions([], &Default::default(), &Default::default())
+            .await
+            .unwrap()
+            .0;
+
+        let chain_config = ChainConfig {
+            base_fee: 10.into(),
+            ..Default::default()
+        };
+        for policy in [
+            BuiltinFeePolicy::PerByte,
+            BuiltinFeePolicy::PerBlock,
+            BuiltinFeePolicy::PerNamespace,
+        ] {
+            let required = policy.required_fee(&chain_config, block_size, ns_table);
+            let offered = policy.builder_fee_per_byte(&chain_config) * block_size;
+            assert!(offered >= required, "{policy:?}: {offered} < {required}");
+
+            // Empty blocks are always free.
+            assert_eq!(policy.required_fee(&chain_config, 0, empty.ns_table()), 0.into());
+        }
+
+        assert_eq!(
+            BuiltinFeePolicy::PerNamespace.required_fee(&chain_config, block_size, ns_table),
+            50.into()
+        );
+    }
+
     #[test]
     fn test_resolve_chain_config() {
         let chain_config = ChainConfig::default();
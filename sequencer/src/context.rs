@@ -1,33 +1,48 @@
-use anyhow::Context;
+use anyhow::{ensure, Context};
 use async_std::{
+    future::timeout,
     sync::{Arc, RwLock},
-    task::{spawn, JoinHandle},
+    task::{sleep, spawn, JoinHandle},
 };
 use derivative::Derivative;
 use futures::{
-    future::{join_all, Future},
+    future::{join_all, select, Either, Future},
+    pin_mut,
     stream::{Stream, StreamExt},
 };
 use hotshot::{
     traits::election::static_committee::GeneralStaticCommittee,
-    types::{Event, SystemContextHandle},
+    types::{Event, EventType, SystemContextHandle},
     Memberships, Networks, SystemContext,
 };
+use hotshot_contract_adapter::light_client::ParsedLightClientState;
 use hotshot_orchestrator::client::OrchestratorClient;
 use hotshot_query_service::Leaf;
+use hotshot_state_prover::service::light_client_genesis_from_stake_table;
 use hotshot_types::{
     consensus::ConsensusMetricsValue,
     data::ViewNumber,
-    traits::{election::Membership, metrics::Metrics},
+    traits::{
+        election::Membership,
+        metrics::Metrics,
+        node_implementation::ConsensusTime,
+        stake_table::{SnapshotVersion, StakeTableScheme as _},
+    },
     HotShotConfig,
 };
-use std::fmt::Display;
+use std::{
+    fmt::Display,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
 use url::Url;
 use vbs::version::StaticVersionType;
 
 use crate::{
-    network, persistence::SequencerPersistence, state_signature::StateSigner,
-    static_stake_table_commitment, Node, NodeState, PubKey, SeqTypes, Transaction, ValidatedState,
+    network,
+    persistence::SequencerPersistence,
+    state_signature::{static_stake_table, StateSigner},
+    Node, NodeState, PubKey, SeqTypes, Transaction, ValidatedState,
 };
 use hotshot_events_service::events_source::{EventConsumer, EventsStreamer};
 /// The consensus handle
@@ -60,7 +75,16 @@ pub struct SequencerContext<
 
     detached: bool,
 
+    /// Set when the node begins shutting down, after which new transactions are rejected.
+    shutting_down: Arc<AtomicBool>,
+
+    /// The view of the most recent decide event which has been persisted.
+    persisted_decide_view: Arc<AtomicU64>,
+
     node_state: NodeState,
+
+    /// The genesis light client state, derived from the static stake table.
+    light_client_genesis: ParsedLightClientState,
 }
 
 impl<N: network::Type, P: SequencerPersistence, Ver: StaticVersionType + 'static>
@@ -110,12 +134,15 @@ impl<N: network::Type, P: SequencerPersistence, Ver: StaticVersionType + 'static
             view_sync_membership: committee_membership.clone(),
         };
 
-        let stake_table_commit = static_stake_table_commitment(
-            &config.known_nodes_with_stake,
-            stake_table_capacity
-                .try_into()
-                .context("stake table capacity out of range")?,
-        );
+        let stake_table_capacity = stake_table_capacity
+            .try_into()
+            .context("stake table capacity out of range")?;
+        let stake_table = static_stake_table(&config.known_nodes_with_stake, stake_table_capacity);
+        let stake_table_commit = stake_table
+            .commitment(SnapshotVersion::LastEpochStart)
+            .context("computing stake table commitment")?;
+        let light_client_genesis = light_client_genesis_from_stake_table(stake_table)
+            .context("computing genesis light client state")?;
         let state_key_pair = config.my_own_validator_config.state_key_pair.clone();
 
         let event_streamer = Arc::new(RwLock::new(EventsStreamer::<SeqTypes>::new(
@@ -139,7 +166,8 @@ impl<N: network::Type, P: SequencerPersistence, Ver: StaticVersionType + 'static
         .await?
         .0;
 
-        let mut state_signer = StateSigner::new(state_key_pair, stake_table_commit);
+        let mut state_signer = StateSigner::new(state_key_pair, stake_table_commit)
+            .with_stake_table(config.known_nodes_with_stake.clone(), stake_table_capacity);
         if let Some(url) = state_relay_server {
             state_signer = state_signer.with_relay_server(url);
         }
@@ -150,6 +178,7 @@ impl<N: network::Type, P: SequencerPersistence, Ver: StaticVersionType + 'static
             state_signer,
             event_streamer,
             instance_state,
+            light_client_genesis,
         ))
     }
 
@@ -160,6 +189,7 @@ impl<N: network::Type, P: SequencerPersistence, Ver: StaticVersionType + 'static
         state_signer: StateSigner<Ver>,
         event_streamer: Arc<RwLock<EventsStreamer<SeqTypes>>>,
         node_state: NodeState,
+        light_client_genesis: ParsedLightClientState,
     ) -> Self {
         let events = handle.event_stream();
 
@@ -168,9 +198,12 @@ impl<N: network::Type, P: SequencerPersistence, Ver: StaticVersionType + 'static
             state_signer: Arc::new(state_signer),
             tasks: Default::default(),
             detached: false,
+            shutting_down: Default::default(),
+            persisted_decide_view: Default::default(),
             wait_for_orchestrator: None,
             events_streamer: event_streamer.clone(),
             node_state,
+            light_client_genesis,
         };
         ctx.spawn(
             "main event handler",
@@ -179,6 +212,7 @@ impl<N: network::Type, P: SequencerPersistence, Ver: StaticVersionType + 'static
                 persistence,
                 ctx.state_signer.clone(),
                 Some(event_streamer.clone()),
+                ctx.persisted_decide_view.clone(),
             ),
         );
 
@@ -208,6 +242,7 @@ impl<N: network::Type, P: SequencerPersistence, Ver: StaticVersionType + 'static
     }
 
     pub async fn submit_transaction(&self, tx: Transaction) -> anyhow::Result<()> {
+        ensure!(!self.is_shutting_down(), "node is shutting down");
         self.handle.read().await.submit_transaction(tx).await?;
         Ok(())
     }
@@ -217,6 +252,16 @@ impl<N: network::Type, P: SequencerPersistence, Ver: StaticVersionType + 'static
         self.events_streamer.clone()
     }
 
+    /// Whether this node has begun shutting down and is no longer accepting transactions.
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::Relaxed)
+    }
+
+    /// Flag which is set when this node begins shutting down.
+    pub(crate) fn shutting_down_flag(&self) -> Arc<AtomicBool> {
+        self.shutting_down.clone()
+    }
+
     /// Return a reference to the underlying consensus handle.
     pub fn consensus(&self) -> Arc<RwLock<Consensus<N, P>>> {
         Arc::clone(&self.handle)
@@ -242,6 +287,11 @@ impl<N: network::Type, P: SequencerPersistence, Ver: StaticVersionType + 'static
         self.node_state.clone()
     }
 
+    /// The genesis light client state for this chain.
+    pub fn light_client_genesis(&self) -> ParsedLightClientState {
+        self.light_client_genesis.clone()
+    }
+
     /// Start participating in consensus.
     pub async fn start_consensus(&self) {
         if let Some(orchestrator_client) = &self.wait_for_orchestrator {
@@ -269,6 +319,69 @@ impl<N: network::Type, P: SequencerPersistence, Ver: StaticVersionType + 'static
         self.tasks.shut_down().await;
     }
 
+    /// Stop participating in consensus, giving in-flight transactions a chance to be decided.
+    ///
+    /// New transaction submissions are rejected immediately. If consensus reaches a decide within
+    /// `grace_period`, we wait for that decide to be persisted before shutting down, so that the
+    /// transactions this node has already sequenced are not lost when it restarts. Whether or not
+    /// this happens, the node is shut down once the grace period has elapsed.
+    pub async fn shut_down_gracefully(&mut self, grace_period: Duration) {
+        tracing::warn!(?grace_period, "shutting down gracefully");
+        self.shutting_down.store(true, Ordering::Relaxed);
+        let deadline = Instant::now() + grace_period;
+
+        // Finish the current view, if it is about to decide.
+        let mut events = self.event_stream().await;
+        let decide = async {
+            while let Some(event) = events.next().await {
+                if let EventType::Decide { .. } = event.event {
+                    return Some(event.view_number);
+                }
+            }
+            None
+        };
+        match timeout(grace_period, decide).await {
+            Ok(Some(view)) => {
+                // Wait for the event handler to flush the decide to storage.
+                let persisted = async {
+                    while self.persisted_decide_view.load(Ordering::Relaxed) < view.u64() {
+                        sleep(Duration::from_millis(10)).await;
+                    }
+                };
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if timeout(remaining, persisted).await.is_err() {
+                    tracing::warn!(?view, "grace period elapsed before decide was persisted");
+                }
+            }
+            Ok(None) => tracing::warn!("event stream ended before a decide"),
+            Err(_) => tracing::warn!("grace period elapsed before a decide"),
+        }
+
+        self.shut_down().await;
+    }
+
+    /// Wait for consensus to complete, or shut down gracefully if `shutdown` completes first.
+    ///
+    /// This is like [`join`](Self::join), but when `shutdown` (e.g. a shutdown signal) completes,
+    /// the node shuts down as in [`shut_down_gracefully`](Self::shut_down_gracefully).
+    pub async fn join_or_shut_down(
+        mut self,
+        shutdown: impl Future<Output = ()>,
+        grace_period: Duration,
+    ) {
+        let interrupted = {
+            let tasks = join_all(self.tasks.0.iter_mut().map(|(_, task)| task));
+            pin_mut!(shutdown);
+            matches!(select(tasks, shutdown).await, Either::Right(_))
+        };
+        if interrupted {
+            self.shut_down_gracefully(grace_period).await;
+        } else {
+            // All background tasks have already completed.
+            self.tasks.0.clear();
+        }
+    }
+
     /// Wait for consensus to complete.
     ///
     /// Under normal conditions, this function will block forever, which is a convenient way of
@@ -299,6 +412,7 @@ async fn handle_events<Ver: StaticVersionType>(
     persistence: Arc<RwLock<impl SequencerPersistence>>,
     state_signer: Arc<StateSigner<Ver>>,
     events_streamer: Option<Arc<RwLock<EventsStreamer<SeqTypes>>>>,
+    persisted_decide_view: Arc<AtomicU64>,
 ) {
     while let Some(event) = events.next().await {
         tracing::debug!(?event, "consensus event");
@@ -308,6 +422,9 @@ async fn handle_events<Ver: StaticVersionType>(
             // Store latest consensus state.
             p.handle_event(&event).await;
         }
+        if let EventType::Decide { .. } = event.event {
+            persisted_decide_view.fetch_max(event.view_number.u64(), Ordering::Relaxed);
+        }
         // Generate state signature.
         state_signer.handle_event(&event).await;
 
@@ -1,13 +1,18 @@
 use crate::{
+    chain_config::{BlockSize, BuiltinFeePolicy, NsTxOrdering},
     l1_client::L1BlockInfo,
     state::{FeeAccount, FeeAmount},
-    ChainConfig,
+    state_signature::static_stake_table,
+    ChainConfig, NsTableLayout, PubKey, SignedTxVerifier, TxVerifier,
 };
-use anyhow::Context;
+use anyhow::{ensure, Context};
 use derive_more::{Display, From, Into};
+use hotshot_contract_adapter::light_client::ParsedLightClientState;
+use hotshot_state_prover::service::light_client_genesis_from_stake_table;
+use hotshot_types::{light_client::StateVerKey, traits::signature_key::SignatureKey, PeerConfig};
 use sequencer_utils::{impl_serde_from_string_or_integer, ser::FromStringOrInteger};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, path::Path};
+use std::{collections::HashMap, path::Path, sync::Arc};
 use time::{format_description::well_known::Rfc3339 as TimestampFormat, OffsetDateTime};
 
 /// Initial configuration of an Espresso stake table.
@@ -98,9 +103,56 @@ pub struct Genesis {
     pub accounts: HashMap<FeeAccount, FeeAmount>,
     pub l1_finalized: Option<L1Finalized>,
     pub header: GenesisHeader,
+    /// Per-namespace ordering of transactions when building blocks.
+    ///
+    /// Namespaces not listed here use [`TxOrdering::Arrival`](crate::TxOrdering::Arrival).
+    #[serde(default)]
+    pub tx_ordering: Vec<NsTxOrdering>,
+    /// Maximum size in bytes of a single transaction.
+    ///
+    /// Enforced when accepting transactions and when building blocks, but not when validating
+    /// proposals, so it can be changed without a protocol upgrade. If not set, a transaction is
+    /// only limited by the maximum block size.
+    #[serde(default)]
+    pub max_transaction_size: Option<BlockSize>,
+    /// Minimum fee a transaction must offer to be accepted.
+    ///
+    /// The fee is offered when a transaction is submitted, and is not part of the transaction
+    /// itself, so this is only enforced by the node accepting the submission. Submissions which do
+    /// not specify a fee are treated as offering a fee of 0. If not set, there is no minimum.
+    #[serde(default)]
+    pub min_fee: Option<FeeAmount>,
+    /// Layout of the namespace table of blocks built by this node.
+    ///
+    /// The layout is flagged in each namespace table, so blocks built with any layout are valid
+    /// for every node. Defaults to [`NsTableLayout::Flat`].
+    #[serde(default)]
+    pub ns_table_layout: NsTableLayout,
+    /// Accounts allowed to sign transactions.
+    ///
+    /// If any are listed, only transactions signed by one of them are accepted, as checked by
+    /// [`SignedTxVerifier`]. Like the maximum transaction size, this is enforced when accepting
+    /// transactions and when building blocks, but not when validating proposals. If empty,
+    /// transactions are not verified.
+    #[serde(default)]
+    pub tx_signers: Vec<FeeAccount>,
+    /// How the minimum fee for each block is computed from the base fee.
+    ///
+    /// Unlike the options above, this is enforced when validating proposals, so every node in the
+    /// network must use the same policy. Defaults to [`BuiltinFeePolicy::PerByte`].
+    #[serde(default)]
+    pub fee_policy: BuiltinFeePolicy,
 }
 
 impl Genesis {
+    /// The verifier for transactions accepted by nodes started from this genesis, if any.
+    pub fn tx_verifier(&self) -> Option<Arc<dyn TxVerifier>> {
+        if self.tx_signers.is_empty() {
+            return None;
+        }
+        Some(Arc::new(SignedTxVerifier::new(self.tx_signers.iter().copied())))
+    }
+
     pub fn to_file(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
         let toml = toml::to_string_pretty(self)?;
         std::fs::write(path, toml.as_bytes())?;
@@ -115,15 +167,105 @@ impl Genesis {
     }
 }
 
+/// A validator in the genesis stake table.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GenesisValidator {
+    pub stake_key: PubKey,
+    pub state_key: StateVerKey,
+    pub stake: u64,
+}
+
+impl GenesisValidator {
+    /// Load a list of validators from a TOML file with a `validators` array.
+    ///
+    /// An exported genesis file (see [`GenesisExport`]) has this format, so it can be used to
+    /// export the genesis again with the same stake table.
+    pub fn list_from_file(path: impl AsRef<Path>) -> anyhow::Result<Vec<Self>> {
+        #[derive(Deserialize)]
+        struct ValidatorsFile {
+            validators: Vec<GenesisValidator>,
+        }
+
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).context(format!("validators file {}", path.display()))?;
+        let text = std::str::from_utf8(&bytes).context("validators file must be UTF-8")?;
+        let file: ValidatorsFile = toml::from_str(text).context("malformed validators file")?;
+        Ok(file.validators)
+    }
+
+    fn peer_config(&self) -> PeerConfig<PubKey> {
+        PeerConfig {
+            stake_table_entry: self.stake_key.stake_table_entry(self.stake),
+            state_ver_key: self.state_key.clone(),
+        }
+    }
+}
+
+/// The full genesis of a new network: a [`Genesis`] along with the initial stake table and the
+/// light client genesis state derived from it.
+///
+/// The exported file is itself a valid genesis file, but nodes loading it only use the [`Genesis`].
+/// The stake table they run with comes from the network config, so `validators` and
+/// `light_client_genesis` only record the stake table the network is meant to start with, e.g. for
+/// deploying the light client contract.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GenesisExport {
+    #[serde(flatten)]
+    pub genesis: Genesis,
+    pub validators: Vec<GenesisValidator>,
+    pub light_client_genesis: ParsedLightClientState,
+}
+
+impl GenesisExport {
+    pub fn new(genesis: Genesis, validators: Vec<GenesisValidator>) -> anyhow::Result<Self> {
+        let capacity = genesis
+            .stake_table
+            .capacity
+            .try_into()
+            .context("stake table capacity out of range")?;
+        ensure!(
+            validators.len() <= capacity,
+            "{} validators do not fit in a stake table with capacity {capacity}",
+            validators.len()
+        );
+        let peers = validators
+            .iter()
+            .map(GenesisValidator::peer_config)
+            .collect::<Vec<_>>();
+        let light_client_genesis =
+            light_client_genesis_from_stake_table(static_stake_table(&peers, capacity))
+                .context("computing genesis light client state")?;
+        Ok(Self {
+            genesis,
+            validators,
+            light_client_genesis,
+        })
+    }
+
+    /// Serialize as TOML, with keys in sorted order so the same genesis always exports the same.
+    pub fn to_toml(&self) -> anyhow::Result<String> {
+        Ok(toml::to_string_pretty(&toml::Value::try_from(self)?)?)
+    }
+
+    /// Serialize as JSON, with keys in sorted order so the same genesis always exports the same.
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(&toml::Value::try_from(self)?)?)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use ethers::prelude::{Address, H160, H256};
+    use hotshot_types::light_client::StateKeyPair;
     use toml::toml;
 
     #[test]
     fn test_genesis_from_toml_with_optional_fields() {
         let toml = toml! {
+            ns_table_layout = "sorted"
+            tx_signers = ["0x23618e81E3f5cdF7f54C3d65f7FBc0aBf5B21E8f"]
+
             [stake_table]
             capacity = 10
 
@@ -193,6 +335,15 @@ mod test {
                 ])
             }))
         );
+        assert_eq!(genesis.ns_table_layout, NsTableLayout::Sorted);
+        assert_eq!(
+            genesis.tx_signers,
+            [FeeAccount::from(H160([
+                0x23, 0x61, 0x8e, 0x81, 0xe3, 0xf5, 0xcd, 0xf7, 0xf5, 0x4c, 0x3d, 0x65, 0xf7, 0xfb,
+                0xc0, 0xab, 0xf5, 0xb2, 0x1e, 0x8f
+            ]))]
+        );
+        assert!(genesis.tx_verifier().is_some());
     }
 
     #[test]
@@ -232,6 +383,9 @@ mod test {
         );
         assert_eq!(genesis.accounts, HashMap::default());
         assert_eq!(genesis.l1_finalized, None);
+        assert_eq!(genesis.ns_table_layout, NsTableLayout::Flat);
+        assert!(genesis.tx_signers.is_empty());
+        assert!(genesis.tx_verifier().is_none());
     }
 
     #[test]
@@ -289,4 +443,65 @@ mod test {
             }
         )
     }
+
+    #[test]
+    fn test_genesis_export() {
+        let toml = toml! {
+            [stake_table]
+            capacity = 4
+
+            [chain_config]
+            chain_id = 12345
+            max_block_size = 30000
+            base_fee = 1
+            fee_recipient = "0x0000000000000000000000000000000000000000"
+
+            [header]
+            timestamp = 123456
+
+            [accounts]
+            "0x23618e81E3f5cdF7f54C3d65f7FBc0aBf5B21E8f" = 100000
+            "0x0000000000000000000000000000000000000000" = 42
+        }
+        .to_string();
+        let genesis: Genesis = toml::from_str(&toml).unwrap_or_else(|err| panic!("{err:#}"));
+        let validators = (0..5)
+            .map(|i| GenesisValidator {
+                stake_key: PubKey::generated_from_seed_indexed([0; 32], i).0,
+                state_key: StateKeyPair::generate_from_seed_indexed([0; 32], i).ver_key(),
+                stake: i + 1,
+            })
+            .collect::<Vec<_>>();
+
+        // The stake table must fit within the configured capacity.
+        GenesisExport::new(genesis.clone(), validators.clone()).unwrap_err();
+        let validators = validators[..3].to_vec();
+
+        // The same genesis always exports the same, despite the unordered accounts map.
+        let export = GenesisExport::new(genesis.clone(), validators.clone()).unwrap();
+        let exported = export.to_toml().unwrap();
+        for _ in 0..5 {
+            let again = GenesisExport::new(genesis.clone(), validators.clone()).unwrap();
+            assert_eq!(again.to_toml().unwrap(), exported);
+        }
+        serde_json::from_str::<serde_json::Value>(&export.to_json().unwrap()).unwrap();
+
+        // The exported file can be loaded as a genesis file and as a list of validators.
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join("genesis.toml");
+        std::fs::write(&path, &exported).unwrap();
+        let loaded = Genesis::from_file(&path).unwrap();
+        assert_eq!(loaded.chain_config, genesis.chain_config);
+        assert_eq!(loaded.stake_table, genesis.stake_table);
+        assert_eq!(loaded.accounts, genesis.accounts);
+        assert_eq!(loaded.header, genesis.header);
+        assert_eq!(GenesisValidator::list_from_file(&path).unwrap(), validators);
+        let reloaded: GenesisExport = toml::from_str(&exported).unwrap();
+        assert_eq!(reloaded.validators, validators);
+        assert_eq!(reloaded.light_client_genesis, export.light_client_genesis);
+
+        // The light client genesis depends on the stake table.
+        let other = GenesisExport::new(genesis, validators[..2].to_vec()).unwrap();
+        assert_ne!(other.light_client_genesis, export.light_client_genesis);
+    }
 }
@@ -795,8 +795,15 @@ mod test_headers {
             chain_id: U256::zero().into(),
             ..Default::default()
         };
-        let err = validate_proposal(&state, chain_config, &parent_leaf, &proposal, &vid_common)
-            .unwrap_err();
+        let err = validate_proposal(
+            &state,
+            chain_config,
+            genesis.instance_state.fee_policy.as_ref(),
+            &parent_leaf,
+            &proposal,
+            &vid_common,
+        )
+        .unwrap_err();
 
         assert_eq!(
             ProposalValidationError::InvalidChainConfig {
@@ -816,6 +823,7 @@ mod test_headers {
         let err = validate_proposal(
             &validated_state,
             genesis.instance_state.chain_config,
+            genesis.instance_state.fee_policy.as_ref(),
             &parent_leaf,
             &proposal,
             &vid_common,
@@ -841,6 +849,7 @@ mod test_headers {
         let err = validate_proposal(
             &validated_state,
             genesis.instance_state.chain_config,
+            genesis.instance_state.fee_policy.as_ref(),
             &parent_leaf,
             &proposal,
             &vid_common,
@@ -943,6 +952,7 @@ mod test_headers {
         validate_proposal(
             &proposal_state,
             genesis.instance_state.chain_config,
+            genesis.instance_state.fee_policy.as_ref(),
             &parent_leaf,
             &proposal.clone(),
             &vid_common,
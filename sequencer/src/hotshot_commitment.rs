@@ -1,4 +1,4 @@
-use anyhow::anyhow;
+use anyhow::{anyhow, bail};
 use async_std::{sync::Arc, task::sleep};
 use async_trait::async_trait;
 use contract_bindings::hot_shot::{HotShot, HotShotErrors, Qc};
@@ -8,11 +8,13 @@ use futures::{
     stream::{self, StreamExt},
 };
 use hotshot_query_service::{availability::LeafQueryData, types::HeightIndexed};
+use hotshot_types::traits::metrics::{Counter, Gauge, Metrics};
 use rand::SeedableRng;
 use rand_chacha::ChaChaRng;
 use rand_distr::Distribution;
 use sequencer_utils::{commitment_to_u256, contract_send, init_signer, Signer};
 use std::error::Error;
+use std::str::FromStr;
 use std::time::Duration;
 use surf_disco::Url;
 use vbs::version::StaticVersionType;
@@ -58,10 +60,65 @@ pub struct CommitmentTaskOptions {
 
     /// If specified, sequencing attempts will be delayed by duration sampled from an exponential distribution with mean DELAY.
     pub delay: Option<Duration>,
+
+    /// When a submission to the HotShot contract is considered final.
+    ///
+    /// The task does not submit the next batch of commitments until the previous one is final.
+    pub finality: CommitmentFinality,
+}
+
+/// Source of L1 finality for commitments submitted to the HotShot contract.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CommitmentFinality {
+    /// A submission is final as soon as it is included in an L1 block.
+    #[default]
+    Inclusion,
+    /// A submission is final once the L1 block including it is buried under this many blocks.
+    Confirmations(u64),
+    /// A submission is final once the L1 block including it is at or below the L1 `finalized`
+    /// block.
+    Finalized,
+}
+
+impl FromStr for CommitmentFinality {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "inclusion" => Ok(Self::Inclusion),
+            "finalized" => Ok(Self::Finalized),
+            s => match s.strip_prefix("confirmations:") {
+                Some(depth) => Ok(Self::Confirmations(depth.parse()?)),
+                None => bail!(
+                    "invalid finality {s}: expected inclusion, finalized, or confirmations:<N>"
+                ),
+            },
+        }
+    }
+}
+
+/// Metrics tracking the progress of the commitment task.
+pub struct CommitmentTaskMetrics {
+    /// Number of commitments submitted to L1 and waiting to become final.
+    pending_commitments: Box<dyn Gauge>,
+    /// Total number of commitments which have become final on L1.
+    confirmed_commitments: Box<dyn Counter>,
+}
+
+impl CommitmentTaskMetrics {
+    pub fn new(metrics: &dyn Metrics) -> Self {
+        Self {
+            pending_commitments: metrics.create_gauge("pending_commitments".into(), None),
+            confirmed_commitments: metrics.create_counter("confirmed_commitments".into(), None),
+        }
+    }
 }
 
 /// main logic for the commitment task, which sync the latest blocks from HotShot to L1 contracts
-pub async fn run_hotshot_commitment_task<Ver: StaticVersionType>(opt: &CommitmentTaskOptions) {
+pub async fn run_hotshot_commitment_task<Ver: StaticVersionType>(
+    opt: &CommitmentTaskOptions,
+    metrics: CommitmentTaskMetrics,
+) {
     // init a client connecting to HotShot query service
     let hotshot = HotShotClient::<Ver>::builder(
         opt.query_service_url
@@ -83,13 +140,15 @@ pub async fn run_hotshot_commitment_task<Ver: StaticVersionType>(opt: &Commitmen
     .unwrap();
     let contract = HotShot::new(opt.hotshot_address, signer.clone());
 
-    sequence(hotshot, contract, opt.delay).await;
+    sequence(hotshot, contract, opt.delay, opt.finality, metrics).await;
 }
 
 async fn sequence<Ver: StaticVersionType>(
     hotshot: HotShotClient<Ver>,
     contract: HotShot<Signer>,
     delay: Option<Duration>,
+    finality: CommitmentFinality,
+    metrics: CommitmentTaskMetrics,
 ) {
     // Get the maximum number of blocks the contract will allow at a time.
     let hard_block_limit = match contract.max_blocks().call().await {
@@ -107,7 +166,15 @@ async fn sequence<Ver: StaticVersionType>(
     let mut soft_block_limit = hard_block_limit;
     let mut rng = ChaChaRng::from_entropy();
     loop {
-        if let Err(sync_err) = sync_with_l1(soft_block_limit, &hotshot, &contract).await {
+        if let Err(sync_err) = sync_with_l1(
+            soft_block_limit,
+            &hotshot,
+            &contract,
+            finality,
+            &metrics,
+        )
+        .await
+        {
             match sync_err {
                 SyncError::Other(err) => {
                     tracing::error!("error synchronizing with HotShot contract: {err}");
@@ -184,6 +251,8 @@ async fn sync_with_l1(
     max_blocks: usize,
     hotshot: &impl HotShotDataSource,
     contract: &HotShot<Signer>,
+    finality: CommitmentFinality,
+    metrics: &CommitmentTaskMetrics,
 ) -> Result<(), SyncError> {
     let contract_block_height = contract
         .block_height()
@@ -249,13 +318,71 @@ async fn sync_with_l1(
     // error. We will retry, and may end up changing the transaction we send if the contract state
     // has changed, which is one possible cause of the transaction failure. This can happen, for
     // example, if there are multiple commitment tasks racing.
-    contract_send::<_, _, HotShotErrors>(&txn)
-        .await
-        .map_err(|e| SyncError::TransactionFailed { err: e, num_leaves })?;
+    metrics.pending_commitments.set(num_leaves);
+    let res = async {
+        let (receipt, block) = contract_send::<_, _, HotShotErrors>(&txn)
+            .await
+            .map_err(|e| SyncError::TransactionFailed { err: e, num_leaves })?;
+        wait_for_finality(contract.client().as_ref(), finality, &receipt, block)
+            .await
+            .map_err(SyncError::Other)
+    }
+    .await;
+    metrics.pending_commitments.set(0);
+    res?;
+    metrics.confirmed_commitments.add(num_leaves);
 
     Ok(())
 }
 
+/// Wait until a mined transaction is final according to `finality`.
+///
+/// Fails if the transaction is reorged out of the L1 chain while we are waiting for it to become
+/// final.
+async fn wait_for_finality<M: Middleware>(
+    provider: &M,
+    finality: CommitmentFinality,
+    receipt: &TransactionReceipt,
+    block: u64,
+) -> anyhow::Result<()> {
+    let hash = receipt.transaction_hash;
+    loop {
+        let final_block = match finality {
+            CommitmentFinality::Inclusion => return Ok(()),
+            CommitmentFinality::Confirmations(depth) => provider
+                .get_block_number()
+                .await
+                .map_err(|err| anyhow!("error getting L1 block number: {err}"))?
+                .as_u64()
+                .saturating_sub(depth),
+            CommitmentFinality::Finalized => provider
+                .get_block(BlockNumber::Finalized)
+                .await
+                .map_err(|err| anyhow!("error getting finalized L1 block: {err}"))?
+                .and_then(|block| block.number)
+                .map(|number| number.as_u64())
+                .unwrap_or_default(),
+        };
+        if final_block >= block {
+            break;
+        }
+        tracing::debug!("waiting for contract call {hash:x} in block {block} to become final");
+        sleep(RETRY_DELAY).await;
+    }
+
+    // Make sure the transaction is still in the block we waited on.
+    let final_receipt = provider
+        .get_transaction_receipt(hash)
+        .await
+        .map_err(|err| {
+            anyhow!("contract call {hash:x}: error getting transaction receipt: {err}")
+        })?;
+    match final_receipt {
+        Some(final_receipt) if final_receipt.block_hash == receipt.block_hash => Ok(()),
+        _ => Err(anyhow!("contract call {hash:x} was reorged out of block {block}")),
+    }
+}
+
 /// prepare the transaction from new leaves (with QC) from HotShot
 fn build_sequence_batches_txn<M: ethers::prelude::Middleware>(
     contract: &HotShot<M>,
@@ -282,7 +409,7 @@ mod test {
     use contract_bindings::hot_shot::{NewBlocksCall, NewBlocksFilter};
     use ethers::{abi::AbiDecode, providers::Middleware};
     use futures::FutureExt;
-    use hotshot_types::simple_certificate::QuorumCertificate;
+    use hotshot_types::{simple_certificate::QuorumCertificate, traits::metrics::NoMetrics};
     use sequencer_utils::test_utils::TestL1System;
     use sequencer_utils::AnvilOptions;
     use surf_disco::{Error, StatusCode};
@@ -386,6 +513,7 @@ mod test {
 
         // Connect to the HotShot contract with the expected L1 client.
         let hotshot = HotShot::new(l1.hotshot.address(), adaptor_l1_signer);
+        let metrics = CommitmentTaskMetrics::new(&NoMetrics);
 
         // Ensure the transaction we're going to execute is less than the Geth RPC size limit.
         let txn = build_sequence_batches_txn(
@@ -398,7 +526,9 @@ mod test {
         assert!(size < 131072);
 
         // Sequence them in the HotShot contract.
-        sync_with_l1(num_batches, &data, &hotshot).await.unwrap();
+        sync_with_l1(num_batches, &data, &hotshot, Default::default(), &metrics)
+            .await
+            .unwrap();
 
         // Check the NewBatches event.
         let (event, meta) = wait_for_new_batches(&l1, l1_initial_block.as_u64()).await;
@@ -452,9 +582,12 @@ mod test {
 
         // Connect to the HotShot contract with the expected L1 client.
         let hotshot = HotShot::new(l1.hotshot.address(), adaptor_l1_signer);
+        let metrics = CommitmentTaskMetrics::new(&NoMetrics);
 
         // Sequence them in the HotShot contract.
-        sync_with_l1(1, &data, &hotshot).await.unwrap();
+        sync_with_l1(1, &data, &hotshot, Default::default(), &metrics)
+            .await
+            .unwrap();
 
         // Check the NewBatches event.
         let (event, meta) = wait_for_new_batches(&l1, from_block.as_u64()).await;
@@ -465,7 +598,10 @@ mod test {
         let fut = {
             let data = data.clone();
             let hotshot = hotshot.clone();
-            spawn(async move { sync_with_l1(1, &data, &hotshot).await })
+            let metrics = CommitmentTaskMetrics::new(&NoMetrics);
+            spawn(async move {
+                sync_with_l1(1, &data, &hotshot, Default::default(), &metrics).await
+            })
         };
         // Sleep for a few seconds and make sure nothing happened.
         sleep(Duration::from_secs(3)).await;
@@ -474,7 +610,9 @@ mod test {
 
         // Once a new batch is available, we can sequence it.
         data.leaves.push(Some(mock_leaf(1, &node_state).await));
-        sync_with_l1(1, &data, &hotshot).await.unwrap();
+        sync_with_l1(1, &data, &hotshot, Default::default(), &metrics)
+            .await
+            .unwrap();
         let (event, _) = wait_for_new_batches(&l1, from_block.as_u64()).await;
         assert_eq!(event.first_block_number.as_u64(), 1);
 
@@ -518,19 +656,76 @@ mod test {
 
         // Connect to the HotShot contract with the expected L1 client.
         let hotshot = HotShot::new(l1.hotshot.address(), adaptor_l1_signer);
+        let metrics = CommitmentTaskMetrics::new(&NoMetrics);
 
         // If the first leaf is missing, we cannot make any progress, and sync should fail.
-        sync_with_l1(3, &data, &hotshot).await.unwrap_err();
+        sync_with_l1(3, &data, &hotshot, Default::default(), &metrics)
+            .await
+            .unwrap_err();
 
         // If the first leaf is present but subsequent leaves are missing, we should sequence the
         // leaves that are available.
 
         data.leaves[0] = Some(mock_leaf(0, &node_state).await);
-        sync_with_l1(3, &data, &hotshot).await.unwrap();
+        sync_with_l1(3, &data, &hotshot, Default::default(), &metrics)
+            .await
+            .unwrap();
 
         // Check the NewBatches event.
         let event = wait_for_new_batches(&l1, l1_initial_block.as_u64()).await.0;
         assert_eq!(event.first_block_number, 0.into());
         assert_eq!(event.num_blocks, 2.into());
     }
+
+    #[async_std::test]
+    async fn test_confirmation_depth() {
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = AnvilOptions::default().spawn().await;
+
+        let l1 = TestL1System::deploy(anvil.provider()).await.unwrap();
+        let adaptor_l1_signer = Arc::new(
+            init_signer(l1.provider.url(), TEST_MNEMONIC, l1.clients.funded[0].index)
+                .await
+                .unwrap(),
+        );
+
+        let node_state =
+            NodeState::mock().with_l1(L1Client::new(anvil.provider().url().clone(), 1));
+        let mut data = MockDataSource::default();
+        data.leaves.push(Some(mock_leaf(0, &node_state).await));
+
+        // Connect to the HotShot contract with the expected L1 client.
+        let hotshot = HotShot::new(l1.hotshot.address(), adaptor_l1_signer);
+
+        // Anvil only mines a block when it receives a transaction, so the block including our
+        // commitment will not get any confirmations until we mine more blocks manually.
+        let mut fut = {
+            let data = data.clone();
+            let hotshot = hotshot.clone();
+            spawn(async move {
+                let metrics = CommitmentTaskMetrics::new(&NoMetrics);
+                sync_with_l1(1, &data, &hotshot, CommitmentFinality::Confirmations(2), &metrics)
+                    .await
+            })
+        };
+
+        // The commitment gets included, but sync does not complete until it is 2 blocks deep.
+        sleep(Duration::from_secs(3)).await;
+        assert_eq!(l1.hotshot.block_height().call().await.unwrap().as_u64(), 1);
+        l1.provider
+            .request::<_, ()>("anvil_mine", [U256::from(1)])
+            .await
+            .unwrap();
+        sleep(Duration::from_secs(3)).await;
+        assert!((&mut fut).now_or_never().is_none());
+
+        // Once the commitment is buried deep enough, sync completes.
+        l1.provider
+            .request::<_, ()>("anvil_mine", [U256::from(1)])
+            .await
+            .unwrap();
+        fut.await.unwrap();
+    }
 }
@@ -13,12 +13,14 @@ pub mod state_signature;
 mod message_compat_tests;
 mod reference_tests;
 
-use anyhow::Context;
+use anyhow::{ensure, Context};
 use async_std::sync::RwLock;
 use async_trait::async_trait;
 use catchup::{StateCatchup, StatePeers};
+use chain_config::BlockSize;
 use context::SequencerContext;
 use ethers::types::U256;
+use futures::channel::mpsc;
 #[cfg(feature = "libp2p")]
 use futures::FutureExt;
 use genesis::{GenesisHeader, L1Finalized};
@@ -29,8 +31,7 @@ use l1_client::L1Client;
 
 use libp2p::Multiaddr;
 use network::libp2p::split_off_peer_id;
-use state::FeeAccount;
-use state_signature::static_stake_table_commitment;
+use state::{FeeAccount, FeeAmount};
 use url::Url;
 pub mod l1_client;
 pub mod persistence;
@@ -75,23 +76,29 @@ use hotshot_types::{
 use persistence::{PersistenceOptions, SequencerPersistence};
 use serde::{Deserialize, Serialize};
 use snafu::Snafu;
-use std::{collections::BTreeMap, fmt::Debug, marker::PhantomData, net::SocketAddr, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Debug,
+    marker::PhantomData,
+    net::SocketAddr,
+    sync::Arc,
+    time::Duration,
+};
 use vbs::version::StaticVersionType;
 
-#[cfg(feature = "libp2p")]
-use std::time::Duration;
-
 #[cfg(feature = "libp2p")]
 use hotshot::traits::implementations::{CombinedNetworks, Libp2pNetwork};
 
-pub use block::Payload;
-pub use chain_config::ChainConfig;
+pub use block::{NsPayloadBuilder, NsTableLayout, Payload, TruncatedTransactions};
+pub use chain_config::{BuiltinFeePolicy, ChainConfig, FeePolicy, NsTxOrdering, TxOrdering};
 pub use genesis::Genesis;
 pub use header::Header;
 pub use l1_client::L1BlockInfo;
 pub use options::Options;
 pub use state::ValidatedState;
-pub use transaction::{NamespaceId, Transaction};
+pub use transaction::{
+    NamespaceId, SignedTxVerifier, Transaction, TransactionSubmission, TxVerifier,
+};
 pub mod network;
 
 /// The Sequencer node is generic over the hotshot CommChannel.
@@ -181,6 +188,14 @@ pub struct NodeState {
     pub genesis_header: GenesisHeader,
     pub genesis_state: ValidatedState,
     pub l1_genesis: Option<L1BlockInfo>,
+    pub tx_ordering: HashMap<NamespaceId, TxOrdering>,
+    pub max_transaction_size: Option<BlockSize>,
+    pub min_fee: Option<FeeAmount>,
+    pub truncation_events: Option<mpsc::UnboundedSender<TruncatedTransactions>>,
+    pub tx_verifier: Option<Arc<dyn TxVerifier>>,
+    pub block_building_deadline: Option<Duration>,
+    pub ns_table_layout: NsTableLayout,
+    pub fee_policy: Arc<dyn FeePolicy>,
 }
 
 impl NodeState {
@@ -198,6 +213,14 @@ impl NodeState {
             genesis_header: Default::default(),
             genesis_state: Default::default(),
             l1_genesis: None,
+            tx_ordering: Default::default(),
+            max_transaction_size: None,
+            min_fee: None,
+            truncation_events: None,
+            tx_verifier: None,
+            block_building_deadline: None,
+            ns_table_layout: Default::default(),
+            fee_policy: Arc::new(BuiltinFeePolicy::default()),
         }
     }
 
@@ -225,6 +248,108 @@ impl NodeState {
         self.chain_config = cfg;
         self
     }
+
+    pub fn with_tx_ordering(mut self, ns: NamespaceId, ordering: TxOrdering) -> Self {
+        self.tx_ordering.insert(ns, ordering);
+        self
+    }
+
+    /// The ordering to apply to transactions in namespace `ns` when building a block.
+    pub fn tx_ordering(&self, ns: &NamespaceId) -> TxOrdering {
+        self.tx_ordering.get(ns).copied().unwrap_or_default()
+    }
+
+    pub fn with_max_transaction_size(mut self, size: BlockSize) -> Self {
+        self.max_transaction_size = Some(size);
+        self
+    }
+
+    pub fn with_min_fee(mut self, fee: FeeAmount) -> Self {
+        self.min_fee = Some(fee);
+        self
+    }
+
+    /// Report transactions dropped from blocks built by this node on `sender`.
+    ///
+    /// Whenever a block is truncated to fit in the maximum block size, a [`TruncatedTransactions`]
+    /// event listing the commitments of the first transactions left out is sent on this channel.
+    pub fn with_truncation_events(
+        mut self,
+        sender: mpsc::UnboundedSender<TruncatedTransactions>,
+    ) -> Self {
+        self.truncation_events = Some(sender);
+        self
+    }
+
+    /// Verify transactions with `verifier` before accepting them.
+    ///
+    /// Transactions rejected by the verifier fail
+    /// [`validate_transaction`](Self::validate_transaction), so they are neither submitted through
+    /// this node's API nor included in blocks it builds.
+    pub fn with_tx_verifier(mut self, verifier: impl TxVerifier + 'static) -> Self {
+        self.tx_verifier = Some(Arc::new(verifier));
+        self
+    }
+
+    /// Stop adding transactions to a block once building it has taken `deadline`.
+    ///
+    /// Transactions not yet added when the deadline passes are left out of the block, which is
+    /// still valid. This bounds the time spent building blocks from very large transaction sets.
+    pub fn with_block_building_deadline(mut self, deadline: Duration) -> Self {
+        self.block_building_deadline = Some(deadline);
+        self
+    }
+
+    /// Lay out the namespace table of blocks built by this node according to `layout`.
+    ///
+    /// Blocks built with any layout are valid for every node, but only tables built with
+    /// [`NsTableLayout::Sorted`] are flagged as sorted, and support namespace lookup by binary
+    /// search.
+    pub fn with_ns_table_layout(mut self, layout: NsTableLayout) -> Self {
+        self.ns_table_layout = layout;
+        self
+    }
+
+    /// Compute the minimum fee for each block with `policy`.
+    ///
+    /// Every node in the network must use the same policy, since it is checked when validating
+    /// proposals, and builders offer to pay the fee per byte it asks for.
+    pub fn with_fee_policy(mut self, policy: impl FeePolicy + 'static) -> Self {
+        self.fee_policy = Arc::new(policy);
+        self
+    }
+
+    /// Check that a submission offering to pay `fee` meets this node's minimum fee.
+    ///
+    /// The fee is only known to the node a transaction is submitted to, so the minimum fee is
+    /// enforced at submission, not when building or validating blocks.
+    pub fn validate_fee(&self, fee: Option<FeeAmount>) -> anyhow::Result<()> {
+        if let Some(min) = self.min_fee {
+            let fee = fee.unwrap_or_default();
+            ensure!(
+                fee >= min,
+                "transaction fee of {fee} is below the minimum fee of {min}"
+            );
+        }
+        Ok(())
+    }
+
+    /// Check that `tx` may be submitted to this node and included in a block it builds.
+    pub fn validate_transaction(&self, tx: &Transaction) -> anyhow::Result<()> {
+        if let Some(max) = self.max_transaction_size {
+            ensure!(
+                tx.payload().len() as u64 <= *max,
+                "transaction of {} bytes exceeds maximum transaction size of {max} bytes",
+                tx.payload().len()
+            );
+        }
+        if let Some(verifier) = &self.tx_verifier {
+            verifier
+                .verify(tx)
+                .context("transaction failed verification")?;
+        }
+        Ok(())
+    }
 }
 
 // This allows us to turn on `Default` on InstanceState trait
@@ -289,6 +414,8 @@ pub struct NetworkParams {
     pub private_staking_key: BLSPrivKey,
     pub private_state_key: StateSignKey,
     pub state_peers: Vec<Url>,
+    /// The maximum number of requests to send to `state_peers` at a time, if any.
+    pub state_peers_max_requests: Option<usize>,
 
     /// The address to send to other Libp2p nodes to contact us
     pub libp2p_advertise_address: SocketAddr,
@@ -487,6 +614,7 @@ pub async fn init_node<P: PersistenceOptions, Ver: StaticVersionType + 'static>(
     // crash horribly just because we're not using the P2P network yet.
     let _ = NetworkingMetricsValue::new(metrics);
 
+    let tx_verifier = genesis.tx_verifier();
     let mut genesis_state = ValidatedState::default();
     for (address, amount) in genesis.accounts {
         tracing::info!(%address, %amount, "Prefunding account for demo");
@@ -507,9 +635,24 @@ pub async fn init_node<P: PersistenceOptions, Ver: StaticVersionType + 'static>(
         genesis_header: genesis.header,
         genesis_state,
         l1_genesis,
+        tx_ordering: genesis
+            .tx_ordering
+            .into_iter()
+            .map(|o| (o.namespace, o.ordering))
+            .collect(),
+        max_transaction_size: genesis.max_transaction_size,
+        min_fee: genesis.min_fee,
+        tx_verifier,
+        // Blocks with transactions are built by builders, which configure their own block building
+        // hooks; a sequencer node only ever builds empty blocks.
+        truncation_events: None,
+        block_building_deadline: None,
+        ns_table_layout: genesis.ns_table_layout,
+        fee_policy: Arc::new(genesis.fee_policy),
         peers: catchup::local_and_remote(
             persistence_opt,
-            StatePeers::<Ver>::from_urls(network_params.state_peers),
+            StatePeers::<Ver>::from_urls(network_params.state_peers)
+                .with_max_in_flight_requests(network_params.state_peers_max_requests),
         )
         .await,
         node_id: node_index,
@@ -565,6 +708,7 @@ pub mod testing {
         ExecutionType, HotShotConfig, PeerConfig,
     };
     use portpicker::pick_unused_port;
+    use rand::{Rng, RngCore};
     use std::time::Duration;
 
     const STAKE_TABLE_CAPACITY_FOR_TEST: u64 = 10;
@@ -822,6 +966,30 @@ pub mod testing {
             }
         }
     }
+
+    /// Generate `count` transactions spread across `namespaces`.
+    ///
+    /// Transactions are assigned to namespaces in round-robin order, so each namespace gets at
+    /// least one transaction if `count >= namespaces.len()`. Payloads are random, between 1 and 100
+    /// bytes long. The output depends only on `rng`, so a seeded RNG always yields the same
+    /// transactions.
+    pub fn random_transactions(
+        count: usize,
+        namespaces: &[NamespaceId],
+        rng: &mut impl RngCore,
+    ) -> Vec<Transaction> {
+        assert!(!namespaces.is_empty(), "at least one namespace is required");
+        namespaces
+            .iter()
+            .cycle()
+            .take(count)
+            .map(|ns_id| {
+                let mut payload = vec![0; rng.gen_range(1..=100)];
+                rng.fill_bytes(&mut payload);
+                Transaction::new(*ns_id, payload)
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
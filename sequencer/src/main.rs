@@ -1,16 +1,20 @@
-use std::net::ToSocketAddrs;
+use std::{net::ToSocketAddrs, path::Path};
 
+use anyhow::Context;
 use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
 use clap::Parser;
 use es_version::SEQUENCER_VERSION;
-use futures::future::FutureExt;
+use futures::{future::FutureExt, stream::StreamExt};
 use hotshot_types::traits::metrics::NoMetrics;
 use sequencer::{
     api::{self, data_source::DataSourceOptions},
+    genesis::{GenesisExport, GenesisValidator},
     init_node,
-    options::{Modules, Options},
+    options::{Command, GenesisFormat, GenesisOptions, Modules, Options},
     persistence, Genesis, L1Params, NetworkParams,
 };
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook_async_std::Signals;
 use vbs::version::StaticVersionType;
 
 #[async_std::main]
@@ -18,8 +22,12 @@ async fn main() -> anyhow::Result<()> {
     setup_logging();
     setup_backtrace();
 
-    tracing::warn!("sequencer starting up");
     let opt = Options::parse();
+    if let Some(Command::Genesis(genesis_opt)) = &opt.command {
+        return export_genesis(&opt.genesis_file, genesis_opt);
+    }
+
+    tracing::warn!("sequencer starting up");
     let mut modules = opt.modules();
     tracing::warn!("modules: {:?}", modules);
 
@@ -39,6 +47,22 @@ async fn main() -> anyhow::Result<()> {
     }
 }
 
+fn export_genesis(genesis_file: &Path, opt: &GenesisOptions) -> anyhow::Result<()> {
+    let genesis = Genesis::from_file(genesis_file)?;
+    let validators = GenesisValidator::list_from_file(&opt.validators_file)?;
+    let export = GenesisExport::new(genesis, validators)?;
+    let output = match opt.format {
+        GenesisFormat::Toml => export.to_toml()?,
+        GenesisFormat::Json => export.to_json()?,
+    };
+    match &opt.output {
+        Some(path) => std::fs::write(path, output)
+            .context(format!("writing genesis to {}", path.display()))?,
+        None => println!("{output}"),
+    }
+    Ok(())
+}
+
 async fn init_with_storage<S, Ver: StaticVersionType + 'static>(
     modules: Modules,
     opt: Options,
@@ -83,6 +107,7 @@ where
         private_staking_key,
         private_state_key,
         state_peers: opt.state_peers,
+        state_peers_max_requests: opt.state_peers_max_requests,
     };
 
     // Initialize HotShot. If the user requested the HTTP module, we must initialize the handle in
@@ -117,6 +142,12 @@ where
             if let Some(config) = modules.config {
                 http_opt = http_opt.config(config);
             }
+            if let Some(decide_sink) = modules.decide_sink {
+                http_opt = http_opt.decide_sink(decide_sink);
+            }
+            if let Some(block_archive) = modules.block_archive {
+                http_opt = http_opt.block_archive(block_archive);
+            }
             http_opt
                 .serve(
                     move |metrics| {
@@ -153,9 +184,16 @@ where
         }
     };
 
-    // Start doing consensus.
+    // Start doing consensus, and keep going until we are told to stop.
+    let mut signals = Signals::new([SIGINT, SIGTERM])?;
+    let shutdown = async move {
+        if let Some(signal) = signals.next().await {
+            tracing::warn!(signal, "received shutdown signal");
+        }
+    };
     ctx.start_consensus().await;
-    ctx.join().await;
+    ctx.join_or_shut_down(shutdown, opt.shutdown_grace_period)
+        .await;
 
     Ok(())
 }
@@ -177,6 +215,22 @@ mod test {
     use surf_disco::{error::ClientError, Client, Url};
     use tempfile::TempDir;
 
+    fn test_genesis() -> Genesis {
+        Genesis {
+            chain_config: Default::default(),
+            stake_table: StakeTableConfig { capacity: 10 },
+            accounts: Default::default(),
+            l1_finalized: Default::default(),
+            header: Default::default(),
+            tx_ordering: Default::default(),
+            max_transaction_size: Default::default(),
+            min_fee: Default::default(),
+            ns_table_layout: Default::default(),
+            tx_signers: Default::default(),
+            fee_policy: Default::default(),
+        }
+    }
+
     #[async_std::test]
     async fn test_startup_before_orchestrator() {
         setup_logging();
@@ -189,14 +243,7 @@ mod test {
         let tmp = TempDir::new().unwrap();
 
         let genesis_file = tmp.path().join("genesis.toml");
-        let genesis = Genesis {
-            chain_config: Default::default(),
-            stake_table: StakeTableConfig { capacity: 10 },
-            accounts: Default::default(),
-            l1_finalized: Default::default(),
-            header: Default::default(),
-        };
-        genesis.to_file(&genesis_file).unwrap();
+        test_genesis().to_file(&genesis_file).unwrap();
 
         let modules = Modules {
             http: Some(Http::with_port(port)),
@@ -265,4 +312,72 @@ mod test {
 
         task.cancel().await;
     }
+
+    #[test]
+    fn test_genesis_command() {
+        setup_logging();
+        setup_backtrace();
+
+        let tmp = TempDir::new().unwrap();
+        let genesis = test_genesis();
+        let genesis_file = tmp.path().join("genesis.toml");
+        genesis.to_file(&genesis_file).unwrap();
+        let genesis_file = genesis_file.display().to_string();
+
+        let validators = (0..3)
+            .map(|i| GenesisValidator {
+                stake_key: PubKey::generated_from_seed_indexed([0; 32], i).0,
+                state_key: StateKeyPair::generate_from_seed_indexed([0; 32], i).ver_key(),
+                stake: 1,
+            })
+            .collect::<Vec<_>>();
+        let validators_file = tmp.path().join("validators.toml");
+        let validators_toml = toml::Table::from_iter([(
+            "validators".to_string(),
+            toml::Value::try_from(&validators).unwrap(),
+        )]);
+        std::fs::write(&validators_file, validators_toml.to_string()).unwrap();
+        let validators_file = validators_file.display().to_string();
+        let output = tmp.path().join("exported.toml");
+
+        // The subcommand follows the options of the sequencer, which it reads GENESIS_FILE from.
+        let opt = Options::parse_from([
+            "sequencer",
+            "--genesis-file",
+            &genesis_file,
+            "genesis",
+            "--validators-file",
+            &validators_file,
+            "--output",
+            &output.display().to_string(),
+        ]);
+        let Some(Command::Genesis(genesis_opt)) = &opt.command else {
+            panic!("expected genesis command, got {:?}", opt.command);
+        };
+        assert!(opt.modules().http.is_none());
+        export_genesis(&opt.genesis_file, genesis_opt).unwrap();
+        let exported = Genesis::from_file(&output).unwrap();
+        assert_eq!(exported.chain_config, genesis.chain_config);
+        assert_eq!(exported.stake_table, genesis.stake_table);
+        assert_eq!(GenesisValidator::list_from_file(&output).unwrap(), validators);
+
+        // Modules after `--` are still parsed as modules, not as a command.
+        let opt = Options::parse_from(["sequencer", "--", "http", "--port", "3000"]);
+        assert!(opt.command.is_none());
+        assert!(opt.modules().http.is_some());
+
+        // Modules can't be started along with the command, so they are rejected rather than
+        // silently ignored.
+        Options::try_parse_from([
+            "sequencer",
+            "--genesis-file",
+            &genesis_file,
+            "genesis",
+            "--validators-file",
+            &validators_file,
+            "--",
+            "http",
+        ])
+        .unwrap_err();
+    }
 }
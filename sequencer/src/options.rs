@@ -1,7 +1,7 @@
 use crate::{api, persistence};
 use anyhow::{bail, Context};
 use bytesize::ByteSize;
-use clap::{error::ErrorKind, Args, FromArgMatches, Parser};
+use clap::{error::ErrorKind, Args, FromArgMatches, Parser, Subcommand, ValueEnum};
 use cld::ClDuration;
 use core::fmt::Display;
 use derivative::Derivative;
@@ -157,6 +157,10 @@ pub struct Options {
     #[clap(raw = true)]
     modules: Vec<String>,
 
+    /// Run a command instead of starting the sequencer.
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+
     /// Url we will use for RPC communication with L1.
     #[clap(
         long,
@@ -182,6 +186,24 @@ pub struct Options {
     #[clap(long, env = "ESPRESSO_SEQUENCER_STATE_PEERS", value_delimiter = ',')]
     #[derivative(Debug(format_with = "fmt_urls"))]
     pub state_peers: Vec<Url>,
+
+    /// Maximum number of requests to send to state peers at a time.
+    ///
+    /// If not set, there is no limit.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_STATE_PEERS_MAX_REQUESTS")]
+    pub state_peers_max_requests: Option<usize>,
+
+    /// Maximum time to wait for in-flight work to complete when shutting down.
+    ///
+    /// On SIGTERM or SIGINT, the node stops accepting transactions and waits up to this long for
+    /// the current view to be decided and persisted before it stops participating in consensus.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_SHUTDOWN_GRACE_PERIOD",
+        default_value = "10s",
+        value_parser = parse_duration
+    )]
+    pub shutdown_grace_period: Duration,
 }
 
 impl Options {
@@ -289,6 +311,12 @@ impl ModuleArgs {
                 SequencerModule::Explorer(m) => {
                     curr = m.add(&mut modules.explorer, &mut provided)?
                 }
+                SequencerModule::DecideSink(m) => {
+                    curr = m.add(&mut modules.decide_sink, &mut provided)?
+                }
+                SequencerModule::BlockArchive(m) => {
+                    curr = m.add(&mut modules.block_archive, &mut provided)?
+                }
             }
         }
 
@@ -324,6 +352,8 @@ module!("catchup", api::options::Catchup, requires: "http");
 module!("config", api::options::Config, requires: "http");
 module!("hotshot-events", api::options::HotshotEvents, requires: "http");
 module!("explorer", api::options::Explorer, requires: "http", "storage-sql");
+module!("decide-sink", api::options::DecideSink, requires: "http");
+module!("block-archive", api::options::BlockArchive, requires: "http");
 
 #[derive(Clone, Debug, Args)]
 struct Module<Options: ModuleInfo> {
@@ -362,6 +392,53 @@ impl<Options: ModuleInfo> Module<Options> {
     }
 }
 
+/// Commands which can be run instead of starting the sequencer.
+#[derive(Clone, Debug, Subcommand)]
+pub enum Command {
+    /// Export the full genesis of a new network.
+    ///
+    /// Combines the genesis file given by GENESIS_FILE with a list of validators into a single
+    /// genesis file, including the initial stake table and the light client genesis state derived
+    /// from it. Nodes can load the exported file as their genesis file, but only use the genesis
+    /// state from it: they take their stake table from the network config, and ignore the
+    /// exported validators and light client genesis state.
+    ///
+    /// Options of the sequencer itself, such as GENESIS_FILE, go before the subcommand, as in
+    ///
+    /// sequencer --genesis-file genesis.toml genesis --validators-file validators.toml
+    ///
+    /// Optional modules cannot be given along with this command.
+    Genesis(GenesisOptions),
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct GenesisOptions {
+    /// Path to TOML file listing the validators in the genesis stake table.
+    ///
+    /// The file should contain a `validators` array, with a `stake_key`, `state_key` and `stake`
+    /// for each validator. A previously exported genesis file also has this format.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_GENESIS_VALIDATORS_FILE")]
+    pub validators_file: PathBuf,
+
+    /// Path to write the exported genesis to.
+    ///
+    /// If not provided, the genesis is written to stdout.
+    #[clap(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Format of the exported genesis.
+    ///
+    /// Only TOML can be loaded as a genesis file. JSON is provided for inspection and tooling.
+    #[clap(long, value_enum, default_value = "toml")]
+    pub format: GenesisFormat,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum GenesisFormat {
+    Toml,
+    Json,
+}
+
 #[derive(Clone, Debug, Parser)]
 enum SequencerModule {
     /// Run an HTTP server.
@@ -406,6 +483,14 @@ enum SequencerModule {
     ///
     /// This module requires the http and storage-sql modules to be started.
     Explorer(Module<api::options::Explorer>),
+    /// Publish decided leaves to an external message queue.
+    ///
+    /// This module requires the http module to be started.
+    DecideSink(Module<api::options::DecideSink>),
+    /// Archive decided blocks to local files.
+    ///
+    /// This module requires the http module to be started.
+    BlockArchive(Module<api::options::BlockArchive>),
 }
 
 #[derive(Clone, Debug, Default)]
@@ -421,4 +506,6 @@ pub struct Modules {
     pub config: Option<api::options::Config>,
     pub hotshot_events: Option<api::options::HotshotEvents>,
     pub explorer: Option<api::options::Explorer>,
+    pub decide_sink: Option<api::options::DecideSink>,
+    pub block_archive: Option<api::options::BlockArchive>,
 }
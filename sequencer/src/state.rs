@@ -1,6 +1,9 @@
 use crate::{
-    api::data_source::CatchupDataSource, catchup::SqlStateCatchup, chain_config::BlockSize,
-    eth_signature_key::EthKeyPair, ChainConfig, Header, Leaf, NodeState, SeqTypes,
+    api::{data_source::CatchupDataSource, sql},
+    catchup::SqlStateCatchup,
+    chain_config::BlockSize,
+    eth_signature_key::EthKeyPair,
+    ChainConfig, FeePolicy, Header, Leaf, NodeState, SeqTypes,
 };
 use anyhow::{bail, ensure, Context};
 use ark_serialize::{
@@ -231,10 +234,11 @@ pub enum ProposalValidationError {
         max_block_size: BlockSize,
         block_size: BlockSize,
     },
-    #[error("Insufficient Fee: block_size={max_block_size}, base_fee={base_fee}, proposed_fee={proposed_fee}")]
+    #[error("Insufficient Fee: block_size={max_block_size}, base_fee={base_fee}, required_fee={required_fee}, proposed_fee={proposed_fee}")]
     InsufficientFee {
         max_block_size: BlockSize,
         base_fee: FeeAmount,
+        required_fee: FeeAmount,
         proposed_fee: FeeAmount,
     },
     #[error("Invalid Height: parent_height={parent_height}, proposal_height={proposal_height}")]
@@ -257,6 +261,7 @@ pub enum ProposalValidationError {
 pub fn validate_proposal(
     state: &ValidatedState,
     expected_chain_config: ChainConfig,
+    fee_policy: &dyn FeePolicy,
     parent_leaf: &Leaf,
     proposal: &Header,
     vid_common: &VidCommon,
@@ -280,10 +285,13 @@ pub fn validate_proposal(
         });
     }
 
-    if proposal.fee_info.amount() < expected_chain_config.base_fee * block_size {
+    let required_fee =
+        fee_policy.required_fee(&expected_chain_config, block_size, &proposal.ns_table);
+    if proposal.fee_info.amount() < required_fee {
         return Err(ProposalValidationError::InsufficientFee {
             max_block_size: expected_chain_config.max_block_size,
             base_fee: expected_chain_config.base_fee,
+            required_fee,
             proposed_fee: proposal.fee_info.amount(),
         });
     }
@@ -361,7 +369,9 @@ pub enum BuilderValidationError {
 }
 
 /// Validate builder account by verifying signature
-fn validate_builder_fee(proposed_header: &Header) -> Result<(), BuilderValidationError> {
+pub(crate) fn validate_builder_fee(
+    proposed_header: &Header,
+) -> Result<(), BuilderValidationError> {
     // Beware of Malice!
     let signature = proposed_header
         .builder_signature
@@ -594,6 +604,71 @@ pub(crate) async fn update_state_storage_loop(
     Ok(())
 }
 
+/// Recompute the state after block `to_height` by replaying decided blocks from storage.
+///
+/// Replay starts from the genesis state in `instance` if `from_height` is 0, and otherwise from the
+/// state committed to by the header at `from_height`, fetching any accounts needed along the way
+/// from the merklized state in `storage`. After each block, the replayed state is checked against
+/// the commitments in that block's header, so an error indicates a bug in the state transition or
+/// corrupted storage.
+pub async fn replay_state(
+    storage: Arc<RwLock<sql::DataSource>>,
+    mut instance: NodeState,
+    from_height: u64,
+    to_height: u64,
+) -> anyhow::Result<ValidatedState> {
+    ensure!(
+        from_height <= to_height,
+        "invalid replay range {from_height}..={to_height}"
+    );
+    instance.peers = Arc::new(SqlStateCatchup::from(storage.clone()));
+
+    let mut parent_leaf = get_stored_leaf(&storage, from_height).await?;
+    let mut state = if from_height == 0 {
+        instance.genesis_state.clone()
+    } else {
+        ValidatedState::from_header(parent_leaf.header())
+    };
+
+    for height in from_height + 1..=to_height {
+        let leaf = get_stored_leaf(&storage, height).await?;
+        let (next, _) = compute_state_update(&state, &instance, &parent_leaf, &leaf)
+            .await
+            .with_context(|| format!("replaying block {height}"))?;
+
+        let header = leaf.header();
+        ensure!(
+            next.block_merkle_tree.commitment() == header.block_merkle_tree_root,
+            "replayed block tree {:?} does not match header {height} {:?}",
+            next.block_merkle_tree.commitment(),
+            header.block_merkle_tree_root
+        );
+        ensure!(
+            next.fee_merkle_tree.commitment() == header.fee_merkle_tree_root,
+            "replayed fee tree {:?} does not match header {height} {:?}",
+            next.fee_merkle_tree.commitment(),
+            header.fee_merkle_tree_root
+        );
+        tracing::debug!(height, "replayed block");
+
+        state = next;
+        parent_leaf = leaf;
+    }
+
+    Ok(state)
+}
+
+async fn get_stored_leaf(
+    storage: &RwLock<impl SequencerStateDataSource>,
+    height: u64,
+) -> anyhow::Result<LeafQueryData<SeqTypes>> {
+    let fetch = storage.read().await.get_leaf(height as usize).await;
+    fetch
+        .try_resolve()
+        .ok()
+        .with_context(|| format!("leaf {height} is not available in storage"))
+}
+
 pub(crate) trait SequencerStateDataSource:
     'static
     + Debug
@@ -804,6 +879,7 @@ impl HotShotState<SeqTypes> for ValidatedState {
         if let Err(err) = validate_proposal(
             &validated_state,
             instance.chain_config,
+            instance.fee_policy.as_ref(),
             parent_leaf,
             proposed_header,
             &vid_common,
@@ -871,6 +947,35 @@ impl hotshot_types::traits::states::TestableState<SeqTypes> for ValidatedState {
 pub type BlockMerkleTree = LightWeightSHA3MerkleTree<Commitment<Header>>;
 pub type BlockMerkleCommitment = <BlockMerkleTree as MerkleTreeScheme>::Commitment;
 
+/// Verify that appending `headers` to the block Merkle tree `frontier` yields the root `expected`.
+///
+/// `frontier` need only contain the path to its last leaf, as recovered by catchup. The header
+/// commitments are appended in order, so this checks a reconstructed frontier against a chain of
+/// published headers: `expected` is the block Merkle tree root of the header after the last one
+/// in `headers`. On success, returns the extended frontier.
+pub fn verify_block_frontier_chain(
+    frontier: &BlockMerkleTree,
+    headers: impl IntoIterator<Item = Commitment<Header>>,
+    expected: BlockMerkleCommitment,
+) -> anyhow::Result<BlockMerkleTree> {
+    let mut tree = frontier.clone();
+    let num_leaves = tree.num_leaves();
+    ensure!(
+        num_leaves == 0 || tree.lookup(num_leaves - 1).expect_ok().is_ok(),
+        "block Merkle tree frontier is not in memory"
+    );
+    for header in headers {
+        tree.push(header)
+            .with_context(|| format!("appending header {header} at {}", tree.num_leaves()))?;
+    }
+    ensure!(
+        tree.commitment() == expected,
+        "block Merkle tree root {:?} does not match expected root {expected:?}",
+        tree.commitment()
+    );
+    Ok(tree)
+}
+
 impl MerklizedState<SeqTypes, { Self::ARITY }> for BlockMerkleTree {
     type Key = Self::Index;
     type Entry = Commitment<Header>;
@@ -1298,6 +1403,33 @@ impl FeeAccountProof {
         }
     }
 
+    /// Prove the balances of several `accounts` against the same root.
+    ///
+    /// The resulting proof shares the parts of the Merkle paths that the accounts have in common,
+    /// so it is smaller than the individual proofs of each account. The balances are returned in
+    /// the same order as `accounts`.
+    pub fn prove_many(
+        tree: &FeeMerkleTree,
+        accounts: impl IntoIterator<Item = Address>,
+    ) -> Option<(FeeAccountMultiProof, Vec<U256>)> {
+        let mut paths = FeeMerkleTree::from_commitment(tree.commitment());
+        let mut proven = vec![];
+        let mut balances = vec![];
+        for account in accounts {
+            let (proof, balance) = Self::prove(tree, account)?;
+            proof.remember(&mut paths).ok()?;
+            proven.push(account);
+            balances.push(balance);
+        }
+        Some((
+            FeeAccountMultiProof {
+                accounts: proven,
+                paths,
+            },
+            balances,
+        ))
+    }
+
     pub fn verify(&self, comm: &FeeMerkleCommitment) -> anyhow::Result<U256> {
         match &self.proof {
             FeeMerkleProof::Presence(proof) => {
@@ -1341,9 +1473,44 @@ impl FeeAccountProof {
     }
 }
 
+/// A proof of the balances of several accounts in the fee ledger, against the same root.
+///
+/// The proof consists of a sparse fee tree in which only the Merkle paths of the proven accounts
+/// are remembered, so that nodes shared by several paths appear only once.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FeeAccountMultiProof {
+    accounts: Vec<Address>,
+    paths: FeeMerkleTree,
+}
+
+impl FeeAccountMultiProof {
+    /// The accounts whose balances are proven, in order.
+    pub fn accounts(&self) -> &[Address] {
+        &self.accounts
+    }
+
+    /// Verify the balances of all the proven accounts against `comm`.
+    ///
+    /// The balances are returned in the same order as [`Self::accounts`].
+    pub fn verify(&self, comm: &FeeMerkleCommitment) -> anyhow::Result<Vec<U256>> {
+        ensure!(self.paths.commitment() == *comm, "proof is for a different root");
+        self.accounts
+            .iter()
+            .map(|account| {
+                // The sparse tree is untrusted, so extract the path of each account from it and
+                // check that path against the root.
+                let (proof, _) = FeeAccountProof::prove(&self.paths, *account)
+                    .context(format!("proof is missing the path of account {account}"))?;
+                proof.verify(comm)
+            })
+            .collect()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::{block::NsTable, BuiltinFeePolicy};
     use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
     use hotshot_types::vid::vid_scheme;
     use jf_vid::VidScheme;
@@ -1387,6 +1554,95 @@ mod test {
         FeeAccountProof::prove(&tree, account2).unwrap();
     }
 
+    #[test]
+    fn test_fee_multiproof() {
+        setup_logging();
+        setup_backtrace();
+
+        let mut tree = ValidatedState::default().fee_merkle_tree;
+        let mut accounts = vec![];
+        for i in 1..=10 {
+            let account = Address::random();
+            tree.update(FeeAccount(account), FeeAmount(i.into()))
+                .unwrap();
+            accounts.push(account);
+        }
+        // Include an account which is not in the tree.
+        accounts.push(Address::default());
+
+        let (multiproof, balances) = FeeAccountProof::prove_many(&tree, accounts.clone()).unwrap();
+        assert_eq!(multiproof.accounts(), accounts);
+        assert_eq!(multiproof.verify(&tree.commitment()).unwrap(), balances);
+
+        // The multiproof agrees with, and is smaller than, the individual proofs.
+        let mut individual_size = 0;
+        for (account, balance) in accounts.iter().zip(&balances) {
+            let (proof, expected) = FeeAccountProof::prove(&tree, *account).unwrap();
+            assert_eq!(*balance, expected);
+            assert_eq!(proof.verify(&tree.commitment()).unwrap(), expected);
+            individual_size += bincode::serialize(&proof).unwrap().len();
+        }
+        let multiproof_size = bincode::serialize(&multiproof).unwrap().len();
+        tracing::info!(multiproof_size, individual_size);
+        assert!(multiproof_size < individual_size);
+
+        // The multiproof does not verify against a different root.
+        let mut other = tree.clone();
+        other
+            .update(FeeAccount(accounts[0]), FeeAmount(100.into()))
+            .unwrap();
+        multiproof.verify(&other.commitment()).unwrap_err();
+    }
+
+    #[async_std::test]
+    async fn test_verify_block_frontier_chain() {
+        setup_logging();
+        setup_backtrace();
+
+        let instance = NodeState::mock();
+        let genesis = Leaf::genesis(&instance.genesis_state, &instance).await;
+        let headers = (0..10)
+            .map(|height| {
+                let mut header = genesis.block_header().clone();
+                header.height = height;
+                header.commit()
+            })
+            .collect::<Vec<_>>();
+
+        let mut full = BlockMerkleTree::new(BLOCK_MERKLE_TREE_HEIGHT);
+        for header in &headers[..5] {
+            full.push(*header).unwrap();
+        }
+
+        // Reconstruct the frontier after 5 blocks the way catchup does.
+        let mut frontier = BlockMerkleTree::from_commitment(full.commitment());
+        let (elem, proof) = full.lookup(4).expect_ok().unwrap();
+        frontier.remember(4, elem, &proof).unwrap();
+
+        for header in &headers[5..] {
+            full.push(*header).unwrap();
+        }
+        let expected = full.commitment();
+
+        let verify = |frontier: &BlockMerkleTree, headers: Vec<Commitment<Header>>| {
+            verify_block_frontier_chain(frontier, headers, expected)
+        };
+
+        // The frontier extends to the expected root.
+        let tree = verify(&frontier, headers[5..].to_vec()).unwrap();
+        assert_eq!(tree.commitment(), expected);
+        assert_eq!(tree.num_leaves(), headers.len() as u64);
+
+        // Missing, reordered, or extra headers are detected.
+        verify(&frontier, headers[5..9].to_vec()).unwrap_err();
+        verify(&frontier, headers[5..].iter().rev().copied().collect()).unwrap_err();
+        verify(&frontier, headers[4..].to_vec()).unwrap_err();
+
+        // A tree without its frontier in memory cannot be extended.
+        let forgotten = BlockMerkleTree::from_commitment(frontier.commitment());
+        verify(&forgotten, headers[5..].to_vec()).unwrap_err();
+    }
+
     #[async_std::test]
     async fn test_validation_max_block_size() {
         setup_logging();
@@ -1406,8 +1662,15 @@ mod test {
         let header = parent.block_header();
 
         // Validation fails because the proposed block exceeds the maximum block size.
-        let err = validate_proposal(&state, instance.chain_config, &parent, header, &vid_common)
-            .unwrap_err();
+        let err = validate_proposal(
+            &state,
+            instance.chain_config,
+            instance.fee_policy.as_ref(),
+            &parent,
+            header,
+            &vid_common,
+        )
+        .unwrap_err();
 
         tracing::info!(%err, "task failed successfully");
         assert_eq!(
@@ -1441,20 +1704,93 @@ mod test {
         let header = parent.block_header();
 
         // Validation fails because the genesis fee (0) is too low.
-        let err = validate_proposal(&state, instance.chain_config, &parent, header, &vid_common)
-            .unwrap_err();
+        let err = validate_proposal(
+            &state,
+            instance.chain_config,
+            instance.fee_policy.as_ref(),
+            &parent,
+            header,
+            &vid_common,
+        )
+        .unwrap_err();
 
         tracing::info!(%err, "task failed successfully");
         assert_eq!(
             ProposalValidationError::InsufficientFee {
                 max_block_size: instance.chain_config.max_block_size,
                 base_fee: instance.chain_config.base_fee,
+                required_fee: instance.chain_config.base_fee * payload.len() as u64,
                 proposed_fee: header.fee_info.amount()
             },
             err
         );
     }
 
+    #[async_std::test]
+    async fn test_validation_fee_policy() {
+        setup_logging();
+        setup_backtrace();
+
+        /// Charges a fixed fee per byte of payload, regardless of the chain's base fee.
+        #[derive(Debug)]
+        struct PerByte(u64);
+
+        impl FeePolicy for PerByte {
+            fn required_fee(&self, _: &ChainConfig, block_size: u64, _: &NsTable) -> FeeAmount {
+                (self.0 * block_size).into()
+            }
+        }
+
+        /// The fee required by `validate_proposal` for a block of `block_size` bytes.
+        async fn required_fee(policy: impl FeePolicy + 'static, block_size: usize) -> FeeAmount {
+            let instance = NodeState::mock()
+                .with_chain_config(ChainConfig {
+                    base_fee: 10.into(),
+                    max_block_size: 100.into(),
+                    ..Default::default()
+                })
+                .with_fee_policy(policy);
+            let parent = Leaf::genesis(&instance.genesis_state, &instance).await;
+            let header = parent.block_header();
+            let vid_common = vid_scheme(1).disperse(vec![0; block_size]).unwrap().common;
+
+            // The genesis header pays no fee, so validation fails on the fee unless none is
+            // required, in which case it goes on to fail on the height of the genesis header.
+            let state = ValidatedState::default();
+            match validate_proposal(
+                &state,
+                instance.chain_config,
+                instance.fee_policy.as_ref(),
+                &parent,
+                header,
+                &vid_common,
+            ) {
+                Err(ProposalValidationError::InsufficientFee {
+                    required_fee,
+                    proposed_fee,
+                    ..
+                }) => {
+                    assert_eq!(proposed_fee, header.fee_info.amount());
+                    required_fee
+                }
+                Err(ProposalValidationError::InvalidHeight { .. }) => 0.into(),
+                res => panic!("unexpected validation result {res:?}"),
+            }
+        }
+
+        // Under a per-byte policy, larger blocks cost more.
+        assert_eq!(required_fee(PerByte(3), 1).await, 3.into());
+        assert_eq!(required_fee(PerByte(3), 50).await, 150.into());
+
+        // The default policy charges the base fee per byte.
+        assert_eq!(required_fee(BuiltinFeePolicy::PerByte, 50).await, 500.into());
+
+        // Under a per-block policy, all non-empty blocks cost the same, and empty blocks are free.
+        assert_eq!(required_fee(BuiltinFeePolicy::PerBlock, 1).await, 10.into());
+        assert_eq!(required_fee(BuiltinFeePolicy::PerBlock, 50).await, 10.into());
+        assert_eq!(required_fee(BuiltinFeePolicy::PerBlock, 0).await, 0.into());
+    }
+
     #[test]
     fn test_charge_fee() {
         setup_logging();
@@ -1,9 +1,11 @@
 //! Utilities for generating and storing the most recent light client state signatures.
 
-use crate::{Leaf, SeqTypes, StateKeyPair};
+use crate::{Header, Leaf, PubKey, SeqTypes, StateKeyPair};
+use anyhow::{ensure, Context};
 use ark_ff::PrimeField;
 use ark_serialize::CanonicalSerialize;
 use async_std::sync::RwLock;
+use async_trait::async_trait;
 use hotshot::types::{Event, EventType};
 use hotshot_stake_table::vec_based::StakeTable;
 use hotshot_types::light_client::{
@@ -24,7 +26,11 @@ use jf_crhf::CRHF;
 use jf_rescue::crhf::VariableLengthRescueCRHF;
 use jf_rescue::RescueError;
 use jf_signature::SignatureScheme;
-use std::collections::{HashMap, VecDeque};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::Debug,
+};
 use surf_disco::{Client, Url};
 use tide_disco::error::ServerError;
 use vbs::version::StaticVersionType;
@@ -35,10 +41,84 @@ pub mod relay_server;
 /// Capacity for the in memory signature storage.
 const SIGNATURE_STORAGE_CAPACITY: usize = 100;
 
+/// A backend capable of signing light client states.
+///
+/// The default backend is an in-memory [`StateKeyPair`]. Deployments which cannot hold the state
+/// signing key in process (for example, because it lives in an HSM or a remote KMS) can use
+/// [`RemoteStateSigner`] or provide their own implementation.
+#[async_trait]
+pub trait StateSignBackend: Debug + Send + Sync + 'static {
+    /// The verification key corresponding to the signing key used by this backend.
+    fn ver_key(&self) -> StateVerKey;
+
+    /// Sign a light client state.
+    async fn sign(&self, state: &LightClientState) -> anyhow::Result<StateSignature>;
+}
+
+#[async_trait]
+impl StateSignBackend for StateKeyPair {
+    fn ver_key(&self) -> StateVerKey {
+        StateKeyPair::ver_key(self)
+    }
+
+    async fn sign(&self, state: &LightClientState) -> anyhow::Result<StateSignature> {
+        let msg: [CircuitField; 7] = state.into();
+        Ok(StateSignatureScheme::sign(
+            &(),
+            self.sign_key_ref(),
+            msg,
+            &mut rand::thread_rng(),
+        )?)
+    }
+}
+
+/// A [`StateSignBackend`] which delegates signing to a remote HTTP service.
+///
+/// The service is expected to accept a `POST` request to `sign` with a binary-serialized
+/// [`LightClientState`] as the body, and to respond with the [`StateSignature`] of that state under
+/// the key corresponding to `ver_key`.
+#[derive(Debug)]
+pub struct RemoteStateSigner<Ver: StaticVersionType> {
+    client: Client<ServerError, Ver>,
+    ver_key: StateVerKey,
+}
+
+impl<Ver: StaticVersionType> RemoteStateSigner<Ver> {
+    pub fn new(url: Url, ver_key: StateVerKey) -> Self {
+        Self {
+            client: Client::new(url),
+            ver_key,
+        }
+    }
+}
+
+#[async_trait]
+impl<Ver: StaticVersionType + 'static> StateSignBackend for RemoteStateSigner<Ver> {
+    fn ver_key(&self) -> StateVerKey {
+        self.ver_key.clone()
+    }
+
+    async fn sign(&self, state: &LightClientState) -> anyhow::Result<StateSignature> {
+        let signature = self
+            .client
+            .post::<StateSignature>("sign")
+            .body_binary(state)?
+            .send()
+            .await?;
+
+        // Don't trust the remote signer blindly; a bad signature would only be discovered when the
+        // prover tries to use it.
+        let msg: [CircuitField; 7] = state.into();
+        StateSignatureScheme::verify(&(), &self.ver_key, msg, &signature)
+            .context("remote signer returned an invalid signature")?;
+        Ok(signature)
+    }
+}
+
 #[derive(Debug)]
-pub struct StateSigner<Ver: StaticVersionType> {
-    /// Key pair for signing a new light client state
-    key_pair: StateKeyPair,
+pub struct StateSigner<Ver: StaticVersionType, B: StateSignBackend = StateKeyPair> {
+    /// Backend for signing a new light client state
+    backend: B,
 
     /// The most recent light client state signatures
     signatures: RwLock<StateSignatureMemStorage>,
@@ -46,15 +126,19 @@ pub struct StateSigner<Ver: StaticVersionType> {
     /// Commitment for current fixed stake table
     stake_table_comm: StakeTableCommitmentType,
 
+    /// The stake table entries and capacity committed to by `stake_table_comm`, if known
+    stake_table: Option<(Vec<PeerConfig<PubKey>>, usize)>,
+
     /// The state relay server url
     relay_server_client: Option<Client<ServerError, Ver>>,
 }
 
-impl<Ver: StaticVersionType> StateSigner<Ver> {
-    pub fn new(key_pair: StateKeyPair, stake_table_comm: StakeTableCommitmentType) -> Self {
+impl<Ver: StaticVersionType, B: StateSignBackend> StateSigner<Ver, B> {
+    pub fn new(backend: B, stake_table_comm: StakeTableCommitmentType) -> Self {
         Self {
-            key_pair,
+            backend,
             stake_table_comm,
+            stake_table: None,
             signatures: Default::default(),
             relay_server_client: Default::default(),
         }
@@ -66,6 +150,18 @@ impl<Ver: StaticVersionType> StateSigner<Ver> {
         self
     }
 
+    /// Provide the stake table committed to by the stake table commitment.
+    ///
+    /// The stake table is needed to serve [`StateSignatureBundle`]s.
+    pub fn with_stake_table(
+        mut self,
+        stake_table: Vec<PeerConfig<PubKey>>,
+        capacity: usize,
+    ) -> Self {
+        self.stake_table = Some((stake_table, capacity));
+        self
+    }
+
     pub(super) async fn handle_event(&self, event: &Event<SeqTypes>) {
         let EventType::Decide { leaf_chain, .. } = &event.event else {
             return;
@@ -75,12 +171,18 @@ impl<Ver: StaticVersionType> StateSigner<Ver> {
         };
         match form_light_client_state(leaf, &self.stake_table_comm) {
             Ok(state) => {
-                let signature = self.sign_new_state(&state).await;
+                let signature = match self.sign_new_state(&state, leaf.block_header()).await {
+                    Ok(signature) => signature,
+                    Err(err) => {
+                        tracing::error!("Error signing light client state: {err:#}");
+                        return;
+                    }
+                };
                 tracing::debug!("New leaves decided. Latest block height: {}", leaf.height(),);
 
                 if let Some(client) = &self.relay_server_client {
                     let request_body = StateSignatureRequestBody {
-                        key: self.key_pair.ver_key(),
+                        key: self.backend.ver_key(),
                         state,
                         signature,
                     };
@@ -107,30 +209,51 @@ impl<Ver: StaticVersionType> StateSigner<Ver> {
         pool_guard.get_signature(height)
     }
 
+    /// Return a self-verifying bundle for the light client state at given height.
+    ///
+    /// Returns [`None`] if the state at `height` has not been signed recently, or if the stake
+    /// table is not known.
+    pub async fn get_state_signature_bundle(&self, height: u64) -> Option<StateSignatureBundle> {
+        let (stake_table, stake_table_capacity) = self.stake_table.clone()?;
+        let (signature, header) = self.signatures.read().await.get(height)?;
+        Some(StateSignatureBundle {
+            header,
+            signature,
+            stake_table,
+            stake_table_capacity,
+        })
+    }
+
+    /// Return a self-verifying bundle for the most recently signed light client state.
+    ///
+    /// Returns [`None`] if no state has been signed yet, or if the stake table is not known.
+    pub async fn get_latest_state_signature_bundle(&self) -> Option<StateSignatureBundle> {
+        let height = self.signatures.read().await.latest_height()?;
+        self.get_state_signature_bundle(height).await
+    }
+
     /// Sign the light client state at given height and store it.
-    async fn sign_new_state(&self, state: &LightClientState) -> StateSignature {
-        let msg: [CircuitField; 7] = state.into();
-        let signature = StateSignatureScheme::sign(
-            &(),
-            self.key_pair.sign_key_ref(),
-            msg,
-            &mut rand::thread_rng(),
-        )
-        .unwrap();
+    async fn sign_new_state(
+        &self,
+        state: &LightClientState,
+        header: &Header,
+    ) -> anyhow::Result<StateSignature> {
+        let signature = self.backend.sign(state).await?;
         let mut pool_guard = self.signatures.write().await;
         pool_guard.push(
             state.block_height as u64,
             StateSignatureRequestBody {
-                key: self.key_pair.ver_key(),
+                key: self.backend.ver_key(),
                 state: state.clone(),
                 signature: signature.clone(),
             },
+            header.clone(),
         );
         tracing::debug!(
             "New signature added for block height {}",
             state.block_height
         );
-        signature
+        Ok(signature)
     }
 }
 
@@ -144,11 +267,8 @@ fn hash_bytes_to_field(bytes: &[u8]) -> Result<CircuitField, RescueError> {
     Ok(VariableLengthRescueCRHF::<_, 1>::evaluate(elem)?[0])
 }
 
-fn form_light_client_state(
-    leaf: &Leaf,
-    stake_table_comm: &StakeTableCommitmentType,
-) -> anyhow::Result<LightClientState> {
-    let header = leaf.block_header();
+/// Compute the block and fee ledger commitments of the light client state for `header`.
+fn light_client_commitments(header: &Header) -> anyhow::Result<(CircuitField, CircuitField)> {
     let mut block_comm_root_bytes = vec![];
     header
         .block_merkle_tree_root
@@ -158,25 +278,106 @@ fn form_light_client_state(
     header
         .fee_merkle_tree_root
         .serialize_compressed(&mut fee_ledger_comm_bytes)?;
+    Ok((
+        hash_bytes_to_field(&block_comm_root_bytes)?,
+        hash_bytes_to_field(&fee_ledger_comm_bytes)?,
+    ))
+}
+
+fn form_light_client_state(
+    leaf: &Leaf,
+    stake_table_comm: &StakeTableCommitmentType,
+) -> anyhow::Result<LightClientState> {
+    let (block_comm_root, fee_ledger_comm) = light_client_commitments(leaf.block_header())?;
     Ok(LightClientState {
         view_number: leaf.view_number().u64() as usize,
         block_height: leaf.height() as usize,
-        block_comm_root: hash_bytes_to_field(&block_comm_root_bytes)?,
-        fee_ledger_comm: hash_bytes_to_field(&fee_ledger_comm_bytes)?,
+        block_comm_root,
+        fee_ledger_comm,
         stake_table_comm: *stake_table_comm,
     })
 }
 
+/// Everything a light client needs to verify a signed light client state in one place.
+///
+/// The bundle contains the header the state was derived from, a node's signature on the state, and
+/// the stake table the state commits to. [`verify`](Self::verify) checks that these are all
+/// consistent without any other input.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StateSignatureBundle {
+    pub header: Header,
+    pub signature: StateSignatureRequestBody,
+    pub stake_table: Vec<PeerConfig<PubKey>>,
+    pub stake_table_capacity: usize,
+}
+
+impl StateSignatureBundle {
+    /// Verify that the signed state is derived from the header and the stake table, and that it is
+    /// signed by a member of the stake table.
+    pub fn verify(&self) -> anyhow::Result<()> {
+        let state = &self.signature.state;
+
+        ensure!(
+            state.block_height as u64 == self.header.height,
+            "state is for block {}, but header is for block {}",
+            state.block_height,
+            self.header.height
+        );
+        let (block_comm_root, fee_ledger_comm) = light_client_commitments(&self.header)?;
+        ensure!(
+            state.block_comm_root == block_comm_root,
+            "block commitment does not match header"
+        );
+        ensure!(
+            state.fee_ledger_comm == fee_ledger_comm,
+            "fee ledger commitment does not match header"
+        );
+
+        // Building the stake table panics on invalid input, so validate it first.
+        ensure!(
+            self.stake_table.len() <= self.stake_table_capacity,
+            "stake table exceeds its capacity"
+        );
+        let keys = self
+            .stake_table
+            .iter()
+            .map(|peer| peer.stake_table_entry.key())
+            .collect::<HashSet<_>>();
+        ensure!(
+            keys.len() == self.stake_table.len(),
+            "duplicate keys in stake table"
+        );
+        ensure!(
+            state.stake_table_comm
+                == static_stake_table_commitment(&self.stake_table, self.stake_table_capacity),
+            "stake table commitment does not match stake table"
+        );
+        ensure!(
+            self.stake_table
+                .iter()
+                .any(|peer| peer.state_ver_key == self.signature.key),
+            "signer is not in the stake table"
+        );
+
+        let msg: [CircuitField; 7] = state.into();
+        StateSignatureScheme::verify(&(), &self.signature.key, msg, &self.signature.signature)
+            .context("invalid signature")?;
+        Ok(())
+    }
+}
+
 /// A rolling in-memory storage for the most recent light client state signatures.
+///
+/// Each signature is stored along with the header of the block whose state was signed.
 #[derive(Debug, Default)]
 pub struct StateSignatureMemStorage {
-    pool: HashMap<u64, StateSignatureRequestBody>,
+    pool: HashMap<u64, (StateSignatureRequestBody, Header)>,
     deque: VecDeque<u64>,
 }
 
 impl StateSignatureMemStorage {
-    pub fn push(&mut self, height: u64, signature: StateSignatureRequestBody) {
-        self.pool.insert(height, signature);
+    pub fn push(&mut self, height: u64, signature: StateSignatureRequestBody, header: Header) {
+        self.pool.insert(height, (signature, header));
         self.deque.push_back(height);
         if self.pool.len() > SIGNATURE_STORAGE_CAPACITY {
             self.pool.remove(&self.deque.pop_front().unwrap());
@@ -184,8 +385,18 @@ impl StateSignatureMemStorage {
     }
 
     pub fn get_signature(&self, height: u64) -> Option<StateSignatureRequestBody> {
+        self.pool.get(&height).map(|(signature, _)| signature.clone())
+    }
+
+    /// Get the signature at `height` along with the header of the signed block.
+    pub fn get(&self, height: u64) -> Option<(StateSignatureRequestBody, Header)> {
         self.pool.get(&height).cloned()
     }
+
+    /// The height of the most recently signed state.
+    pub fn latest_height(&self) -> Option<u64> {
+        self.deque.back().copied()
+    }
 }
 
 /// Type for stake table commitment
@@ -196,6 +407,16 @@ pub fn static_stake_table_commitment(
     known_nodes_with_stakes: &[PeerConfig<BLSPubKey>],
     capacity: usize,
 ) -> (CircuitField, CircuitField, CircuitField) {
+    let st = static_stake_table(known_nodes_with_stakes, capacity);
+    // This `unwrap()` won't fail
+    st.commitment(SnapshotVersion::LastEpochStart).unwrap()
+}
+
+/// Helper function to build the static stake table from the known nodes.
+pub fn static_stake_table(
+    known_nodes_with_stakes: &[PeerConfig<BLSPubKey>],
+    capacity: usize,
+) -> StakeTable<BLSPubKey, StateVerKey, CircuitField> {
     let mut st = StakeTable::<BLSPubKey, StateVerKey, CircuitField>::new(capacity);
     known_nodes_with_stakes.iter().for_each(|peer| {
         // This `unwrap()` won't fail unless number of entries exceeds `capacity`
@@ -208,6 +429,128 @@ pub fn static_stake_table_commitment(
     });
     st.advance();
     st.advance();
-    // This `unwrap()` won't fail
-    st.commitment(SnapshotVersion::LastEpochStart).unwrap()
+    st
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::NodeState;
+    use async_std::task::spawn;
+    use es_version::SequencerVersion;
+    use futures::FutureExt;
+    use portpicker::pick_unused_port;
+    use tide_disco::{App, Error as _, StatusCode};
+    use toml::toml;
+
+    /// Start a mock signing service which signs with `key`, or fails every request if `key` is
+    /// [`None`].
+    async fn mock_signer(key: Option<StateKeyPair>) -> Url {
+        let port = pick_unused_port().unwrap();
+        let mut app = App::<_, ServerError>::with_state(());
+        let api = toml! {
+            [route.sign]
+            PATH = ["sign"]
+            METHOD = "POST"
+        };
+        app.module::<ServerError, SequencerVersion>("signer", api)
+            .unwrap()
+            .post("sign", move |req, _state| {
+                let key = key.clone();
+                async move {
+                    let state = req
+                        .body_auto::<LightClientState, SequencerVersion>(
+                            SequencerVersion::instance(),
+                        )
+                        .map_err(ServerError::from_request_error)?;
+                    let Some(key) = key else {
+                        return Err(ServerError::catch_all(
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            "signing key unavailable".into(),
+                        ));
+                    };
+                    StateSignBackend::sign(&key, &state).await.map_err(|err| {
+                        ServerError::catch_all(StatusCode::BAD_REQUEST, err.to_string())
+                    })
+                }
+                .boxed()
+            })
+            .unwrap();
+        spawn(app.serve(format!("0.0.0.0:{port}"), SequencerVersion::instance()));
+
+        let url: Url = format!("http://localhost:{port}").parse().unwrap();
+        Client::<ServerError, SequencerVersion>::new(url.clone())
+            .connect(None)
+            .await;
+        url.join("signer/").unwrap()
+    }
+
+    async fn light_client_state() -> (LightClientState, Header) {
+        let instance = NodeState::mock();
+        let leaf = Leaf::genesis(&instance.genesis_state, &instance).await;
+        let state = form_light_client_state(&leaf, &Default::default()).unwrap();
+        (state, leaf.block_header().clone())
+    }
+
+    #[async_std::test]
+    async fn test_remote_state_signer() {
+        let key = StateKeyPair::generate_from_seed_indexed([0; 32], 0);
+        let url = mock_signer(Some(key.clone())).await;
+        let (state, header) = light_client_state().await;
+
+        let remote = RemoteStateSigner::<SequencerVersion>::new(url, key.ver_key());
+        let signature = remote.sign(&state).await.unwrap();
+        let msg: [CircuitField; 7] = (&state).into();
+        StateSignatureScheme::verify(&(), &key.ver_key(), msg, &signature).unwrap();
+
+        // The remote backend can be used in place of a local key to sign and store states.
+        let signer = StateSigner::<SequencerVersion, _>::new(remote, Default::default());
+        let signature = signer.sign_new_state(&state, &header).await.unwrap();
+        let stored = signer
+            .get_state_signature(state.block_height as u64)
+            .await
+            .unwrap();
+        assert_eq!(stored.key, key.ver_key());
+        assert_eq!(stored.state, state);
+        assert_eq!(stored.signature, signature);
+    }
+
+    #[async_std::test]
+    async fn test_remote_state_signer_wrong_key() {
+        let key = StateKeyPair::generate_from_seed_indexed([0; 32], 0);
+        let other = StateKeyPair::generate_from_seed_indexed([0; 32], 1);
+        let url = mock_signer(Some(other)).await;
+        let (state, header) = light_client_state().await;
+
+        // A signature under a key other than the one we expect is rejected.
+        let remote = RemoteStateSigner::<SequencerVersion>::new(url, key.ver_key());
+        let err = remote.sign(&state).await.unwrap_err();
+        assert!(format!("{err:#}").contains("invalid signature"), "{err:#}");
+
+        // Nothing is stored for a state which could not be signed.
+        let signer = StateSigner::<SequencerVersion, _>::new(remote, Default::default());
+        signer.sign_new_state(&state, &header).await.unwrap_err();
+        assert!(signer
+            .get_state_signature(state.block_height as u64)
+            .await
+            .is_none());
+    }
+
+    #[async_std::test]
+    async fn test_remote_state_signer_error() {
+        let key = StateKeyPair::generate_from_seed_indexed([0; 32], 0);
+        let url = mock_signer(None).await;
+        let (state, header) = light_client_state().await;
+
+        let remote = RemoteStateSigner::<SequencerVersion>::new(url, key.ver_key());
+        let err = remote.sign(&state).await.unwrap_err();
+        assert!(format!("{err:#}").contains("signing key unavailable"), "{err:#}");
+
+        let signer = StateSigner::<SequencerVersion, _>::new(remote, Default::default());
+        signer.sign_new_state(&state, &header).await.unwrap_err();
+        assert!(signer
+            .get_state_signature(state.block_height as u64)
+            .await
+            .is_none());
+    }
 }
@@ -1,8 +1,13 @@
+use crate::state::{FeeAccount, FeeAmount};
+use anyhow::ensure;
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use committable::{Commitment, Committable};
 use derive_more::Display;
+use ethers::types::Signature;
 use hotshot_query_service::explorer::ExplorerTransaction;
-use hotshot_types::traits::block_contents::Transaction as HotShotTransaction;
+use hotshot_types::traits::{
+    block_contents::Transaction as HotShotTransaction, signature_key::BuilderSignatureKey,
+};
 use jf_merkle_tree::namespaced_merkle_tree::{Namespace, Namespaced};
 use serde::{de::Error, Deserialize, Deserializer, Serialize};
 
@@ -145,6 +150,100 @@ impl Transaction {
     }
 }
 
+/// A transaction as submitted to a node, along with the fee its submitter offers to pay.
+///
+/// The fee is metadata about the submission, checked by the node which receives it. It is not part
+/// of the [`Transaction`], so it is not sequenced, committed to, or forwarded to other nodes.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TransactionSubmission {
+    transaction: Transaction,
+    fee: Option<FeeAmount>,
+}
+
+impl TransactionSubmission {
+    /// Submit `transaction`, offering to pay `fee`.
+    pub fn new(transaction: Transaction, fee: FeeAmount) -> Self {
+        Self {
+            transaction,
+            fee: Some(fee),
+        }
+    }
+
+    pub fn transaction(&self) -> &Transaction {
+        &self.transaction
+    }
+
+    pub fn into_transaction(self) -> Transaction {
+        self.transaction
+    }
+
+    /// The fee offered by the submitter, if specified.
+    pub fn fee(&self) -> Option<FeeAmount> {
+        self.fee
+    }
+}
+
+impl From<Transaction> for TransactionSubmission {
+    fn from(transaction: Transaction) -> Self {
+        Self {
+            transaction,
+            fee: None,
+        }
+    }
+}
+
+/// An application-level check applied to transactions before this node accepts them.
+///
+/// The sequencer does not interpret transaction payloads, so by default it does no verification. A
+/// deployment which knows the format of the transactions it sequences (for example, that each
+/// carries a signature over its contents) can install a verifier with
+/// [`NodeState::with_tx_verifier`](crate::NodeState::with_tx_verifier) to reject malformed or
+/// improperly signed transactions at the edge, before they are submitted to consensus. A node
+/// verifies signed transactions with [`SignedTxVerifier`] if its genesis file lists
+/// [`tx_signers`](crate::Genesis::tx_signers).
+pub trait TxVerifier: Send + Sync + std::fmt::Debug {
+    /// Check `tx`, returning an error describing why it is invalid if it should be rejected.
+    fn verify(&self, tx: &Transaction) -> anyhow::Result<()>;
+}
+
+/// A [`TxVerifier`] accepting only transactions signed by one of a set of accounts.
+///
+/// The payload of a signed transaction is an Ethereum signature of
+/// [`SIGNATURE_BYTE_LEN`](Self::SIGNATURE_BYTE_LEN) bytes, followed by the signed data.
+#[derive(Clone, Debug)]
+pub struct SignedTxVerifier {
+    signers: Vec<FeeAccount>,
+}
+
+impl SignedTxVerifier {
+    /// Byte length of the signature at the start of a signed transaction payload.
+    pub const SIGNATURE_BYTE_LEN: usize = 65;
+
+    pub fn new(signers: impl IntoIterator<Item = FeeAccount>) -> Self {
+        Self {
+            signers: signers.into_iter().collect(),
+        }
+    }
+}
+
+impl TxVerifier for SignedTxVerifier {
+    fn verify(&self, tx: &Transaction) -> anyhow::Result<()> {
+        ensure!(
+            tx.payload().len() >= Self::SIGNATURE_BYTE_LEN,
+            "transaction is too short to be signed"
+        );
+        let (sig, data) = tx.payload().split_at(Self::SIGNATURE_BYTE_LEN);
+        let sig = Signature::try_from(sig)?;
+        ensure!(
+            self.signers
+                .iter()
+                .any(|signer| signer.validate_builder_signature(&sig, data)),
+            "transaction is not signed by an accepted signer"
+        );
+        Ok(())
+    }
+}
+
 impl HotShotTransaction for Transaction {}
 
 // TODO seems that `Namespaced` is unneeded.